@@ -0,0 +1,61 @@
+// Generate the day modules and the puzzle registry from the files in src/days, so that adding a day
+// only means adding its file. Each day file registers itself with 'register_puzzle!'. The 2020 days
+// are directly in src/days, later years in a directory per year, e.g. src/days/2021/day1.rs.
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// Get the day files in 'dir' as (day, path) pairs, ordered by day.
+fn day_files(dir: &Path) -> Vec<(u8, String)> {
+    let mut days = vec![];
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        if let Some(day) = name.strip_prefix("day").and_then(|n| n.parse::<u8>().ok()) {
+            days.push((day, path.display().to_string()));
+        }
+    }
+    days.sort();
+    days
+}
+
+fn main() {
+    let dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("src/days");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    let mut years = vec![];
+    for entry in fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        if let (true, Ok(year)) = (path.is_dir(), name.parse::<u16>()) {
+            println!("cargo:rerun-if-changed={}", path.display());
+            years.push((year, day_files(&path)));
+        }
+    }
+    years.sort();
+
+    let mut code = String::new();
+    let mut modules = vec![];
+    for (day, path) in day_files(&dir) {
+        code += &format!("#[path = {:?}]\npub mod day{};\n", path, day);
+        modules.push(format!("day{}", day));
+    }
+    for (year, days) in &years {
+        code += &format!("\n/// The puzzles of {}.\npub mod y{} {{\n", year, year);
+        for (day, path) in days {
+            code += &format!("    #[path = {:?}]\n    pub mod day{};\n", path, day);
+            modules.push(format!("y{}::day{}", year, day));
+        }
+        code += "}\n";
+    }
+    code += "\n/// All registered puzzles.\n";
+    code += "pub static REGISTRY: &[Registration] = &[\n";
+    for module in &modules {
+        code += &format!("    {}::REGISTRATION,\n", module);
+    }
+    code += "];\n";
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("days.rs");
+    fs::write(out, code).unwrap();
+}
@@ -0,0 +1,401 @@
+//! A life-like cellular automaton, as used by the Day 11 seating system, the Day 17 Conway cubes
+//! and the Day 24 lobby floor. The state is the set of live cells. Each generation a cell lives
+//! or dies depending on its number of live neighbours, as given by a birth/survival [`Rule`].
+//! Which cells exist and which are neighbours is up to the [`Topology`].
+//!
+//! Running generations gives up with [`PuzzleError::Timeout`] once the automaton's [`Cancel`]
+//! token is cancelled, see [`Automaton::cancellable`].
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+
+use crate::cancel::Cancel;
+use crate::grid::Grid;
+use crate::puzzle::PuzzleError;
+
+/// A set of live cells.
+pub type Cells<C> = HashSet<C, BuildHasherDefault<CellHasher>>;
+
+/// The cells of an automaton and how they neighbour each other. Neighbourhoods must be
+/// symmetric: if a is a neighbour of b, b is a neighbour of a.
+pub trait Topology {
+    type Cell: Clone + Eq + Hash;
+
+    /// Call 'f' with each neighbour of 'cell'.
+    fn neighbours(&self, cell: &Self::Cell, f: &mut dyn FnMut(Self::Cell));
+
+    /// All cells of a bounded topology, or None if it is unbounded. In an unbounded topology only
+    /// the cells next to a live cell can be born.
+    fn cells(&self) -> Option<&[Self::Cell]>;
+}
+
+/// A bounded grid where only some of the positions are cells, with the neighbours of each cell
+/// worked out up front. Cells are (x, y) positions in the grid.
+#[derive(Debug, Clone)]
+pub struct BoundedGrid {
+    cells: Vec<(usize, usize)>,
+    neighbours: Grid<Vec<(usize, usize)>>,
+}
+
+impl BoundedGrid {
+    /// Make a cell of each position of 'grid' for which 'is_cell' holds. 'neighbours' gives the
+    /// neighbouring cells of a cell.
+    pub fn new<T, F, N>(grid: &Grid<T>, is_cell: F, neighbours: N) -> BoundedGrid
+    where
+        F: Fn(&T) -> bool,
+        N: Fn(usize, usize) -> Vec<(usize, usize)>,
+    {
+        let mut cells = vec![];
+        let mut all_neighbours = Grid::new(grid.width(), grid.height(), vec![]);
+        for ((x, y), value) in grid.iter() {
+            if is_cell(value) {
+                cells.push((x, y));
+                all_neighbours[(x, y)] = neighbours(x, y);
+            }
+        }
+        BoundedGrid {
+            cells,
+            neighbours: all_neighbours,
+        }
+    }
+}
+
+impl Topology for BoundedGrid {
+    type Cell = (usize, usize);
+
+    fn neighbours(&self, cell: &(usize, usize), f: &mut dyn FnMut((usize, usize))) {
+        for neighbour in &self.neighbours[*cell] {
+            f(*neighbour);
+        }
+    }
+
+    fn cells(&self) -> Option<&[(usize, usize)]> {
+        Some(&self.cells)
+    }
+}
+
+/// The unbounded N-dimensional integer lattice, where each cell has the 3^N - 1 cells around it
+/// as neighbours.
+#[derive(Debug, Clone)]
+pub struct Lattice<const N: usize> {
+    offsets: Vec<[i64; N]>,
+}
+
+impl<const N: usize> Default for Lattice<N> {
+    fn default() -> Self {
+        let mut offsets = vec![[0; N]];
+        for dimension in 0..N {
+            offsets = offsets
+                .iter()
+                .flat_map(|offset| {
+                    (-1..=1).map(move |d| {
+                        let mut offset = *offset;
+                        offset[dimension] = d;
+                        offset
+                    })
+                })
+                .collect();
+        }
+        offsets.retain(|offset| offset.iter().any(|d| *d != 0));
+        Lattice { offsets }
+    }
+}
+
+impl<const N: usize> Topology for Lattice<N> {
+    type Cell = [i64; N];
+
+    fn neighbours(&self, cell: &[i64; N], f: &mut dyn FnMut([i64; N])) {
+        for offset in &self.offsets {
+            let mut neighbour = *cell;
+            for (c, d) in neighbour.iter_mut().zip(offset) {
+                *c += d;
+            }
+            f(neighbour);
+        }
+    }
+
+    fn cells(&self) -> Option<&[[i64; N]]> {
+        None
+    }
+}
+
+/// Axial offsets of the 6 neighbours on the hex lattice, in the order east, south-east,
+/// south-west, west, north-west and north-east. The rows of hexagons run east to west.
+pub const HEX_DIRECTIONS: [(i64, i64); 6] = [(1, 0), (0, 1), (-1, 1), (-1, 0), (0, -1), (1, -1)];
+
+/// The unbounded lattice of hexagons, addressed with axial (q, r) coordinates.
+#[derive(Debug, Clone, Default)]
+pub struct HexLattice;
+
+impl Topology for HexLattice {
+    type Cell = (i64, i64);
+
+    fn neighbours(&self, cell: &(i64, i64), f: &mut dyn FnMut((i64, i64))) {
+        for (dq, dr) in &HEX_DIRECTIONS {
+            f((cell.0 + dq, cell.1 + dr));
+        }
+    }
+
+    fn cells(&self) -> Option<&[(i64, i64)]> {
+        None
+    }
+}
+
+/// Which cells are live in the next generation: a dead cell is born if its number of live
+/// neighbours is one of 'birth', a live cell survives if it is one of 'survival'.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    birth: Vec<usize>,
+    survival: Vec<usize>,
+}
+
+impl Rule {
+    pub fn new(birth: &[usize], survival: &[usize]) -> Rule {
+        Rule {
+            birth: birth.to_vec(),
+            survival: survival.to_vec(),
+        }
+    }
+
+    pub fn is_live(&self, live: bool, live_neighbours: usize) -> bool {
+        if live {
+            self.survival.contains(&live_neighbours)
+        } else {
+            self.birth.contains(&live_neighbours)
+        }
+    }
+}
+
+/// Where the generations start repeating: generation 'start' comes back every 'length'
+/// generations. 'live' holds the live cells of generation 'start'.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cycle<C: Eq + Hash> {
+    pub start: usize,
+    pub length: usize,
+    pub live: Cells<C>,
+}
+
+pub struct Automaton<T: Topology> {
+    pub topology: T,
+    pub rule: Rule,
+    pub cancel: Cancel,
+}
+
+impl<T: Topology> Automaton<T> {
+    pub fn new(topology: T, rule: Rule) -> Self {
+        Automaton {
+            topology,
+            rule,
+            cancel: Cancel::default(),
+        }
+    }
+
+    /// Check 'cancel' before every generation.
+    pub fn cancellable(mut self, cancel: &Cancel) -> Self {
+        self.cancel = cancel.clone();
+        self
+    }
+
+    /// The live cells of the generation after 'live'.
+    pub fn step(&self, live: &Cells<T::Cell>) -> Cells<T::Cell> {
+        let mut counts: HashMap<T::Cell, usize, BuildHasherDefault<CellHasher>> =
+            HashMap::default();
+        for cell in live {
+            self.topology.neighbours(cell, &mut |neighbour| {
+                *counts.entry(neighbour).or_insert(0) += 1
+            });
+        }
+        let count = |cell: &T::Cell| counts.get(cell).copied().unwrap_or(0);
+        match self.topology.cells() {
+            Some(cells) => cells
+                .iter()
+                .filter(|cell| self.rule.is_live(live.contains(cell), count(cell)))
+                .cloned()
+                .collect(),
+            None => counts
+                .keys()
+                .chain(live.iter().filter(|cell| !counts.contains_key(cell)))
+                .filter(|cell| self.rule.is_live(live.contains(cell), count(cell)))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// The live cells after 'generations' generations.
+    pub fn run(
+        &self,
+        mut live: Cells<T::Cell>,
+        generations: usize,
+    ) -> Result<Cells<T::Cell>, PuzzleError> {
+        for _ in 0..generations {
+            self.cancel.check()?;
+            live = self.step(&live);
+        }
+        Ok(live)
+    }
+
+    /// Run until a generation repeats an earlier one. This never returns if the live cells keep
+    /// spreading out over an unbounded topology.
+    pub fn run_until_repeat(&self, live: Cells<T::Cell>) -> Result<Cycle<T::Cell>, PuzzleError> {
+        // Only a fingerprint of each generation is kept. A generation with the fingerprint of an
+        // earlier one is compared with that generation, which is computed again unless it is the
+        // previous one.
+        let initial = live.clone();
+        let mut seen: HashMap<u64, usize> = HashMap::new();
+        let mut previous = Cells::default();
+        let mut live = live;
+        for generation in 0.. {
+            self.cancel.check()?;
+            let key = fingerprint(&live);
+            if let Some(start) = seen.get(&key).copied() {
+                let repeated = if start + 1 == generation {
+                    previous == live
+                } else {
+                    self.run(initial.clone(), start)? == live
+                };
+                if repeated {
+                    return Ok(Cycle {
+                        start,
+                        length: generation - start,
+                        live,
+                    });
+                }
+            }
+            seen.insert(key, generation);
+            let next = self.step(&live);
+            previous = std::mem::replace(&mut live, next);
+        }
+        unreachable!()
+    }
+
+    /// Run until nothing changes any more and return the live cells, or None if the generations
+    /// end up in a longer cycle instead.
+    pub fn run_to_fixpoint(
+        &self,
+        live: Cells<T::Cell>,
+    ) -> Result<Option<Cells<T::Cell>>, PuzzleError> {
+        let cycle = self.run_until_repeat(live)?;
+        if cycle.length == 1 {
+            Ok(Some(cycle.live))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// A fast hasher for cells, which are small and not chosen by an attacker. This is the multiply and
+/// rotate step of the hash used by rustc.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CellHasher(u64);
+
+impl Hasher for CellHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u64(u64::from(*byte));
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = (self.0.rotate_left(5) ^ n).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_i64(&mut self, n: i64) {
+        self.write_u64(n as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// A fingerprint of the live cells that does not depend on the order in which they are stored.
+fn fingerprint<C: Hash>(live: &Cells<C>) -> u64 {
+    live.iter().fold(0, |sum: u64, cell| {
+        let mut hasher = DefaultHasher::new();
+        cell.hash(&mut hasher);
+        sum.wrapping_add(hasher.finish())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lattice_neighbours() {
+        let mut neighbours = vec![];
+        Lattice::<2>::default().neighbours(&[0, 0], &mut |cell| neighbours.push(cell));
+        neighbours.sort_unstable();
+        assert_eq!(
+            neighbours,
+            vec![
+                [-1, -1],
+                [-1, 0],
+                [-1, 1],
+                [0, -1],
+                [0, 1],
+                [1, -1],
+                [1, 0],
+                [1, 1]
+            ]
+        );
+        assert_eq!(Lattice::<4>::default().offsets.len(), 80);
+    }
+
+    #[test]
+    fn game_of_life() {
+        let life = Automaton::new(Lattice::<2>::default(), Rule::new(&[3], &[2, 3]));
+        let blinker: Cells<[i64; 2]> = [[0, 0], [1, 0], [2, 0]].iter().cloned().collect();
+        let turned: Cells<[i64; 2]> = [[1, -1], [1, 0], [1, 1]].iter().cloned().collect();
+        assert_eq!(life.step(&blinker), turned);
+        assert_eq!(life.run(blinker.clone(), 2), Ok(blinker.clone()));
+        let cycle = life.run_until_repeat(blinker.clone()).unwrap();
+        assert_eq!((cycle.start, cycle.length), (0, 2));
+        assert_eq!(life.run_to_fixpoint(blinker), Ok(None));
+
+        let block: Cells<[i64; 2]> = [[0, 0], [0, 1], [1, 0], [1, 1]].iter().cloned().collect();
+        assert_eq!(life.run_to_fixpoint(block.clone()), Ok(Some(block.clone())));
+
+        let cancel = Cancel::new();
+        cancel.cancel();
+        let life = life.cancellable(&cancel);
+        assert_eq!(life.run(block.clone(), 1), Err(PuzzleError::Timeout));
+        assert_eq!(life.run(block.clone(), 0), Ok(block));
+    }
+
+    #[test]
+    fn bounded_grid() {
+        // Cells are born without live neighbours and never survive, so the generations alternate
+        // between all cells live and none. The '#' position is not a cell.
+        let grid = Grid::from_rows(vec![vec!['.', '.', '#', '.']]).unwrap();
+        let topology = BoundedGrid::new(
+            &grid,
+            |c| *c == '.',
+            |x, y| {
+                grid.neighbours4(x, y)
+                    .filter(|(_, c)| **c == '.')
+                    .map(|(pos, _)| pos)
+                    .collect()
+            },
+        );
+        let automaton = Automaton::new(topology, Rule::new(&[0], &[]));
+        let live = automaton.step(&Cells::default());
+        assert_eq!(live.len(), 3);
+        assert!(automaton.step(&live).is_empty());
+        let cycle = automaton.run_until_repeat(Cells::default()).unwrap();
+        assert_eq!((cycle.start, cycle.length), (0, 2));
+    }
+
+    #[test]
+    fn hex_lattice() {
+        let automaton = Automaton::new(HexLattice, Rule::new(&[2], &[1, 2]));
+        let live: Cells<(i64, i64)> = [(0, 0), (1, 0)].iter().cloned().collect();
+        // The two cells next to both of them are born.
+        assert_eq!(automaton.step(&live).len(), 4);
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use crate::cli::{BenchOptions, Format};
+use crate::days::PuzzleId;
+use crate::json::Value;
+use crate::puzzle::Context;
+use crate::report;
+use crate::runner::{self, DayResult, Input};
+
+/// Exit code used when a day is significantly slower than in the baseline.
+pub const SLOWDOWN_EXIT_CODE: i32 = 5;
+
+// A day is flagged as slower than the baseline if its mean time grew by more than this fraction and
+// Welch's t-test says the difference is significant.
+const SLOWDOWN_THRESHOLD: f64 = 0.1;
+const T_CRITICAL: f64 = 2.0;
+
+/// Timing statistics for a number of runs. All times are in nanoseconds.
+#[derive(Debug, PartialEq)]
+pub struct Stats {
+    pub runs: usize,
+    pub min: f64,
+    pub median: f64,
+    pub mean: f64,
+    pub p95: f64,
+    pub stddev: f64,
+}
+
+impl Stats {
+    pub fn from_samples(samples: &[Duration]) -> Stats {
+        let mut nanos: Vec<f64> = samples.iter().map(|s| s.as_nanos() as f64).collect();
+        nanos.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let runs = nanos.len();
+        let mean = nanos.iter().sum::<f64>() / runs as f64;
+        let variance = if runs > 1 {
+            nanos.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (runs - 1) as f64
+        } else {
+            0.0
+        };
+        let median = if runs.is_multiple_of(2) {
+            (nanos[runs / 2 - 1] + nanos[runs / 2]) / 2.0
+        } else {
+            nanos[runs / 2]
+        };
+        // Nearest-rank percentile.
+        let p95 = nanos[((0.95 * runs as f64).ceil() as usize).max(1) - 1];
+        Stats {
+            runs,
+            min: nanos[0],
+            median,
+            mean,
+            p95,
+            stddev: variance.sqrt(),
+        }
+    }
+
+    /// True if these runs are significantly slower than the 'baseline' runs.
+    pub fn is_slowdown(&self, baseline: &Stats) -> bool {
+        if self.mean <= baseline.mean * (1.0 + SLOWDOWN_THRESHOLD) {
+            return false;
+        }
+        let std_error = (self.stddev.powi(2) / self.runs as f64
+            + baseline.stddev.powi(2) / baseline.runs as f64)
+            .sqrt();
+        // Without any spread there is nothing to test, the threshold alone decides.
+        std_error == 0.0 || (self.mean - baseline.mean) / std_error > T_CRITICAL
+    }
+}
+
+pub fn format_nanos(nanos: f64) -> String {
+    if nanos < 1e3 {
+        format!("{:.0} ns", nanos)
+    } else if nanos < 1e6 {
+        format!("{:.1} us", nanos / 1e3)
+    } else if nanos < 1e9 {
+        format!("{:.2} ms", nanos / 1e6)
+    } else {
+        format!("{:.2} s", nanos / 1e9)
+    }
+}
+
+// Time a single run of the selected parts of 'id', including parsing. A failed run is returned as
+// is.
+fn run_once(
+    id: PuzzleId,
+    parts: &[u8],
+    input: &Input,
+    context: &Context,
+    timeout: Option<Duration>,
+) -> Result<Duration, Box<DayResult>> {
+    let result = runner::run_day(id, parts, input, context, None, timeout).unwrap();
+    match result.error() {
+        Some(_) => Err(Box::new(result)),
+        None => Ok(result.elapsed()),
+    }
+}
+
+fn bench_day(
+    id: PuzzleId,
+    parts: &[u8],
+    input: &Input,
+    context: &Context,
+    timeout: Option<Duration>,
+    options: &BenchOptions,
+) -> Result<Stats, Box<DayResult>> {
+    for _ in 0..options.warmup {
+        run_once(id, parts, input, context, timeout)?;
+    }
+    let mut samples = vec![];
+    let start = Instant::now();
+    loop {
+        samples.push(run_once(id, parts, input, context, timeout)?);
+        let done = match options.runs {
+            Some(runs) => samples.len() >= runs,
+            None => start.elapsed() >= options.budget,
+        };
+        if done {
+            return Ok(Stats::from_samples(&samples));
+        }
+    }
+}
+
+// The baseline file has a comment header followed by one line per day:
+// year day runs min median mean p95 stddev
+fn format_baseline(stats: &[(PuzzleId, &Stats)]) -> String {
+    let mut text = "# year day runs min median mean p95 stddev (nanoseconds)\n".to_string();
+    for (id, s) in stats {
+        text += &format!(
+            "{} {} {} {:.0} {:.0} {:.0} {:.0} {:.0}\n",
+            id.year, id.day, s.runs, s.min, s.median, s.mean, s.p95, s.stddev
+        );
+    }
+    text
+}
+
+fn parse_baseline(text: &str) -> Result<HashMap<PuzzleId, Stats>, String> {
+    let mut baseline = HashMap::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = || format!("Malformed baseline at line {}: '{}'", i + 1, line);
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 8 {
+            return Err(error());
+        }
+        let year = fields[0].parse::<u16>().map_err(|_| error())?;
+        let day = fields[1].parse::<u8>().map_err(|_| error())?;
+        let runs = fields[2].parse::<usize>().map_err(|_| error())?;
+        let mut times = vec![];
+        for field in &fields[3..] {
+            times.push(field.parse::<f64>().map_err(|_| error())?);
+        }
+        baseline.insert(
+            PuzzleId::new(year, day),
+            Stats {
+                runs,
+                min: times[0],
+                median: times[1],
+                mean: times[2],
+                p95: times[3],
+                stddev: times[4],
+            },
+        );
+    }
+    Ok(baseline)
+}
+
+// Columns of the machine-readable output, one record per day. 'part' is empty if both parts were
+// run, times are in nanoseconds and the baseline columns are empty without '--compare'.
+const COLUMNS: [&str; 15] = [
+    "year",
+    "day",
+    "part",
+    "status",
+    "error",
+    "runs",
+    "min_ns",
+    "median_ns",
+    "mean_ns",
+    "p95_ns",
+    "stddev_ns",
+    "baseline_mean_ns",
+    "change_percent",
+    "slower",
+    "input",
+];
+
+fn record(
+    id: PuzzleId,
+    parts: &[u8],
+    input: &Input,
+    outcome: &Result<Stats, Box<DayResult>>,
+    old: Option<&Stats>,
+) -> Vec<Value> {
+    let part = if parts.len() == 1 {
+        Value::Int(parts[0] as i64)
+    } else {
+        Value::Null
+    };
+    let mut record = vec![Value::Int(id.year as i64), Value::Int(id.day as i64), part];
+    match outcome {
+        Ok(stats) => {
+            record.push(Value::from("ok"));
+            record.push(Value::Null);
+            record.push(Value::Int(stats.runs as i64));
+            for nanos in &[stats.min, stats.median, stats.mean, stats.p95, stats.stddev] {
+                record.push(Value::Float(nanos.round()));
+            }
+            match old {
+                Some(old) => {
+                    record.push(Value::Float(old.mean.round()));
+                    record.push(Value::Float(change(stats, old)));
+                    record.push(Value::Bool(stats.is_slowdown(old)));
+                }
+                None => record.append(&mut vec![Value::Null; 3]),
+            }
+        }
+        Err(result) => {
+            if result.is_skipped() {
+                record.push(Value::from("skipped"));
+                record.push(Value::Null);
+            } else {
+                record.push(Value::from("error"));
+                record.push(Value::from(result.error().unwrap().to_string()));
+            }
+            record.append(&mut vec![Value::Null; 9]);
+        }
+    }
+    record.push(Value::from(input.path(id)));
+    record
+}
+
+// Change of the mean time in percent.
+fn change(stats: &Stats, old: &Stats) -> f64 {
+    (1000.0 * (stats.mean - old.mean) / old.mean).round() / 10.0
+}
+
+fn row(label: String, outcome: &Result<Stats, Box<DayResult>>, old: Option<&Stats>) -> Vec<String> {
+    let mut row = vec![label];
+    match outcome {
+        Ok(stats) => {
+            row.push(stats.runs.to_string());
+            for nanos in &[stats.min, stats.median, stats.mean, stats.p95, stats.stddev] {
+                row.push(format_nanos(*nanos));
+            }
+            if let Some(old) = old {
+                row.push(format_nanos(old.mean));
+                if stats.is_slowdown(old) {
+                    row.push(format!("{:+.1}% SLOWER", change(stats, old)));
+                } else {
+                    row.push(format!("{:+.1}%", change(stats, old)));
+                }
+            }
+        }
+        Err(result) if result.is_skipped() => row.push("skipped".to_string()),
+        Err(result) => row.push(format!("error: {}", result.error().unwrap())),
+    }
+    row
+}
+
+/// Benchmark the selected days, print the statistics and optionally save them to, or compare them
+/// with, a baseline file. Returns the exit code.
+pub fn run(
+    puzzles: &[PuzzleId],
+    parts: &[u8],
+    input: &Input,
+    context: &Context,
+    timeout: Option<Duration>,
+    options: &BenchOptions,
+    format: Format,
+) -> Result<i32, String> {
+    let baseline = match &options.compare {
+        Some(path) => {
+            let text = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read baseline '{}': {}", path, e))?;
+            Some(parse_baseline(&text)?)
+        }
+        None => None,
+    };
+
+    let mut header = vec!["Day", "Runs", "Min", "Median", "Mean", "P95", "Stddev"];
+    if baseline.is_some() {
+        header.append(&mut vec!["Baseline", "Change"]);
+    }
+    let mut rows = vec![header
+        .iter()
+        .map(|h| h.to_string())
+        .collect::<Vec<String>>()];
+    let mut records = vec![];
+    let mut results = vec![];
+    let mut exit_code = 0;
+    let mut nof_slowdowns = 0;
+    for id in puzzles {
+        let outcome = bench_day(*id, parts, input, context, timeout, options);
+        let old = baseline.as_ref().and_then(|b| b.get(id));
+        let label = runner::day_label(*id, runner::several_years(puzzles));
+        let mut row = row(label, &outcome, old);
+        row.resize(rows[0].len(), String::new());
+        rows.push(row);
+        records.push(record(*id, parts, input, &outcome, old));
+        match outcome {
+            Ok(stats) => {
+                if old.is_some_and(|old| stats.is_slowdown(old)) {
+                    nof_slowdowns += 1;
+                }
+                results.push((*id, stats));
+            }
+            Err(result) if result.is_skipped() => (),
+            Err(result) => {
+                if exit_code == 0 {
+                    exit_code = result.error().unwrap().exit_code();
+                }
+            }
+        }
+    }
+    // Machine-readable output keeps stdout clean, so any remarks go to stderr.
+    let remark = |text: String| {
+        if format == Format::Text {
+            println!("{}", text);
+        } else {
+            eprintln!("{}", text);
+        }
+    };
+    if format == Format::Text {
+        runner::print_rows(&rows, &[]);
+    } else {
+        report::print_records(&COLUMNS, &records, format);
+    }
+
+    if let Some(path) = &options.save {
+        let stats: Vec<(PuzzleId, &Stats)> = results.iter().map(|(id, s)| (*id, s)).collect();
+        fs::write(path, format_baseline(&stats))
+            .map_err(|e| format!("Failed to write baseline '{}': {}", path, e))?;
+        remark(format!("Baseline saved to {}", path));
+    }
+    if nof_slowdowns > 0 {
+        remark(format!(
+            "{} day(s) significantly slower than the baseline",
+            nof_slowdowns
+        ));
+        if exit_code == 0 {
+            exit_code = SLOWDOWN_EXIT_CODE;
+        }
+    }
+    Ok(exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn micros(samples: &[u64]) -> Vec<Duration> {
+        samples.iter().map(|s| Duration::from_micros(*s)).collect()
+    }
+
+    #[test]
+    fn statistics() {
+        let stats = Stats::from_samples(&micros(&[4, 1, 3, 2]));
+        assert_eq!(stats.runs, 4);
+        assert_eq!(stats.min, 1000.0);
+        assert_eq!(stats.median, 2500.0);
+        assert_eq!(stats.mean, 2500.0);
+        assert_eq!(stats.p95, 4000.0);
+        assert!((stats.stddev - 1290.99).abs() < 0.01);
+
+        let baseline = Stats::from_samples(&micros(&[100, 101, 99, 100, 102, 98]));
+        let same = Stats::from_samples(&micros(&[101, 100, 99, 102, 100, 98]));
+        let noisy = Stats::from_samples(&micros(&[60, 180, 90, 150, 70, 140]));
+        let slower = Stats::from_samples(&micros(&[120, 121, 119, 120, 122, 118]));
+        assert!(!same.is_slowdown(&baseline));
+        assert!(!noisy.is_slowdown(&baseline));
+        assert!(slower.is_slowdown(&baseline));
+        assert!(!baseline.is_slowdown(&slower));
+    }
+
+    #[test]
+    fn baseline_file() {
+        let stats = Stats::from_samples(&micros(&[10, 20, 30]));
+        let id = PuzzleId::new(2020, 7);
+        let text = format_baseline(&[(id, &stats)]);
+        assert_eq!(
+            text,
+            concat!(
+                "# year day runs min median mean p95 stddev (nanoseconds)\n",
+                "2020 7 3 10000 20000 20000 30000 10000\n"
+            )
+        );
+        assert_eq!(parse_baseline(&text).unwrap().get(&id), Some(&stats));
+        assert!(parse_baseline("2020 7 3 10000").is_err());
+        assert!(parse_baseline("7 3 10000 20000 20000 30000 10000").is_err());
+    }
+}
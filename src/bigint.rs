@@ -0,0 +1,441 @@
+//! Arbitrary-precision integers for the answers that can grow beyond 64 bits, such as the number
+//! of Day 10 adapter arrangements or the Day 18 expression values. Only what the puzzles need is
+//! here: arithmetic, comparison and printing in decimal.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+/// A non-negative integer of any size.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigUint {
+    // Base 2^32 digits, least significant first, without leading zeros. Zero has no digits.
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> BigUint {
+        BigUint { limbs: vec![] }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn from_u128(mut n: u128) -> BigUint {
+        let mut limbs = vec![];
+        while n > 0 {
+            limbs.push(n as u32);
+            n >>= 32;
+        }
+        BigUint { limbs }
+    }
+
+    /// The value as a u128, or None if it does not fit.
+    pub fn to_u128(&self) -> Option<u128> {
+        if self.limbs.len() > 4 {
+            return None;
+        }
+        Some(
+            self.limbs
+                .iter()
+                .rev()
+                .fold(0, |n, limb| n << 32 | u128::from(*limb)),
+        )
+    }
+
+    fn normalize(mut self) -> BigUint {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        self
+    }
+
+    fn bits(&self) -> usize {
+        match self.limbs.last() {
+            Some(top) => self.limbs.len() * 32 - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        self.limbs[i / 32] >> (i % 32) & 1 == 1
+    }
+
+    // Shift left by one bit and set the lowest bit to 'bit'.
+    fn shift_in(&mut self, bit: bool) {
+        let mut carry = u32::from(bit);
+        for limb in self.limbs.iter_mut() {
+            let next = *limb >> 31;
+            *limb = *limb << 1 | carry;
+            carry = next;
+        }
+        if carry != 0 {
+            self.limbs.push(carry);
+        }
+    }
+
+    // Divide by a single digit, returning the quotient and the remainder.
+    fn div_rem_digit(&self, divisor: u32) -> (BigUint, u32) {
+        let mut quotient = vec![0; self.limbs.len()];
+        let mut remainder = 0u64;
+        for (i, limb) in self.limbs.iter().enumerate().rev() {
+            let n = remainder << 32 | u64::from(*limb);
+            quotient[i] = (n / u64::from(divisor)) as u32;
+            remainder = n % u64::from(divisor);
+        }
+        (BigUint { limbs: quotient }.normalize(), remainder as u32)
+    }
+
+    /// The quotient and remainder of dividing by 'divisor'. Panics if 'divisor' is zero.
+    pub fn div_rem(&self, divisor: &BigUint) -> (BigUint, BigUint) {
+        assert!(!divisor.is_zero(), "attempt to divide by zero");
+        if divisor.limbs.len() == 1 {
+            let (quotient, remainder) = self.div_rem_digit(divisor.limbs[0]);
+            return (quotient, BigUint::from(u64::from(remainder)));
+        }
+        // Binary long division, which is plenty fast for numbers of a few hundred bits.
+        let mut quotient = BigUint {
+            limbs: vec![0; self.limbs.len()],
+        };
+        let mut remainder = BigUint::zero();
+        for i in (0..self.bits()).rev() {
+            remainder.shift_in(self.bit(i));
+            if remainder >= *divisor {
+                remainder = &remainder - divisor;
+                quotient.limbs[i / 32] |= 1 << (i % 32);
+            }
+        }
+        (quotient.normalize(), remainder)
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(n: u64) -> Self {
+        BigUint::from_u128(u128::from(n))
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> Add<&'a BigUint> for &'a BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: &BigUint) -> BigUint {
+        let mut limbs = Vec::with_capacity(self.limbs.len().max(other.limbs.len()) + 1);
+        let mut carry = 0u64;
+        for i in 0..self.limbs.len().max(other.limbs.len()) {
+            let sum = u64::from(*self.limbs.get(i).unwrap_or(&0))
+                + u64::from(*other.limbs.get(i).unwrap_or(&0))
+                + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        limbs.push(carry as u32);
+        BigUint { limbs }.normalize()
+    }
+}
+
+impl<'a> Sub<&'a BigUint> for &'a BigUint {
+    type Output = BigUint;
+
+    /// Panics if 'other' is larger than 'self', like the unsigned integers do in debug builds.
+    fn sub(self, other: &BigUint) -> BigUint {
+        assert!(*self >= *other, "attempt to subtract with overflow");
+        let mut limbs = Vec::with_capacity(self.limbs.len());
+        let mut borrow = 0i64;
+        for (i, limb) in self.limbs.iter().enumerate() {
+            let mut difference =
+                i64::from(*limb) - i64::from(*other.limbs.get(i).unwrap_or(&0)) - borrow;
+            borrow = 0;
+            if difference < 0 {
+                difference += 1 << 32;
+                borrow = 1;
+            }
+            limbs.push(difference as u32);
+        }
+        BigUint { limbs }.normalize()
+    }
+}
+
+impl<'a> Mul<&'a BigUint> for &'a BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: &BigUint) -> BigUint {
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in other.limbs.iter().enumerate() {
+                let product = u64::from(*a) * u64::from(*b) + u64::from(limbs[i + j]) + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        BigUint { limbs }.normalize()
+    }
+}
+
+impl<'a> Div<&'a BigUint> for &'a BigUint {
+    type Output = BigUint;
+
+    fn div(self, other: &BigUint) -> BigUint {
+        self.div_rem(other).0
+    }
+}
+
+impl<'a> Rem<&'a BigUint> for &'a BigUint {
+    type Output = BigUint;
+
+    fn rem(self, other: &BigUint) -> BigUint {
+        self.div_rem(other).1
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Split into groups of 9 decimal digits, least significant first.
+        let mut groups = vec![];
+        let mut rest = self.clone();
+        while !rest.is_zero() {
+            let (quotient, group) = rest.div_rem_digit(1_000_000_000);
+            groups.push(group);
+            rest = quotient;
+        }
+        let mut text = groups.pop().unwrap_or(0).to_string();
+        for group in groups.iter().rev() {
+            text += &format!("{:09}", group);
+        }
+        f.pad_integral(true, "", &text)
+    }
+}
+
+/// A signed integer of any size. Division rounds towards zero, as it does for the primitive
+/// integers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: BigUint,
+}
+
+impl BigInt {
+    // Make sure that zero is never negative.
+    fn new(negative: bool, magnitude: BigUint) -> BigInt {
+        BigInt {
+            negative: negative && !magnitude.is_zero(),
+            magnitude,
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn magnitude(&self) -> &BigUint {
+        &self.magnitude
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(n: i64) -> Self {
+        BigInt::new(n < 0, BigUint::from(n.unsigned_abs()))
+    }
+}
+
+impl From<BigUint> for BigInt {
+    fn from(n: BigUint) -> Self {
+        BigInt::new(false, n)
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => self.magnitude.cmp(&other.magnitude),
+            (true, true) => other.magnitude.cmp(&self.magnitude),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl<'a> Add<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, &self.magnitude + &other.magnitude);
+        }
+        // Opposite signs: subtract the smaller magnitude, the larger one decides the sign.
+        if self.magnitude >= other.magnitude {
+            BigInt::new(self.negative, &self.magnitude - &other.magnitude)
+        } else {
+            BigInt::new(other.negative, &other.magnitude - &self.magnitude)
+        }
+    }
+}
+
+impl<'a> Sub<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl<'a> Mul<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::new(
+            self.negative != other.negative,
+            &self.magnitude * &other.magnitude,
+        )
+    }
+}
+
+impl<'a> Div<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    fn div(self, other: &BigInt) -> BigInt {
+        BigInt::new(
+            self.negative != other.negative,
+            &self.magnitude / &other.magnitude,
+        )
+    }
+}
+
+impl<'a> Rem<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    /// The remainder has the sign of 'self', as it does for the primitive integers.
+    fn rem(self, other: &BigInt) -> BigInt {
+        BigInt::new(self.negative, &self.magnitude % &other.magnitude)
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad_integral(!self.negative, "", &self.magnitude.to_string())
+    }
+}
+
+// The operators above take references so that no operand has to be cloned. These forward the
+// operators on owned values to them.
+macro_rules! forward_owned_ops {
+    ($type:ty, $($trait:ident $method:ident),*) => {
+        $(
+            impl $trait<$type> for $type {
+                type Output = $type;
+
+                fn $method(self, other: $type) -> $type {
+                    (&self).$method(&other)
+                }
+            }
+        )*
+    };
+}
+
+forward_owned_ops!(BigUint, Add add, Sub sub, Mul mul, Div div, Rem rem);
+forward_owned_ops!(BigInt, Add add, Sub sub, Mul mul, Div div, Rem rem);
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        -&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(n: u128) -> BigUint {
+        BigUint::from_u128(n)
+    }
+
+    #[test]
+    fn unsigned_arithmetic() {
+        let a = big(u64::MAX as u128);
+        let b = big(12345678901234567890);
+        assert_eq!(&a + &b, big(u64::MAX as u128 + 12345678901234567890));
+        assert_eq!(&a - &b, big(u64::MAX as u128 - 12345678901234567890));
+        assert_eq!(&a * &b, big(u64::MAX as u128 * 12345678901234567890));
+        assert_eq!(
+            (&a * &b).to_u128(),
+            Some(u64::MAX as u128 * 12345678901234567890)
+        );
+        assert_eq!(&(&a * &b) / &b, a);
+        assert_eq!(&(&(&a * &b) + &big(7)) % &b, big(7));
+        assert_eq!(&big(1 << 100) / &big(1 << 60), big(1 << 40));
+        assert_eq!(&big(100) / &big(7), big(14));
+        assert_eq!(BigUint::zero() - BigUint::zero(), BigUint::zero());
+        assert_eq!((&(&a * &a) * &a).to_u128(), None);
+        assert!(big(1 << 64) > a);
+        assert!(BigUint::zero() < big(1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn unsigned_underflow() {
+        let _ = big(1) - big(2);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(BigUint::zero().to_string(), "0");
+        assert_eq!(big(1_000_000_000).to_string(), "1000000000");
+        let factorial = (1..=30).fold(BigUint::from(1), |f, n| f * BigUint::from(n));
+        assert_eq!(factorial.to_string(), "265252859812191058636308480000000");
+        assert_eq!(format!("{:>5}", big(42)), "   42");
+        assert_eq!(BigInt::from(-42).to_string(), "-42");
+    }
+
+    #[test]
+    fn signed_arithmetic() {
+        let values = [-7i64, -3, 0, 2, 5];
+        for a in &values {
+            for b in &values {
+                let (x, y) = (BigInt::from(*a), BigInt::from(*b));
+                assert_eq!(&x + &y, BigInt::from(a + b));
+                assert_eq!(&x - &y, BigInt::from(a - b));
+                assert_eq!(&x * &y, BigInt::from(a * b));
+                assert_eq!(x.cmp(&y), a.cmp(b));
+                if *b != 0 {
+                    assert_eq!(&x / &y, BigInt::from(a / b));
+                    assert_eq!(&x % &y, BigInt::from(a % b));
+                }
+            }
+        }
+        assert_eq!(-BigInt::from(0), BigInt::from(0));
+        assert!(!BigInt::from(i64::MIN).magnitude().is_zero());
+    }
+}
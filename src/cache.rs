@@ -0,0 +1,154 @@
+//! An on-disk cache of answers, so that days whose input, parameters and solver did not change are
+//! not solved again. An answer is keyed by the puzzle, the part, the solver's version string, a
+//! hash of the input and the overridden parameters. Bumping the `version` in a day's registration
+//! makes its cached answers stale, they are then solved and cached again.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::days::PuzzleId;
+use crate::params::Params;
+
+/// Where the command line tool keeps its cache.
+pub const DIR: &str = "target/aoc-cache";
+
+/// A 128-bit FNV-1a hash, enough to tell inputs apart without a hashing crate.
+pub fn hash(bytes: &[u8]) -> u128 {
+    const OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+    bytes.iter().fold(OFFSET, |hash, byte| {
+        (hash ^ *byte as u128).wrapping_mul(PRIME)
+    })
+}
+
+/// What an answer is cached under.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Key {
+    pub id: PuzzleId,
+    pub part: u8,
+    pub version: &'static str,
+    pub input_hash: u128,
+    pub params: String,
+}
+
+impl Key {
+    pub fn new(
+        id: PuzzleId,
+        part: u8,
+        version: &'static str,
+        input: &str,
+        params: &Params,
+    ) -> Self {
+        let params: Vec<String> = params
+            .overrides()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        Key {
+            id,
+            part,
+            version,
+            input_hash: hash(input.as_bytes()),
+            params: params.join(","),
+        }
+    }
+
+    // The whole key on one line, stored with the answer to rule out clashing file names.
+    fn line(&self) -> String {
+        format!(
+            "{} {} {} {} {:032x} {}",
+            self.id.year, self.id.day, self.part, self.version, self.input_hash, self.params
+        )
+    }
+
+    fn filename(&self) -> String {
+        format!("{:032x}", hash(self.line().as_bytes()))
+    }
+}
+
+/// A directory of cached answers, one file per key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Cache { dir: dir.into() }
+    }
+
+    /// The cached answer for 'key', None if there is none or it can not be read.
+    pub fn get(&self, key: &Key) -> Option<String> {
+        let text = fs::read_to_string(self.dir.join(key.filename())).ok()?;
+        match text.split_once('\n') {
+            Some((line, answer)) if line == key.line() => Some(answer.to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn put(&self, key: &Key, answer: &str) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(
+            self.dir.join(key.filename()),
+            format!("{}\n{}", key.line(), answer),
+        )
+    }
+
+    /// Remove all cached answers, returning how many there were.
+    pub fn clear(&self) -> io::Result<usize> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let mut removed = 0;
+        for entry in entries {
+            fs::remove_file(entry?.path())?;
+            removed += 1;
+        }
+        fs::remove_dir(&self.dir)?;
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::Param;
+    use std::env;
+
+    fn key(version: &'static str, input: &str) -> Key {
+        Key::new(
+            PuzzleId::new(2020, 15),
+            1,
+            version,
+            input,
+            &Params::default(),
+        )
+    }
+
+    #[test]
+    fn answers_by_key() {
+        let dir = env::temp_dir().join(format!("aoc-cache-test-{}", std::process::id()));
+        let cache = Cache::new(&dir);
+        assert_eq!(cache.get(&key("1", "0,3,6\n")), None);
+        cache.put(&key("1", "0,3,6\n"), "436").unwrap();
+        assert_eq!(cache.get(&key("1", "0,3,6\n")), Some("436".to_string()));
+        // Another input or a new solver version misses.
+        assert_eq!(cache.get(&key("1", "1,3,2\n")), None);
+        assert_eq!(cache.get(&key("2", "0,3,6\n")), None);
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert_eq!(cache.get(&key("1", "0,3,6\n")), None);
+        assert_eq!(cache.clear().unwrap(), 0);
+    }
+
+    #[test]
+    fn parameters_are_part_of_the_key() {
+        const TURNS: Param = Param::integer("turns", 2020, 1, "The number of turns");
+        let params = Params::new(&[TURNS], &[("turns".to_string(), "10".to_string())]).unwrap();
+        let id = PuzzleId::new(2020, 15);
+        let overridden = Key::new(id, 1, "1", "0,3,6\n", &params);
+        assert_eq!(overridden.params, "turns=10");
+        assert_ne!(overridden.filename(), key("1", "0,3,6\n").filename());
+    }
+}
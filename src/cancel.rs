@@ -0,0 +1,142 @@
+//! Cooperative cancellation of solvers. The runner hands every puzzle a [`Cancel`] token in its
+//! context and cancels it when a part runs out of time, see [`Cancel::after`]. Solvers call
+//! [`Cancel::check`] in their long loops and return the error it gives once cancelled.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::puzzle::PuzzleError;
+
+/// A token telling a solver to stop. Clones share the same state, and the default token can never
+/// be cancelled, so checking it costs next to nothing.
+#[derive(Debug, Clone, Default)]
+pub struct Cancel {
+    cancelled: Option<Arc<AtomicBool>>,
+}
+
+impl Cancel {
+    /// A token that can be cancelled.
+    pub fn new() -> Self {
+        Cancel {
+            cancelled: Some(Arc::new(AtomicBool::new(false))),
+        }
+    }
+
+    pub fn cancel(&self) {
+        if let Some(cancelled) = &self.cancelled {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        match &self.cancelled {
+            Some(cancelled) => cancelled.load(Ordering::Relaxed),
+            None => false,
+        }
+    }
+
+    /// Err(PuzzleError::Timeout) once the token is cancelled.
+    pub fn check(&self) -> Result<(), PuzzleError> {
+        if self.is_cancelled() {
+            Err(PuzzleError::Timeout)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Clear the token, and cancel it after 'timeout' unless the returned deadline is dropped
+    /// first.
+    pub fn after(&self, timeout: Duration) -> Deadline {
+        if let Some(cancelled) = &self.cancelled {
+            cancelled.store(false, Ordering::Relaxed);
+        }
+        let (done, finished) = mpsc::channel::<()>();
+        let token = self.clone();
+        // Dropping the sender wakes the watcher up early.
+        let watcher = thread::spawn(move || {
+            if finished.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+                token.cancel();
+            }
+        });
+        Deadline {
+            done: Some(done),
+            watcher: Some(watcher),
+        }
+    }
+}
+
+/// Cancels a token when its time is up, see [`Cancel::after`].
+pub struct Deadline {
+    done: Option<Sender<()>>,
+    watcher: Option<JoinHandle<()>>,
+}
+
+impl Drop for Deadline {
+    fn drop(&mut self) {
+        drop(self.done.take());
+        if let Some(watcher) = self.watcher.take() {
+            let _ = watcher.join();
+        }
+    }
+}
+
+/// Parse a duration such as "500ms", "10s", "2m" or "1.5", which is in seconds.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    // Milliseconds are divided rather than multiplied by 0.001, which is not exact.
+    let (number, seconds): (&str, fn(f64) -> f64) = if let Some(number) = text.strip_suffix("ms") {
+        (number, |n| n / 1e3)
+    } else if let Some(number) = text.strip_suffix('s') {
+        (number, |n| n)
+    } else if let Some(number) = text.strip_suffix('m') {
+        (number, |n| n * 60.0)
+    } else {
+        (text, |n| n)
+    };
+    match number.parse::<f64>() {
+        Ok(n) if n.is_finite() && n > 0.0 => Ok(Duration::from_secs_f64(seconds(n))),
+        _ => Err(format!("Incorrect duration '{}'", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn cancelled_after_timeout() {
+        let cancel = Cancel::new();
+        let deadline = cancel.after(Duration::from_millis(10));
+        let start = Instant::now();
+        while cancel.check().is_ok() {
+            assert!(start.elapsed() < Duration::from_secs(10));
+        }
+        assert_eq!(cancel.check(), Err(PuzzleError::Timeout));
+        drop(deadline);
+        // A new deadline starts over.
+        let _deadline = cancel.after(Duration::from_secs(60));
+        assert!(!cancel.is_cancelled());
+    }
+
+    #[test]
+    fn finished_in_time() {
+        let cancel = Cancel::new();
+        drop(cancel.after(Duration::from_millis(10)));
+        thread::sleep(Duration::from_millis(20));
+        assert!(!cancel.is_cancelled());
+        assert!(Cancel::default().check().is_ok());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("10s"), Ok(Duration::from_secs(10)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1.5"), Ok(Duration::from_millis(1500)));
+        assert!(parse_duration("0").is_err());
+        assert!(parse_duration("soon").is_err());
+    }
+}
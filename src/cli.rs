@@ -0,0 +1,519 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::cancel;
+use crate::params;
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Solve the selected days once and print the answers.
+    Solve,
+    /// Solve the selected days repeatedly and print timing statistics.
+    Bench,
+    /// Solve the selected days and compare the answers with the answers manifest.
+    Verify,
+    /// Print the registered puzzles.
+    List,
+    /// Print a synthetic input for a single day.
+    Generate,
+    /// Print the parameters the selected days accept.
+    Help,
+    /// Remove the cached answers.
+    ClearCache,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+/// Options for the 'bench' command.
+#[derive(Debug, PartialEq)]
+pub struct BenchOptions {
+    /// Number of untimed runs before measuring.
+    pub warmup: usize,
+    /// Fixed number of timed runs. If not given, runs are repeated until 'budget' is used up.
+    pub runs: Option<usize>,
+    pub budget: Duration,
+    /// Baseline files to write the results to and to compare the results with.
+    pub save: Option<String>,
+    pub compare: Option<String>,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        BenchOptions {
+            warmup: 1,
+            runs: None,
+            budget: Duration::from_secs(1),
+            save: None,
+            compare: None,
+        }
+    }
+}
+
+/// Options for the 'generate' command.
+#[derive(Debug, PartialEq)]
+pub struct GenerateOptions {
+    /// The number of records to generate, see the day's generator for what a record is.
+    pub size: usize,
+    pub seed: u64,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        GenerateOptions {
+            size: 1000,
+            seed: 1,
+        }
+    }
+}
+
+/// Options given on the command line.
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub command: Command,
+    /// The day selection, e.g. "7", "all" or "1-5,9".
+    pub days: String,
+    /// The year selection, e.g. "2020", "all" or "2020-2021". Defaults to all years.
+    pub years: String,
+    /// The parts to solve, 1 and/or 2.
+    pub parts: Vec<u8>,
+    pub bench: BenchOptions,
+    pub generate: GenerateOptions,
+    /// Write the answers to the manifest instead of comparing them.
+    pub record: bool,
+    pub format: Format,
+    /// Input file to use instead of input/dayN.txt, "-" for stdin. Only valid for a single day.
+    pub input: Option<String>,
+    /// The number of days to solve at the same time, one per CPU if not given.
+    pub jobs: Option<usize>,
+    /// Puzzle parameters to override as name and value, checked once the day is known. Only
+    /// valid for a single day.
+    pub params: Vec<(String, String)>,
+    /// Print the steps each day took to its answers.
+    pub explain: bool,
+    /// Solve every part even if its answer is cached.
+    pub no_cache: bool,
+    /// How long a part may take before it is cancelled.
+    pub timeout: Option<Duration>,
+}
+
+const BENCH_OPTIONS: [&str; 5] = ["--warmup", "--runs", "--time", "--save", "--compare"];
+const GENERATE_OPTIONS: [&str; 2] = ["--size", "--seed"];
+
+fn value<'a>(option: &str, value: Option<&'a String>) -> Result<&'a String, String> {
+    value.ok_or_else(|| format!("Missing value for '{}'", option))
+}
+
+fn parse_value<T>(option: &str, value: Option<&String>) -> Result<T, String>
+where
+    T: FromStr,
+{
+    let value = self::value(option, value)?;
+    value
+        .parse::<T>()
+        .map_err(|_| format!("Incorrect value '{}' for '{}'", value, option))
+}
+
+fn parse_part(value: Option<&String>) -> Result<u8, String> {
+    match parse_value::<u8>("--part", value)? {
+        part @ 1..=2 => Ok(part),
+        part => Err(format!("Incorrect part '{}', expected 1 or 2", part)),
+    }
+}
+
+fn parse_format(value: Option<&String>) -> Result<Format, String> {
+    match &self::value("--format", value)?[..] {
+        "text" => Ok(Format::Text),
+        "json" => Ok(Format::Json),
+        "csv" => Ok(Format::Csv),
+        format => Err(format!(
+            "Incorrect format '{}', expected text, json or csv",
+            format
+        )),
+    }
+}
+
+// Parse a time budget in seconds, e.g. "0.5".
+fn parse_budget(value: Option<&String>) -> Result<Duration, String> {
+    let seconds = parse_value::<f64>("--time", value)?;
+    if seconds.is_finite() && seconds > 0.0 {
+        Ok(Duration::from_secs_f64(seconds))
+    } else {
+        Err(format!("Incorrect time budget '{}'", seconds))
+    }
+}
+
+/// Parse the command line arguments, not including the program name.
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut command = Command::Solve;
+    let mut days = None;
+    let mut years = "all".to_string();
+    let mut parts = vec![1, 2];
+    let mut bench = BenchOptions::default();
+    let mut bench_option = None;
+    let mut generate = GenerateOptions::default();
+    let mut generate_option = None;
+    let mut record = false;
+    let mut format = Format::Text;
+    let mut input = None;
+    let mut jobs = None;
+    let mut params = vec![];
+    let mut explain = false;
+    let mut no_cache = false;
+    let mut timeout = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--part" => parts = vec![parse_part(args.next())?],
+            "--warmup" => bench.warmup = parse_value(arg, args.next())?,
+            "--runs" => match parse_value(arg, args.next())? {
+                0 => return Err("At least one run is needed for '--runs'".to_string()),
+                runs => bench.runs = Some(runs),
+            },
+            "--time" => bench.budget = parse_budget(args.next())?,
+            "--save" => bench.save = Some(value(arg, args.next())?.clone()),
+            "--compare" => bench.compare = Some(value(arg, args.next())?.clone()),
+            "--size" => generate.size = parse_value(arg, args.next())?,
+            "--seed" => generate.seed = parse_value(arg, args.next())?,
+            "--record" => record = true,
+            "--format" => format = parse_format(args.next())?,
+            "--input" => input = Some(value(arg, args.next())?.clone()),
+            "--year" => years = value(arg, args.next())?.clone(),
+            "--jobs" => match parse_value(arg, args.next())? {
+                0 => return Err("At least one job is needed for '--jobs'".to_string()),
+                n => jobs = Some(n),
+            },
+            "--param" => params.push(params::parse_override(value(arg, args.next())?)?),
+            "--explain" => explain = true,
+            "--no-cache" => no_cache = true,
+            "--timeout" => timeout = Some(cancel::parse_duration(value(arg, args.next())?)?),
+            "--help" if command == Command::Solve => command = Command::Help,
+            "bench" if days.is_none() && command == Command::Solve => command = Command::Bench,
+            "verify" if days.is_none() && command == Command::Solve => command = Command::Verify,
+            "list" if days.is_none() && command == Command::Solve => command = Command::List,
+            "generate" if days.is_none() && command == Command::Solve => {
+                command = Command::Generate
+            }
+            "cache" if days.is_none() && command == Command::Solve => match args.next() {
+                Some(arg) if arg == "clear" => command = Command::ClearCache,
+                _ => return Err("Expected 'cache clear'".to_string()),
+            },
+            _ if arg.starts_with("--") => return Err(format!("Unknown option '{}'", arg)),
+            _ if days.is_none() => days = Some(arg.clone()),
+            _ => return Err(format!("Unexpected argument '{}'", arg)),
+        }
+        if BENCH_OPTIONS.contains(&&arg[..]) {
+            bench_option = Some(arg);
+        }
+        if GENERATE_OPTIONS.contains(&&arg[..]) {
+            generate_option = Some(arg);
+        }
+    }
+    if let (Some(option), false) = (bench_option, command == Command::Bench) {
+        return Err(format!("Option '{}' is only valid with 'bench'", option));
+    }
+    if let (Some(option), false) = (generate_option, command == Command::Generate) {
+        return Err(format!("Option '{}' is only valid with 'generate'", option));
+    }
+    if command == Command::Generate && (input.is_some() || format != Format::Text) {
+        return Err(
+            "Options '--input' and '--format' are not supported with 'generate'".to_string(),
+        );
+    }
+    // Days solved side by side would distort the benchmark timings.
+    if jobs.is_some() && command != Command::Solve && command != Command::Verify {
+        return Err("Option '--jobs' is only valid when solving or verifying".to_string());
+    }
+    if record && command != Command::Verify {
+        return Err("Option '--record' is only valid with 'verify'".to_string());
+    }
+    if record && input.is_some() {
+        return Err("Option '--record' can not be combined with '--input'".to_string());
+    }
+    if format != Format::Text && command == Command::Verify {
+        return Err("Option '--format' is not supported with 'verify'".to_string());
+    }
+    if input.is_some() && command == Command::List {
+        return Err("Option '--input' is not supported with 'list'".to_string());
+    }
+    // The answers manifest and the generated answers are for the default parameters.
+    if !params.is_empty() && command != Command::Solve && command != Command::Bench {
+        return Err("Option '--param' is only valid when solving or benchmarking".to_string());
+    }
+    if explain && command != Command::Solve {
+        return Err("Option '--explain' is only valid when solving".to_string());
+    }
+    let solving = [Command::Solve, Command::Bench, Command::Verify];
+    if timeout.is_some() && !solving.contains(&command) {
+        return Err("Option '--timeout' is only valid when solving".to_string());
+    }
+    // Only solving uses the cache, verifying and benchmarking always solve.
+    if no_cache && command != Command::Solve {
+        return Err("Option '--no-cache' is only valid when solving".to_string());
+    }
+    // A trace does not fit in a CSV field.
+    if explain && format == Format::Csv {
+        return Err("Option '--explain' is not supported with '--format csv'".to_string());
+    }
+    if command == Command::Help && (input.is_some() || format != Format::Text) {
+        return Err("Options '--input' and '--format' are not supported with '--help'".to_string());
+    }
+    // Verify, list and help cover all days unless told otherwise. Clearing the cache covers all
+    // days anyway.
+    let all_by_default = [
+        Command::Verify,
+        Command::List,
+        Command::Help,
+        Command::ClearCache,
+    ];
+    if days.is_none() && all_by_default.contains(&command) {
+        days = Some("all".to_string());
+    }
+    match days {
+        Some(days) => Ok(Options {
+            command,
+            days,
+            years,
+            parts,
+            bench,
+            generate,
+            record,
+            format,
+            input,
+            jobs,
+            params,
+            explain,
+            no_cache,
+            timeout,
+        }),
+        None => Err("Missing mandatory argument 'day'".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn part_flag() {
+        assert_eq!(
+            parse_args(&args(&["15"])),
+            Ok(Options {
+                command: Command::Solve,
+                days: "15".to_string(),
+                years: "all".to_string(),
+                parts: vec![1, 2],
+                bench: BenchOptions::default(),
+                generate: GenerateOptions::default(),
+                record: false,
+                format: Format::Text,
+                input: None,
+                jobs: None,
+                params: vec![],
+                explain: false,
+                no_cache: false,
+                timeout: None
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["--part", "2", "all"])).unwrap().parts,
+            vec![2]
+        );
+        assert!(parse_args(&args(&["15", "--part", "3"])).is_err());
+        assert!(parse_args(&args(&["15", "--part"])).is_err());
+        assert!(parse_args(&args(&["--part", "1"])).is_err());
+    }
+
+    #[test]
+    fn bench_options() {
+        let options = parse_args(&args(&[
+            "bench", "1-3", "--runs", "50", "--save", "base.txt",
+        ]));
+        assert_eq!(
+            options,
+            Ok(Options {
+                command: Command::Bench,
+                days: "1-3".to_string(),
+                years: "all".to_string(),
+                parts: vec![1, 2],
+                bench: BenchOptions {
+                    runs: Some(50),
+                    save: Some("base.txt".to_string()),
+                    ..BenchOptions::default()
+                },
+                generate: GenerateOptions::default(),
+                record: false,
+                format: Format::Text,
+                input: None,
+                jobs: None,
+                params: vec![],
+                explain: false,
+                no_cache: false,
+                timeout: None
+            })
+        );
+        assert!(parse_args(&args(&["bench", "1", "--runs", "0"])).is_err());
+        assert!(parse_args(&args(&["1", "--runs", "10"])).is_err());
+        assert!(parse_args(&args(&["bench"])).is_err());
+    }
+
+    #[test]
+    fn format_option() {
+        let options = parse_args(&args(&["--format", "csv", "all"])).unwrap();
+        assert_eq!(options.format, Format::Csv);
+        assert!(parse_args(&args(&["1", "--format", "xml"])).is_err());
+        assert!(parse_args(&args(&["verify", "--format", "json"])).is_err());
+    }
+
+    #[test]
+    fn input_option() {
+        let options = parse_args(&args(&["7", "--input", "-"])).unwrap();
+        assert_eq!(options.input, Some("-".to_string()));
+        assert!(parse_args(&args(&["7", "--input"])).is_err());
+        assert!(parse_args(&args(&["verify", "7", "--record", "--input", "x.txt"])).is_err());
+    }
+
+    #[test]
+    fn verify_options() {
+        let options = parse_args(&args(&["verify", "--record"])).unwrap();
+        assert_eq!(options.command, Command::Verify);
+        assert_eq!(options.days, "all");
+        assert!(options.record);
+        assert!(parse_args(&args(&["7", "--record"])).is_err());
+    }
+
+    #[test]
+    fn list_command() {
+        let options = parse_args(&args(&["list", "--format", "csv"])).unwrap();
+        assert_eq!(options.command, Command::List);
+        assert_eq!(options.days, "all");
+        assert_eq!(parse_args(&args(&["list", "1-5"])).unwrap().days, "1-5");
+        assert!(parse_args(&args(&["list", "--input", "-"])).is_err());
+    }
+
+    #[test]
+    fn year_option() {
+        let options = parse_args(&args(&["verify", "--year", "2020"])).unwrap();
+        assert_eq!(options.years, "2020");
+        assert_eq!(options.days, "all");
+        assert!(parse_args(&args(&["7", "--year"])).is_err());
+    }
+
+    #[test]
+    fn generate_options() {
+        let options =
+            parse_args(&args(&["generate", "20", "--size", "500", "--seed", "3"])).unwrap();
+        assert_eq!(options.command, Command::Generate);
+        assert_eq!(options.days, "20");
+        assert_eq!(options.generate, GenerateOptions { size: 500, seed: 3 });
+        assert_eq!(
+            parse_args(&args(&["generate", "1"])).unwrap().generate.size,
+            1000
+        );
+        assert!(parse_args(&args(&["1", "--seed", "3"])).is_err());
+        assert!(parse_args(&args(&["generate", "1", "--size", "x"])).is_err());
+        assert!(parse_args(&args(&["generate", "1", "--input", "-"])).is_err());
+        assert!(parse_args(&args(&["generate"])).is_err());
+    }
+
+    #[test]
+    fn jobs_option() {
+        assert_eq!(
+            parse_args(&args(&["all", "--jobs", "4"])).unwrap().jobs,
+            Some(4)
+        );
+        assert_eq!(
+            parse_args(&args(&["verify", "--jobs", "2"])).unwrap().jobs,
+            Some(2)
+        );
+        assert_eq!(parse_args(&args(&["all"])).unwrap().jobs, None);
+        assert!(parse_args(&args(&["all", "--jobs", "0"])).is_err());
+        assert!(parse_args(&args(&["bench", "all", "--jobs", "2"])).is_err());
+    }
+
+    #[test]
+    fn param_option() {
+        let options = parse_args(&args(&[
+            "15",
+            "--param",
+            "part1_turns=10",
+            "--param",
+            "part2_turns=20",
+        ]))
+        .unwrap();
+        assert_eq!(
+            options.params,
+            vec![
+                ("part1_turns".to_string(), "10".to_string()),
+                ("part2_turns".to_string(), "20".to_string())
+            ]
+        );
+        assert!(parse_args(&args(&["bench", "15", "--param", "part1_turns=10"])).is_ok());
+        assert!(parse_args(&args(&["15", "--param", "part1_turns"])).is_err());
+        assert!(parse_args(&args(&["15", "--param"])).is_err());
+        assert!(parse_args(&args(&["verify", "15", "--param", "part1_turns=10"])).is_err());
+    }
+
+    #[test]
+    fn explain_option() {
+        assert!(parse_args(&args(&["9", "--explain"])).unwrap().explain);
+        assert!(
+            parse_args(&args(&["all", "--explain", "--format", "json"]))
+                .unwrap()
+                .explain
+        );
+        assert!(!parse_args(&args(&["9"])).unwrap().explain);
+        assert!(parse_args(&args(&["9", "--explain", "--format", "csv"])).is_err());
+        assert!(parse_args(&args(&["verify", "--explain"])).is_err());
+    }
+
+    #[test]
+    fn cache_options() {
+        assert!(parse_args(&args(&["all", "--no-cache"])).unwrap().no_cache);
+        assert!(!parse_args(&args(&["all"])).unwrap().no_cache);
+        assert!(parse_args(&args(&["verify", "--no-cache"])).is_err());
+        assert_eq!(
+            parse_args(&args(&["cache", "clear"])).unwrap().command,
+            Command::ClearCache
+        );
+        assert!(parse_args(&args(&["cache"])).is_err());
+        assert!(parse_args(&args(&["cache", "all"])).is_err());
+    }
+
+    #[test]
+    fn timeout_option() {
+        assert_eq!(
+            parse_args(&args(&["all", "--timeout", "2s"]))
+                .unwrap()
+                .timeout,
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            parse_args(&args(&["verify", "--timeout", "500ms"]))
+                .unwrap()
+                .timeout,
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(parse_args(&args(&["all"])).unwrap().timeout, None);
+        assert!(parse_args(&args(&["all", "--timeout", "0s"])).is_err());
+        assert!(parse_args(&args(&["all", "--timeout"])).is_err());
+        assert!(parse_args(&args(&["list", "--timeout", "1s"])).is_err());
+    }
+
+    #[test]
+    fn help_option() {
+        let options = parse_args(&args(&["--help", "15"])).unwrap();
+        assert_eq!(options.command, Command::Help);
+        assert_eq!(options.days, "15");
+        assert_eq!(parse_args(&args(&["--help"])).unwrap().days, "all");
+        assert!(parse_args(&args(&["list", "--help"])).is_err());
+    }
+}
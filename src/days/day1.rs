@@ -1,45 +1,141 @@
-use crate::puzzle::{io, File, Puzzle};
-pub struct Day1;
+//! Day 1: Report Repair. [`Day1::solve_part1`] and [`Day1::solve_part2`] find the two and the
+//! three expense report entries that sum to 2020 and multiply them. The sum and the numbers of
+//! entries are parameters.
 
-fn sum_to(val: i64, limit: i64, data: &[i64]) -> Option<Vec<i64>> {
+use crate::cancel::Cancel;
+use crate::generate::{Generated, Generator, Rng};
+use crate::params::Param;
+use crate::puzzle::{parse_token, read_lines, BufRead, Context, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day1 {
+    numbers: Vec<i64>,
+    context: Context,
+}
+
+const TARGET: Param = Param::integer("target", 2020, 1, "The sum of the entries");
+const PART1_ENTRIES: Param =
+    Param::integer("part1_entries", 2, 1, "The number of entries in the first part");
+const PART2_ENTRIES: Param =
+    Param::integer("part2_entries", 3, 1, "The number of entries in the second part");
+
+register_puzzle! {
+    puzzle: Day1,
+    year: 2020,
+    day: 1,
+    title: "Report Repair",
+    input: Numbers,
+    reads_input: true,
+    params: &[TARGET, PART1_ENTRIES, PART2_ENTRIES],
+}
+
+fn sum_to(
+    val: i64,
+    limit: i64,
+    data: &[i64],
+    cancel: &Cancel,
+) -> Result<Option<Vec<i64>>, PuzzleError> {
     if data.len() == 0 || val <= 0 || limit <= 0 {
-        return None;
+        return Ok(None);
     }
+    cancel.check()?;
     if val == data[0] && limit == 1 {
-        return Some(vec![data[0]]);
+        return Ok(Some(vec![data[0]]));
     }
-    if let Some(mut included) = sum_to(val - data[0], limit - 1, &data[1..]) {
+    if let Some(mut included) = sum_to(val - data[0], limit - 1, &data[1..], cancel)? {
         included.push(data[0]);
-        return Some(included);
+        return Ok(Some(included));
     }
-    if let Some(excluded) = sum_to(val, limit, &data[1..]) {
-        return Some(excluded);
+    if let Some(excluded) = sum_to(val, limit, &data[1..], cancel)? {
+        return Ok(Some(excluded));
     }
-    None
+    Ok(None)
 }
 
 impl Day1 {
-    fn solve_part1(&self, input: &Vec<i64>) -> i64 {
-        let parts = sum_to(2020, 2, input).unwrap();
-        parts.iter().fold(1, |answer, i| answer * i)
+    // The product of 'entries' entries that sum to the target.
+    fn solve(&self, input: &[i64], entries: &Param) -> Result<i64, PuzzleError> {
+        let target = self.context.params.integer(&TARGET);
+        let entries = self.context.params.integer(entries);
+        let parts = sum_to(target, entries, input, &self.context.cancel)?.ok_or_else(|| {
+            PuzzleError::NoSolution(format!("no {} entries sum to {}", entries, target))
+        })?;
+        Ok(parts.iter().product())
     }
 
-    fn solve_part2(&self, input: &Vec<i64>) -> i64 {
-        let parts = sum_to(2020, 3, input).unwrap();
-        parts.iter().fold(1, |answer, i| answer * i)
+    pub fn solve_part1(&self, input: &[i64]) -> Result<i64, PuzzleError> {
+        self.solve(input, &PART1_ENTRIES)
+    }
+
+    pub fn solve_part2(&self, input: &[i64]) -> Result<i64, PuzzleError> {
+        self.solve(input, &PART2_ENTRIES)
     }
 }
 
 impl Puzzle for Day1 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let numbers: Vec<i64> = lines
-            .expect("No input file")
-            .map(|l| l.unwrap().parse::<i64>().unwrap())
-            .collect();
-        return (
-            self.solve_part1(&numbers).to_string(),
-            self.solve_part2(&numbers).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.numbers = read_lines(input)?
+            .iter()
+            .enumerate()
+            .map(|(i, l)| parse_token::<i64>(i, l, l))
+            .collect::<Result<Vec<i64>, PuzzleError>>()?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&self.numbers)?.to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(&self.numbers)?.to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+// 'size' entries: a planted pair and a planted triple summing to 2020 among entries above 1010,
+// which are too large to be part of any other pair or triple. Entries that would complete one with
+// the planted entries are left out.
+impl Generator for Day1 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let planted = loop {
+            let a = rng.range(20..=1000);
+            let (c, d) = (rng.range(506..=1000), rng.range(506..=1000));
+            let planted = [a, 2020 - a, c, d, 2020 - c - d];
+            let pairs = (0..5)
+                .flat_map(|i| (i + 1..5).map(move |j| (i, j)))
+                .filter(|&(i, j)| planted[i] + planted[j] == 2020)
+                .count();
+            let triples = (0..5)
+                .flat_map(|i| (i + 1..5).flat_map(move |j| (j + 1..5).map(move |k| (i, j, k))))
+                .filter(|&(i, j, k)| planted[i] + planted[j] + planted[k] == 2020)
+                .count();
+            let mut distinct = planted.to_vec();
+            distinct.sort_unstable();
+            distinct.dedup();
+            if pairs == 1 && triples == 1 && distinct.len() == 5 {
+                break planted;
+            }
+        };
+
+        let mut entries = planted.to_vec();
+        let high = 2019.max(1011 + 3 * size as i64);
+        while entries.len() < size.max(5) {
+            let entry = rng.range(1011..=high);
+            let completes = planted.iter().any(|&x| {
+                entry + x == 2020 || planted.iter().any(|&y| x != y && entry + x + y == 2020)
+            });
+            if !completes && !entries.contains(&entry) {
+                entries.push(entry);
+            }
+        }
+        rng.shuffle(&mut entries);
+        Generated::from_lines(entries.iter().map(|x| x.to_string()).collect()).with_answers(
+            planted[0] * planted[1],
+            planted[2] * planted[3] * planted[4],
+        )
     }
 }
 
@@ -50,16 +146,24 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day1 {}.solve_part1(&vec!(1721, 979, 366, 299, 675, 1456)),
-            514579
+            Day1::default().solve_part1(&vec!(1721, 979, 366, 299, 675, 1456)),
+            Ok(514579)
         );
     }
 
     #[test]
     fn part2_example1() {
         assert_eq!(
-            Day1 {}.solve_part2(&vec!(1721, 979, 366, 299, 675, 1456)),
-            241861950
+            Day1::default().solve_part2(&vec!(1721, 979, 366, 299, 675, 1456)),
+            Ok(241861950)
         );
     }
+
+    #[test]
+    fn example_from_str() {
+        let mut day = Day1::default();
+        day.parse_str("1721\n979\n366\n299\n675\n1456\n").unwrap();
+        assert_eq!(day.part1(), Ok("514579".to_string()));
+        assert_eq!(day.part2(), Ok("241861950".to_string()));
+    }
 }
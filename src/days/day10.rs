@@ -1,7 +1,28 @@
-use crate::puzzle::{io, File, Puzzle};
+//! Day 10: Adapter Array. Counts the joltage differences in the adapter chain and the number of
+//! ways the adapters can be arranged.
+
+use crate::bigint::BigUint;
+use crate::cancel::Cancel;
+use crate::generate::{Generated, Generator, Rng};
+use crate::puzzle::{parse_token, read_lines, BufRead, Context, Puzzle, PuzzleError};
+use crate::reference::{Answer, Reference};
 use std::collections::HashMap;
 
-pub struct Day10;
+#[derive(Default)]
+pub struct Day10 {
+    adapters: Vec<usize>,
+    context: Context,
+}
+
+register_puzzle! {
+    puzzle: Day10,
+    year: 2020,
+    day: 10,
+    title: "Adapter Array",
+    input: Numbers,
+    reads_input: true,
+    reference: true,
+}
 
 fn get_nof_difference(numbers: &[usize], difference: usize) -> usize {
     numbers.iter().enumerate().fold(0, |acc, (i, x)| {
@@ -13,61 +34,149 @@ fn get_nof_difference(numbers: &[usize], difference: usize) -> usize {
     })
 }
 
+// Count the arrangements of 'adapters' after the adapter 'prev'. The count can exceed 64 bits for
+// long chains of adapters that are close together. Gives up with the error of 'cancel' once it is
+// cancelled.
 fn get_combinations(
     adapters: &[usize],
     prev: usize,
-    mut part_results: &mut HashMap<usize, usize>,
-) -> usize {
+    mut part_results: &mut HashMap<usize, BigUint>,
+    cancel: &Cancel,
+) -> Result<BigUint, PuzzleError> {
+    cancel.check()?;
     if adapters[0] > prev + 3 {
-        return 0;
+        return Ok(BigUint::zero());
     }
     if adapters.len() == 1 {
-        return 1;
+        return Ok(BigUint::from(1));
     }
 
     let included = match part_results.get(&adapters[0]) {
-        Some(result) => *result,
+        Some(result) => result.clone(),
         None => {
-            let result = get_combinations(&adapters[1..], adapters[0], &mut part_results);
-            part_results.insert(adapters[0], result);
+            let result = get_combinations(&adapters[1..], adapters[0], &mut part_results, cancel)?;
+            part_results.insert(adapters[0], result.clone());
             result
         }
     };
 
-    let excluded = get_combinations(&adapters[1..], prev, &mut part_results);
-    included + excluded
+    let excluded = get_combinations(&adapters[1..], prev, &mut part_results, cancel)?;
+    Ok(included + excluded)
 }
 
 impl Day10 {
-    fn solve_part1(&self, numbers: &mut Vec<usize>) -> usize {
-        numbers.sort();
-        let max = *numbers.iter().max().unwrap();
+    pub fn solve_part1(&self, numbers: &mut Vec<usize>) -> Result<usize, PuzzleError> {
+        numbers.sort_unstable();
+        let max = *numbers
+            .iter()
+            .max()
+            .ok_or_else(|| PuzzleError::no_solution("no adapters"))?;
         numbers.push(max + 3);
         numbers.insert(0, 0);
 
-        get_nof_difference(&numbers[..], 1) * get_nof_difference(&numbers[..], 3)
+        Ok(get_nof_difference(&numbers[..], 1) * get_nof_difference(&numbers[..], 3))
     }
 
-    fn solve_part2(&self, numbers: &mut Vec<usize>) -> usize {
-        let mut part_results: HashMap<usize, usize> = HashMap::new();
-        numbers.sort();
-        let max = *numbers.iter().max().unwrap();
+    pub fn solve_part2(&self, numbers: &mut Vec<usize>) -> Result<BigUint, PuzzleError> {
+        let mut part_results: HashMap<usize, BigUint> = HashMap::new();
+        numbers.sort_unstable();
+        let max = *numbers
+            .iter()
+            .max()
+            .ok_or_else(|| PuzzleError::no_solution("no adapters"))?;
         numbers.push(max + 3);
 
-        get_combinations(&numbers[..], 0, &mut part_results)
+        get_combinations(&numbers[..], 0, &mut part_results, &self.context.cancel)
     }
 }
 
 impl Puzzle for Day10 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<usize> = lines
-            .expect("No input file")
-            .map(|l| l.unwrap().parse::<usize>().unwrap())
-            .collect();
-        return (
-            self.solve_part1(&mut input.clone()).to_string(),
-            self.solve_part2(&mut input.clone()).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.adapters = read_lines(input)?
+            .iter()
+            .enumerate()
+            .map(|(i, l)| parse_token::<usize>(i, l, l))
+            .collect::<Result<Vec<usize>, PuzzleError>>()?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&mut self.adapters.clone())?.to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(&mut self.adapters.clone())?.to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+// About 'size' adapters in runs of up to 4 that differ by 1 jolt, the runs 3 jolts apart. No
+// arrangement can skip a 3 jolt gap, so the arrangements are the product of those of the runs.
+impl Generator for Day10 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        // The ways to cross a run of n 1 jolt steps in steps of 1 to 3 jolts.
+        const WAYS: [u64; 5] = [1, 1, 2, 4, 7];
+        let mut adapters = vec![];
+        let mut jolts = 0;
+        // The device is 3 jolts above the last adapter.
+        let (mut ones, mut threes) = (0, 1);
+        let mut arrangements = BigUint::from(1);
+        while adapters.len() < size.max(1) {
+            let run = rng.below(WAYS.len());
+            for _ in 0..run {
+                jolts += 1;
+                adapters.push(jolts);
+            }
+            ones += run;
+            arrangements = &arrangements * &BigUint::from(WAYS[run]);
+            jolts += 3;
+            adapters.push(jolts);
+            threes += 1;
+        }
+        rng.shuffle(&mut adapters);
+        let lines = adapters.iter().map(|x| x.to_string()).collect();
+        Generated::from_lines(lines).with_answers(ones * threes, arrangements)
+    }
+}
+
+// Try every subset of the adapters, which takes exponential time.
+impl Reference for Day10 {
+    fn reference(&self, part: u8) -> Option<Answer> {
+        let mut adapters = self.adapters.clone();
+        adapters.sort_unstable();
+        let device = match adapters.last() {
+            Some(max) => max + 3,
+            None => return Some(Err(PuzzleError::no_solution("no adapters"))),
+        };
+        let chain_differences = |chain: &[usize]| -> Vec<usize> {
+            let mut joltages = vec![0];
+            joltages.extend_from_slice(chain);
+            joltages.push(device);
+            joltages.windows(2).map(|pair| pair[1] - pair[0]).collect()
+        };
+        if part == 1 {
+            let differences = chain_differences(&adapters);
+            let ones = differences.iter().filter(|d| **d == 1).count();
+            let threes = differences.iter().filter(|d| **d == 3).count();
+            return Some(Ok((ones * threes).to_string()));
+        }
+        let mut arrangements = 0u64;
+        for subset in 0..1u64 << adapters.len() {
+            let chain: Vec<usize> = (0..adapters.len())
+                .filter(|i| subset >> i & 1 == 1)
+                .map(|i| adapters[i])
+                .collect();
+            if chain_differences(&chain)
+                .iter()
+                .all(|d| (1..=3).contains(d))
+            {
+                arrangements += 1;
+            }
+        }
+        Some(Ok(arrangements.to_string()))
     }
 }
 
@@ -78,38 +187,63 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day10 {}.solve_part1(&mut vec!(16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4)),
-            35
+            Day10::default().solve_part1(&mut vec!(16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4)),
+            Ok(35)
         );
     }
 
     #[test]
     fn part1_example2() {
         assert_eq!(
-            Day10 {}.solve_part1(&mut vec!(
+            Day10::default().solve_part1(&mut vec!(
                 28, 33, 18, 42, 31, 14, 46, 20, 48, 47, 24, 23, 49, 45, 19, 38, 39, 11, 1, 32, 25,
                 35, 8, 17, 7, 9, 4, 2, 34, 10, 3
             )),
-            220
+            Ok(220)
         );
     }
 
     #[test]
     fn part2_example1() {
         assert_eq!(
-            Day10 {}.solve_part2(&mut vec!(16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4)),
-            8
+            Day10::default().solve_part2(&mut vec!(16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4)),
+            Ok(BigUint::from(8))
         );
     }
 
     #[test]
     fn part2_example2() {
         assert_eq!(
-            Day10 {}.solve_part2(&mut vec!(
+            Day10::default().solve_part2(&mut vec!(
                 28, 33, 18, 42, 31, 14, 46, 20, 48, 47, 24, 23, 49, 45, 19, 38, 39, 11, 1, 32, 25,
                 35, 8, 17, 7, 9, 4, 2, 34, 10, 3
             )),
-            19208
+            Ok(BigUint::from(19208))
+        );
+    }
+
+    #[test]
+    fn part2_beyond_64_bits() {
+        let mut adapters: Vec<usize> = (1..=100).collect();
+        assert_eq!(
+            Day10::default()
+                .solve_part2(&mut adapters)
+                .map(|arrangements| arrangements.to_string()),
+            Ok("180396380815100901214157639".to_string())
         );
     }
+
+    #[test]
+    fn cancelled() {
+        let cancel = Cancel::new();
+        cancel.cancel();
+        let mut day = Day10::default();
+        day.set_context(Context {
+            cancel,
+            ..Context::default()
+        });
+        day.parse_str("16\n10\n15\n5\n1\n11\n7\n19\n6\n12\n4\n")
+            .unwrap();
+        assert_eq!(day.part2(), Err(PuzzleError::Timeout));
+    }
 }
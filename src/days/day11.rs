@@ -1,120 +1,131 @@
-use crate::puzzle::{io, File, Puzzle};
-
-pub struct Day11;
-
-fn get_adjacent_occupied_seats(x: i64, y: i64, seatmap: &Vec<String>) -> usize {
-    let mut sum: usize = 0;
-    let points = [
-        (x - 1, y - 1),
-        (x, y - 1),
-        (x + 1, y - 1),
-        (x - 1, y),
-        (x + 1, y),
-        (x - 1, y + 1),
-        (x, y + 1),
-        (x + 1, y + 1),
-    ];
-    for (x2, y2) in points.iter() {
-        if (*x2 >= 0 && *x2 < seatmap[0].len() as i64) && (*y2 >= 0 && *y2 < seatmap.len() as i64) {
-            if seatmap[*y2 as usize].chars().nth(*x2 as usize).unwrap() == '#' {
-                sum += 1;
-            }
-        }
-    }
-    sum
+//! Day 11: Seating System. Runs the seating rules as a cellular automaton on the seats of the
+//! [`Grid`], where the neighbours of a seat are either the adjacent seats or the first seat visible
+//! in each direction, until nothing changes.
+
+use crate::automaton::{Automaton, BoundedGrid, Rule};
+use crate::cancel::Cancel;
+use crate::generate::{Generated, Generator, Rng};
+use crate::grid::{Grid, DIRECTIONS_8};
+use crate::puzzle::{read_lines, BufRead, Context, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day11 {
+    seatmap: Grid<char>,
+    context: Context,
 }
 
-fn get_visible_occupied_seats_(
-    mut x: i64,
-    mut y: i64,
-    dx: i64,
-    dy: i64,
-    seatmap: &Vec<String>,
-) -> usize {
-    loop {
-        x += dx;
-        y += dy;
-        if x < 0 || x >= seatmap[0].len() as i64 || y < 0 || y >= seatmap.len() as i64 {
-            return 0;
-        }
-        let place = seatmap[y as usize].chars().nth(x as usize).unwrap();
-        if place == '#' {
-            return 1;
-        }
-        if place == 'L' {
-            return 0;
-        }
-    }
+register_puzzle! {
+    puzzle: Day11,
+    year: 2020,
+    day: 11,
+    title: "Seating System",
+    input: Grid,
+    reads_input: true,
 }
 
-fn get_visible_occupied_seats(x: i64, y: i64, seatmap: &Vec<String>) -> usize {
-    get_visible_occupied_seats_(x, y, 0, 1, seatmap)
-        + get_visible_occupied_seats_(x, y, 0, -1, seatmap)
-        + get_visible_occupied_seats_(x, y, 1, 0, seatmap)
-        + get_visible_occupied_seats_(x, y, -1, 0, seatmap)
-        + get_visible_occupied_seats_(x, y, 1, 1, seatmap)
-        + get_visible_occupied_seats_(x, y, -1, -1, seatmap)
-        + get_visible_occupied_seats_(x, y, 1, -1, seatmap)
-        + get_visible_occupied_seats_(x, y, -1, 1, seatmap)
+// The seats next to (x, y).
+fn adjacent_seats(x: usize, y: usize, seatmap: &Grid<char>) -> Vec<(usize, usize)> {
+    seatmap
+        .neighbours8(x, y)
+        .filter(|(_, place)| **place != '.')
+        .map(|(pos, _)| pos)
+        .collect()
 }
 
-fn next_turn<F>(seatmap: &Vec<String>, occupied_seats_fn: F, max_neighbours: usize) -> Vec<String>
+// The first seat that can be seen from (x, y) in each direction.
+fn visible_seats(x: usize, y: usize, seatmap: &Grid<char>) -> Vec<(usize, usize)> {
+    DIRECTIONS_8
+        .iter()
+        .filter_map(|direction| {
+            seatmap
+                .ray(x, y, *direction)
+                .find(|(_, place)| **place != '.')
+        })
+        .map(|(pos, _)| pos)
+        .collect()
+}
+
+// Run the seating rules until nothing changes and count the occupied seats. An empty seat is taken
+// if none of its neighbouring seats are occupied, and an occupied seat is left if at least
+// 'max_neighbours' of them are. Gives up with the error of 'cancel' once it is cancelled.
+fn settle<F>(
+    seatmap: &Grid<char>,
+    neighbours: F,
+    max_neighbours: usize,
+    cancel: &Cancel,
+) -> Result<usize, PuzzleError>
 where
-    F: Fn(i64, i64, &Vec<String>) -> usize,
+    F: Fn(usize, usize, &Grid<char>) -> Vec<(usize, usize)>,
 {
-    let mut new_seatmap = seatmap.clone();
-    for y in 0..seatmap.len() {
-        for x in 0..seatmap[y].len() {
-            let occouped_neighbours = occupied_seats_fn(x as i64, y as i64, seatmap);
-            if seatmap[y].chars().nth(x).unwrap() == 'L' && occouped_neighbours == 0 {
-                unsafe { new_seatmap[y][..].as_bytes_mut()[x] = '#' as u8 };
-            } else if seatmap[y].chars().nth(x).unwrap() == '#'
-                && occouped_neighbours >= max_neighbours
-            {
-                unsafe { new_seatmap[y][..].as_bytes_mut()[x] = 'L' as u8 };
-            }
-        }
-    }
-    new_seatmap
+    let topology = BoundedGrid::new(
+        seatmap,
+        |place| *place != '.',
+        |x, y| neighbours(x, y, seatmap),
+    );
+    let survival: Vec<usize> = (0..max_neighbours).collect();
+    let automaton = Automaton::new(topology, Rule::new(&[0], &survival)).cancellable(cancel);
+    let occupied = seatmap
+        .iter()
+        .filter(|(_, place)| **place == '#')
+        .map(|(pos, _)| pos)
+        .collect();
+    automaton
+        .run_to_fixpoint(occupied)?
+        .map(|occupied| occupied.len())
+        .ok_or_else(|| PuzzleError::no_solution("the seating never settles"))
 }
 
-fn get_nof_occupied_seats(seatmap: &Vec<String>) -> usize {
-    return seatmap.iter().fold(0, |acc, x| {
-        acc + x
-            .chars()
-            .fold(0, |acc, y| if y == '#' { acc + 1 } else { acc })
-    });
+/// Parse the seat map of floor '.', empty seats 'L' and occupied seats '#'.
+pub fn parse_input(input: &[String]) -> Result<Grid<char>, PuzzleError> {
+    Grid::from_lines(input, "L.#")
 }
 
 impl Day11 {
-    fn solve_part1(&self, mut seatmap: Vec<String>) -> usize {
-        loop {
-            let new_seatmap = next_turn(&seatmap, get_adjacent_occupied_seats, 4);
-            if new_seatmap == seatmap {
-                return get_nof_occupied_seats(&new_seatmap);
-            }
-            seatmap = new_seatmap;
-        }
+    pub fn solve_part1(&self, seatmap: Grid<char>) -> Result<usize, PuzzleError> {
+        settle(&seatmap, adjacent_seats, 4, &self.context.cancel)
     }
 
-    fn solve_part2(&self, mut seatmap: Vec<String>) -> usize {
-        loop {
-            let new_seatmap = next_turn(&seatmap, get_visible_occupied_seats, 5);
-            if new_seatmap == seatmap {
-                return get_nof_occupied_seats(&new_seatmap);
-            }
-            seatmap = new_seatmap;
-        }
+    pub fn solve_part2(&self, seatmap: Grid<char>) -> Result<usize, PuzzleError> {
+        settle(&seatmap, visible_seats, 5, &self.context.cancel)
     }
 }
 
 impl Puzzle for Day11 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(input.clone()).to_string(),
-            self.solve_part2(input.clone()).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.seatmap = parse_input(&read_lines(input)?)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(self.seatmap.clone())?.to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(self.seatmap.clone())?.to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+// A seat layout of 'size' rows 100 places wide, with about one place in four floor. Some random
+// layouts end up flipping between two seatings forever, those are drawn again.
+impl Generator for Day11 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        loop {
+            let lines: Vec<String> = (0..size.max(1))
+                .map(|_| {
+                    (0..100)
+                        .map(|_| if rng.chance(0.25) { '.' } else { 'L' })
+                        .collect()
+                })
+                .collect();
+            let seatmap = parse_input(&lines).unwrap();
+            if self.solve_part1(seatmap.clone()).is_ok() && self.solve_part2(seatmap).is_ok() {
+                return Generated::from_lines(lines);
+            }
+        }
     }
 }
 
@@ -125,71 +136,87 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day11 {}.solve_part1(
-                vec!(
-                    "L.LL.LL.LL",
-                    "LLLLLLL.LL",
-                    "L.L.L..L..",
-                    "LLLL.LL.LL",
-                    "L.LL.LL.LL",
-                    "L.LLLLL.LL",
-                    "..L.L.....",
-                    "LLLLLLLLLL",
-                    "L.LLLLLL.L",
-                    "L.LLLLL.LL"
+            Day11::default().solve_part1(
+                parse_input(
+                    &vec!(
+                        "L.LL.LL.LL",
+                        "LLLLLLL.LL",
+                        "L.L.L..L..",
+                        "LLLL.LL.LL",
+                        "L.LL.LL.LL",
+                        "L.LLLLL.LL",
+                        "..L.L.....",
+                        "LLLLLLLLLL",
+                        "L.LLLLLL.L",
+                        "L.LLLLL.LL"
+                    )
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
                 )
-                .iter()
-                .map(|x| x.to_string())
-                .collect()
+                .unwrap()
             ),
-            37
+            Ok(37)
         );
     }
 
     #[test]
     fn part2_example1() {
         assert_eq!(
-            Day11 {}.solve_part2(
-                vec!(
-                    "L.LL.LL.LL",
-                    "LLLLLLL.LL",
-                    "L.L.L..L..",
-                    "LLLL.LL.LL",
-                    "L.LL.LL.LL",
-                    "L.LLLLL.LL",
-                    "..L.L.....",
-                    "LLLLLLLLLL",
-                    "L.LLLLLL.L",
-                    "L.LLLLL.LL"
+            Day11::default().solve_part2(
+                parse_input(
+                    &vec!(
+                        "L.LL.LL.LL",
+                        "LLLLLLL.LL",
+                        "L.L.L..L..",
+                        "LLLL.LL.LL",
+                        "L.LL.LL.LL",
+                        "L.LLLLL.LL",
+                        "..L.L.....",
+                        "LLLLLLLLLL",
+                        "L.LLLLLL.L",
+                        "L.LLLLL.LL"
+                    )
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
                 )
-                .iter()
-                .map(|x| x.to_string())
-                .collect()
+                .unwrap()
             ),
-            26
+            Ok(26)
         );
     }
 
+    fn visible_occupied_seats(x: usize, y: usize, seatmap: &Grid<char>) -> usize {
+        visible_seats(x, y, seatmap)
+            .iter()
+            .filter(|pos| seatmap[**pos] == '#')
+            .count()
+    }
+
     #[test]
     fn part2_get_visible_occupied_seats1() {
         assert_eq!(
-            get_visible_occupied_seats(
+            visible_occupied_seats(
                 3,
                 4,
-                &vec!(
-                    ".......#.",
-                    "...#.....",
-                    ".#.......",
-                    ".........",
-                    "..#L....#",
-                    "....#....",
-                    ".........",
-                    "#........",
-                    "...#....."
+                &parse_input(
+                    &vec!(
+                        ".......#.",
+                        "...#.....",
+                        ".#.......",
+                        ".........",
+                        "..#L....#",
+                        "....#....",
+                        ".........",
+                        "#........",
+                        "...#....."
+                    )
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
                 )
-                .iter()
-                .map(|x| x.to_string())
-                .collect()
+                .unwrap()
             ),
             8
         );
@@ -198,15 +225,32 @@ mod tests {
     #[test]
     fn part2_get_visible_occupied_seats2() {
         assert_eq!(
-            get_visible_occupied_seats(
+            visible_occupied_seats(
                 1,
                 1,
-                &vec!(".............", ".L.L.#.#.#.#.", ".............")
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect()
+                &parse_input(
+                    &vec!(".............", ".L.L.#.#.#.#.", ".............")
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<String>>()
+                )
+                .unwrap()
             ),
             0
         );
     }
+
+    #[test]
+    fn cancelled() {
+        let cancel = Cancel::new();
+        cancel.cancel();
+        let mut day = Day11::default();
+        day.set_context(Context {
+            cancel,
+            ..Context::default()
+        });
+        day.parse_str("L.LL\nLLLL\n").unwrap();
+        assert_eq!(day.part1(), Err(PuzzleError::Timeout));
+        assert_eq!(day.part2(), Err(PuzzleError::Timeout));
+    }
 }
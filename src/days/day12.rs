@@ -1,10 +1,27 @@
-use crate::puzzle::{io, File, Puzzle};
+//! Day 12: Rain Risk. [`parse_input`] turns the navigation instructions into [`Action`]s that
+//! steer the ship directly or move its waypoint.
+
+use crate::generate::{Generated, Generator, Rng};
+use crate::puzzle::{parse_token, read_lines, BufRead, Puzzle, PuzzleError};
 use std::mem;
 
-pub struct Day12;
+#[derive(Default)]
+pub struct Day12 {
+    actions: Vec<Action>,
+}
+
+register_puzzle! {
+    puzzle: Day12,
+    year: 2020,
+    day: 12,
+    title: "Rain Risk",
+    input: Lines,
+    reads_input: true,
+}
 
+/// A navigation instruction and its value.
 #[derive(Debug)]
-enum Action {
+pub enum Action {
     North(i64),
     South(i64),
     East(i64),
@@ -43,16 +60,10 @@ impl Ship {
             Action::East(distance) => self.pos.x += *distance as i64,
             Action::West(distance) => self.pos.x -= *distance as i64,
             Action::Left(degrees) => {
-                self.bearing -= degrees;
-                if self.bearing < 0 {
-                    self.bearing += 360;
-                }
+                self.bearing = (self.bearing - degrees).rem_euclid(360);
             }
             Action::Right(degrees) => {
-                self.bearing += degrees;
-                if self.bearing >= 360 {
-                    self.bearing -= 360;
-                }
+                self.bearing = (self.bearing + degrees).rem_euclid(360);
             }
             Action::Forward(distance) => match self.bearing {
                 0 => self.navigate_bearing(&Action::North(*distance)),
@@ -70,7 +81,7 @@ impl Ship {
             Action::South(distance) => self.waypoint.y -= *distance as i64,
             Action::East(distance) => self.waypoint.x += *distance as i64,
             Action::West(distance) => self.waypoint.x -= *distance as i64,
-            Action::Left(degrees) => match degrees % 360 {
+            Action::Left(degrees) => match degrees.rem_euclid(360) {
                 0 => (),
                 90 => {
                     mem::swap(&mut self.waypoint.x, &mut self.waypoint.y);
@@ -87,7 +98,7 @@ impl Ship {
                 _ => panic!("Illegal ship bearing!"),
             },
 
-            Action::Right(degrees) => match degrees % 360 {
+            Action::Right(degrees) => match degrees.rem_euclid(360) {
                 0 => (),
                 90 => {
                     mem::swap(&mut self.waypoint.x, &mut self.waypoint.y);
@@ -115,47 +126,87 @@ impl Ship {
     }
 }
 
-fn parse_action(action_str: &str) -> Action {
-    let val = action_str[1..].parse::<i64>().unwrap();
-    match action_str.chars().nth(0).unwrap() {
-        'N' => Action::North(val),
-        'S' => Action::South(val),
-        'E' => Action::East(val),
-        'W' => Action::West(val),
-        'L' => Action::Left(val),
-        'R' => Action::Right(val),
-        'F' => Action::Forward(val),
-        _ => panic!("Illegal action string!"),
+/// Parse instructions such as "F10" or "R90".
+pub fn parse_input(input: &[String]) -> Result<Vec<Action>, PuzzleError> {
+    let mut actions = vec![];
+    for (i, line) in input.iter().enumerate() {
+        let c = line
+            .chars()
+            .next()
+            .ok_or_else(|| PuzzleError::malformed(i, line, line, "missing action"))?;
+        let arg = &line[c.len_utf8()..];
+        let val = parse_token::<i64>(i, line, arg)?;
+        let action = match c {
+            'N' => Action::North(val),
+            'S' => Action::South(val),
+            'E' => Action::East(val),
+            'W' => Action::West(val),
+            'L' => Action::Left(val),
+            'R' => Action::Right(val),
+            'F' => Action::Forward(val),
+            _ => return Err(PuzzleError::malformed(i, line, line, "illegal action")),
+        };
+        if matches!(action, Action::Left(_) | Action::Right(_)) && val % 90 != 0 {
+            return Err(PuzzleError::malformed(
+                i,
+                line,
+                arg,
+                "turn is not a multiple of 90",
+            ));
+        }
+        actions.push(action);
     }
+    Ok(actions)
 }
 
 impl Day12 {
-    fn solve_part1(&self, input: Vec<String>) -> usize {
+    pub fn solve_part1(&self, actions: &[Action]) -> usize {
         let mut ship = Ship::new();
-        for line in &input {
-            let action = parse_action(line);
-            ship.navigate_bearing(&action);
+        for action in actions {
+            ship.navigate_bearing(action);
         }
         ship.manhattan_distance()
     }
 
-    fn solve_part2(&self, input: Vec<String>) -> usize {
+    pub fn solve_part2(&self, actions: &[Action]) -> usize {
         let mut ship = Ship::new();
-        for line in &input {
-            let action = parse_action(line);
-            ship.navigate_waypoint(&action);
+        for action in actions {
+            ship.navigate_waypoint(action);
         }
         ship.manhattan_distance()
     }
 }
 
 impl Puzzle for Day12 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(input.clone()).to_string(),
-            self.solve_part2(input.clone()).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.actions = parse_input(&read_lines(input)?)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&self.actions).to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(&self.actions).to_string())
+    }
+}
+
+// 'size' random navigation instructions.
+impl Generator for Day12 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let lines = (0..size)
+            .map(|_| match rng.below(7) {
+                0 => format!("N{}", rng.range(1..=5)),
+                1 => format!("S{}", rng.range(1..=5)),
+                2 => format!("E{}", rng.range(1..=5)),
+                3 => format!("W{}", rng.range(1..=5)),
+                4 => format!("L{}", rng.range(1..=3) * 90),
+                5 => format!("R{}", rng.range(1..=3) * 90),
+                _ => format!("F{}", rng.range(1..=100)),
+            })
+            .collect();
+        Generated::from_lines(lines)
     }
 }
 
@@ -166,11 +217,14 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day12 {}.solve_part1(
-                vec!("F10", "N3", "F7", "R90", "F11")
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect()
+            Day12::default().solve_part1(
+                &parse_input(
+                    &vec!("F10", "N3", "F7", "R90", "F11")
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<String>>()
+                )
+                .unwrap()
             ),
             25
         );
@@ -179,11 +233,14 @@ mod tests {
     #[test]
     fn part2_example1() {
         assert_eq!(
-            Day12 {}.solve_part2(
-                vec!("F10", "N3", "F7", "R90", "F11")
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect()
+            Day12::default().solve_part2(
+                &parse_input(
+                    &vec!("F10", "N3", "F7", "R90", "F11")
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<String>>()
+                )
+                .unwrap()
             ),
             286
         );
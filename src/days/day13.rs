@@ -1,113 +1,178 @@
-use crate::puzzle::{io, File, Puzzle};
-use std::sync::{Arc, Mutex};
-use std::thread;
-pub struct Day13;
-
-const NOF_WORKERS: i64 = 20;
-
-fn worker(
-    worker_id: i64,
-    nof_workers: i64,
-    buses: &Vec<(i64, i64)>,
-    stop: Arc<Mutex<bool>>,
-) -> Option<i64> {
-    let mut i: i64 = worker_id;
-    'outer: loop {
-        let time = buses[0].1 * i;
-        for (offset, id) in buses {
-            if ((time + offset) % id) != 0 {
-                i += nof_workers;
-                if (i - worker_id) % 10000000 == 0 {
-                    if *stop.lock().unwrap() {
-                        return None;
-                    }
-                }
-                continue 'outer;
+//! Day 13: Shuttle Search. The input is the earliest departure time and the bus list, which
+//! [`parse_buses`] turns into (offset, id) pairs. The second part is a system of congruences,
+//! solved with the Chinese remainder theorem.
+
+use crate::bigint::BigUint;
+use crate::generate::{Generated, Generator, Rng};
+use crate::json::Value;
+use crate::math;
+use crate::puzzle::{parse_token, read_lines, BufRead, Context, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day13 {
+    input: Vec<String>,
+    context: Context,
+}
+
+register_puzzle! {
+    puzzle: Day13,
+    year: 2020,
+    day: 13,
+    title: "Shuttle Search",
+    input: Lines,
+    reads_input: true,
+}
+
+fn get_line(input: &[String], i: usize) -> Result<&str, PuzzleError> {
+    input
+        .get(i)
+        .map(|line| &line[..])
+        .ok_or_else(|| PuzzleError::Parse {
+            line: i + 1,
+            column: 1,
+            text: String::new(),
+            reason: "missing line".to_string(),
+        })
+}
+
+/// Parse the bus list on the second line into (offset, id) pairs, skipping out of service buses.
+pub fn parse_buses(input: &[String]) -> Result<Vec<(i64, i64)>, PuzzleError> {
+    let line = get_line(input, 1)?;
+    let mut buses = vec![];
+    for (i, token) in line.split(',').enumerate() {
+        if token != "x" {
+            let id: i64 = parse_token(1, line, token)?;
+            if id <= 0 {
+                return Err(PuzzleError::malformed(
+                    1,
+                    line,
+                    token,
+                    "bus id must be positive",
+                ));
             }
+            buses.push((i as i64, id));
         }
-        *stop.lock().unwrap() = true;
-        return Some(time);
     }
+    if buses.is_empty() {
+        return Err(PuzzleError::no_solution("no buses in service"));
+    }
+    Ok(buses)
 }
 
 impl Day13 {
-    fn solve_part1(&self, input: Vec<String>) -> usize {
-        let mut min: usize = std::usize::MAX;
+    pub fn solve_part1(&self, input: Vec<String>) -> Result<usize, PuzzleError> {
+        let mut min: usize = usize::MAX;
         let mut min_id: usize = 0;
-        let earliest = input.iter().nth(0).unwrap().parse::<usize>().unwrap();
-        let buses: Vec<_> = input
-            .iter()
-            .nth(1)
-            .unwrap()
-            .split(",")
-            .map(|x| x.parse::<usize>())
-            .collect();
-        for bus in &buses {
-            if let Ok(id) = bus {
-                let depart = if (earliest % id) == 0 {
-                    earliest
-                } else {
-                    ((earliest / id) + 1) * id
-                };
-                if depart < min {
-                    min = depart;
-                    min_id = *id;
-                }
+        let line = get_line(&input, 0)?;
+        let earliest: usize = parse_token(0, line, line)?;
+        for (_, id) in parse_buses(&input)? {
+            let id = id as usize;
+            let depart = if (earliest % id) == 0 {
+                earliest
+            } else {
+                ((earliest / id) + 1) * id
+            };
+            if depart < min {
+                min = depart;
+                min_id = id;
             }
         }
-        min_id * (min - earliest)
+        self.context.trace.value("earliest bus", min_id as i64);
+        self.context.trace.value("departs at", min as i64);
+        Ok(min_id * (min - earliest))
     }
 
-    fn solve_part2(&self, input: Vec<String>) -> i64 {
-        let mut buses: Vec<_> = input
-            .iter()
-            .nth(1)
-            .unwrap()
-            .split(",")
-            .enumerate()
-            .map(|(i, x)| (i, x.parse::<i64>()))
-            .filter(|(_, x)| x.is_ok())
-            .map(|(i, x)| (i as i64, x.unwrap()))
-            .collect();
-
-        buses.sort_by(|(_, id1), (_, id2)| id2.partial_cmp(id1).unwrap());
-        let offset_adjustment = buses[0].0;
-        buses = buses
+    /// The earliest timestamp at which each bus departs its offset in minutes later, that is a
+    /// timestamp t with t + offset = 0 modulo the bus id for all buses.
+    pub fn solve_part2(&self, input: Vec<String>) -> Result<BigUint, PuzzleError> {
+        let congruences: Vec<(i128, i128)> = parse_buses(&input)?
             .iter()
-            .map(|(offset, id)| (*offset - buses[0].0, *id))
+            .map(|(offset, id)| (-i128::from(*offset), i128::from(*id)))
             .collect();
-
-        let results = Arc::new(Mutex::new(vec![]));
-        let stop = Arc::new(Mutex::new(false));
-        let mut handles = vec![];
-        for worker_id in 0..NOF_WORKERS {
-            let buses = buses.clone();
-            let results = Arc::clone(&results);
-            let stop = Arc::clone(&stop);
-            let handle = thread::spawn(move || {
-                if let Some(timestamp) = worker(worker_id, NOF_WORKERS, &buses, stop) {
-                    let mut results = results.lock().unwrap();
-                    (*results).push(timestamp);
-                }
-            });
-            handles.push(handle);
+        // The timestamp is 'residue' modulo each bus id.
+        if self.context.trace.is_recording() {
+            let rows = parse_buses(&input)?
+                .iter()
+                .zip(&congruences)
+                .map(|((offset, id), (r, n))| {
+                    let residue = r.rem_euclid(*n) as i64;
+                    vec![Value::Int(*id), Value::Int(*offset), Value::Int(residue)]
+                })
+                .collect();
+            let columns = ["bus", "offset", "residue"];
+            self.context.trace.table("congruences", &columns, rows);
         }
-        for handle in handles {
-            handle.join().unwrap();
+        // Enough buses with large ids overflow even an i128, fall back to arbitrary precision.
+        match math::crt(&congruences) {
+            Some((timestamp, _)) => Ok(BigUint::from_u128(timestamp as u128)),
+            None => math::crt_big(&congruences)
+                .map(|(timestamp, _)| timestamp)
+                .ok_or_else(|| PuzzleError::no_solution("the buses never line up")),
         }
-        (*results).lock().unwrap().sort();
-        let timestamp = (*results).lock().unwrap()[0];
-        timestamp - offset_adjustment
     }
 }
 
 impl Puzzle for Day13 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(input.clone()).to_string(),
-            self.solve_part2(input.clone()).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.input = read_lines(input)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(self.input.clone())?.to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(self.input.clone())?.to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+// 'size' buses, at most 160, with distinct prime ids below 1000. The timestamp of the second part
+// is picked first and every bus is placed at an offset that departs at the right time, which is
+// the earliest such timestamp as it is below the product of the ids.
+impl Generator for Day13 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let mut primes: Vec<u64> = (11..1000)
+            .filter(|n| (2..*n).take_while(|d| d * d <= *n).all(|d| n % d != 0))
+            .collect();
+        rng.shuffle(&mut primes);
+        primes.truncate(size.clamp(1, 160));
+        let product = primes
+            .iter()
+            .try_fold(1u64, |product, id| product.checked_mul(*id))
+            .unwrap_or(u64::MAX);
+        let timestamp = rng.next_u64() % product.min(1 << 62);
+
+        // The offset of each bus must be minus the timestamp modulo its id, move buses up by their
+        // id until they have an offset of their own.
+        let mut schedule: Vec<Option<u64>> = vec![];
+        for &id in &primes {
+            let mut offset = ((id - timestamp % id) % id) as usize;
+            while matches!(schedule.get(offset), Some(Some(_))) {
+                offset += id as usize;
+            }
+            if schedule.len() <= offset {
+                schedule.resize(offset + 1, None);
+            }
+            schedule[offset] = Some(id);
+        }
+        let earliest = rng.range(100_000..=1_000_000) as u64;
+        let (wait, id) = schedule
+            .iter()
+            .flatten()
+            .map(|id| ((id - earliest % id) % id, id))
+            .min_by_key(|(wait, _)| *wait)
+            .unwrap();
+        let line: Vec<String> = schedule
+            .iter()
+            .map(|id| id.map_or("x".to_string(), |id| id.to_string()))
+            .collect();
+        Generated::from_lines(vec![earliest.to_string(), line.join(",")])
+            .with_answers(wait * id, timestamp)
     }
 }
 
@@ -118,78 +183,125 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day13 {}.solve_part1(
+            Day13::default().solve_part1(
                 vec!("939", "7,13,x,x,59,x,31,19")
                     .iter()
                     .map(|x| x.to_string())
                     .collect()
             ),
-            295
+            Ok(295)
         );
     }
 
     #[test]
     fn part2_example1() {
         assert_eq!(
-            Day13 {}.solve_part2(
+            Day13::default().solve_part2(
                 vec!("939", "7,13,x,x,59,x,31,19")
                     .iter()
                     .map(|x| x.to_string())
                     .collect()
             ),
-            1068781
+            Ok(BigUint::from(1068781))
         );
     }
 
     #[test]
     fn part2_example2() {
         assert_eq!(
-            Day13 {}.solve_part2(
+            Day13::default().solve_part2(
                 vec!("", "67,7,59,61")
                     .iter()
                     .map(|x| x.to_string())
                     .collect()
             ),
-            754018
+            Ok(BigUint::from(754018))
         );
     }
 
     #[test]
     fn part2_example3() {
         assert_eq!(
-            Day13 {}.solve_part2(
+            Day13::default().solve_part2(
                 vec!("", "67,x,7,59,61")
                     .iter()
                     .map(|x| x.to_string())
                     .collect()
             ),
-            779210
+            Ok(BigUint::from(779210))
         );
     }
 
     #[test]
     fn part2_example4() {
         assert_eq!(
-            Day13 {}.solve_part2(
+            Day13::default().solve_part2(
                 vec!("", "67,7,x,59,61")
                     .iter()
                     .map(|x| x.to_string())
                     .collect()
             ),
-            1261476
+            Ok(BigUint::from(1261476))
         );
     }
 
     #[test]
     fn part2_example5() {
         assert_eq!(
-            Day13 {}.solve_part2(
+            Day13::default().solve_part2(
                 vec!("", "1789,37,47,1889")
                     .iter()
                     .map(|x| x.to_string())
                     .collect()
             ),
-            1202161486
+            Ok(BigUint::from(1202161486))
         );
     }
+
+    #[test]
+    fn part2_shared_factors() {
+        assert_eq!(
+            Day13::default().solve_part2(
+                ["", "x,6,x,4"]
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect()
+            ),
+            Ok(BigUint::from(5))
+        );
+        assert!(Day13::default()
+            .solve_part2(["", "4,6"].iter().map(|x| x.to_string()).collect())
+            .is_err());
+    }
+
+    #[test]
+    fn part2_large_ids() {
+        assert_eq!(
+            Day13::default().solve_part2(
+                ["", "999983,x,1000003,1000033"]
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect()
+            ),
+            Ok(BigUint::from(170669910579195711))
+        );
+        for (buses, timestamp) in &[
+            (
+                "1000000007,x,998244353,1000000009",
+                "374700138495202145106108358",
+            ),
+            (
+                "1000000007,x,998244353,1000000009,1000000021,1000000033",
+                "961867492764100459444794421829686711655503941",
+            ),
+        ] {
+            assert_eq!(
+                Day13::default()
+                    .solve_part2(["", buses].iter().map(|x| x.to_string()).collect())
+                    .unwrap()
+                    .to_string(),
+                *timestamp
+            );
+        }
+    }
 }
@@ -1,52 +1,85 @@
+//! Day 14: Docking Data. [`get_program`] parses the initialization program into [`Op`]s that
+//! apply the bitmask to either the values or the memory addresses.
+
 use std::collections::HashMap;
 use std::vec;
 
-use crate::puzzle::{io, File, Puzzle};
-pub struct Day14;
+use crate::cancel::Cancel;
+use crate::generate::{Generated, Generator, Rng};
+use crate::puzzle::{next_token, parse_token, read_lines, BufRead, Context, Puzzle, PuzzleError};
+use crate::reference::{Answer, Reference};
+
+#[derive(Default)]
+pub struct Day14 {
+    program: Vec<Op>,
+    context: Context,
+}
+
+register_puzzle! {
+    puzzle: Day14,
+    year: 2020,
+    day: 14,
+    title: "Docking Data",
+    input: Lines,
+    reads_input: true,
+    reference: true,
+}
 
+/// Either a mask, stored as the bits to set, the bits to clear and the floating bits, or a
+/// write of a value to an address.
 #[derive(Debug, Clone, Copy)]
-enum Op {
+pub enum Op {
     Mask(u64, u64, u64),
     Write(u64, u64),
 }
 
-fn get_program(input: &Vec<String>) -> Vec<Op> {
+/// Parse lines such as `mask = X1001X` and `mem[8] = 11`.
+pub fn get_program(input: &[String]) -> Result<Vec<Op>, PuzzleError> {
     let mut program = vec![];
-    for line in input {
-        let mut words = line.split(" ");
-        let cmd = words.nth(0).unwrap();
+    for (i, line) in input.iter().enumerate() {
+        let mut words = line.split(' ');
+        let cmd = next_token(&mut words, i, line)?;
+        next_token(&mut words, i, line)?;
+        let arg = next_token(&mut words, i, line)?;
         if cmd == "mask" {
-            let mask = words.nth(1).unwrap();
+            if arg.len() != 36 {
+                return Err(PuzzleError::malformed(i, line, arg, "expected 36 bits"));
+            }
             let mut clear_mask = 0;
             let mut set_mask = 0;
             let mut x_mask = 0;
-            for i in 0..mask.len() {
-                match mask.chars().nth(i).unwrap() {
-                    'X' => x_mask |= 1 << (35 - i),
-                    '0' => clear_mask |= 1 << (35 - i),
-                    '1' => set_mask |= 1 << (35 - i),
-                    _ => panic!("Illegal mask!"),
+            for (pos, c) in arg.char_indices() {
+                match c {
+                    'X' => x_mask |= 1 << (35 - pos),
+                    '0' => clear_mask |= 1 << (35 - pos),
+                    '1' => set_mask |= 1 << (35 - pos),
+                    _ => return Err(PuzzleError::malformed(i, line, &arg[pos..], "illegal mask")),
                 }
             }
             program.push(Op::Mask(clear_mask, set_mask, x_mask));
-        } else {
-            let val = words.nth(1).unwrap().parse::<u64>().unwrap();
-            let mut addr_split = cmd.split(|c| c == '[' || c == ']');
-            let addr = addr_split.nth(1).unwrap().parse::<u64>().unwrap();
+        } else if cmd.starts_with("mem[") && cmd.ends_with(']') {
+            let val = parse_token::<u64>(i, line, arg)?;
+            let addr = parse_token::<u64>(i, line, &cmd[4..cmd.len() - 1])?;
             program.push(Op::Write(addr, val));
+        } else {
+            return Err(PuzzleError::malformed(i, line, cmd, "illegal command"));
         }
     }
-    program
+    Ok(program)
 }
 
+// Write 'val' to every address the floating bits from 'offset' up make of 'addr'. Gives up with the
+// error of 'cancel' once it is cancelled.
 fn floating_write(
     val: u64,
     addr: u64,
     floating_mask: u64,
     offset: u64,
     mut mem: &mut HashMap<u64, u64>,
-) {
+    cancel: &Cancel,
+) -> Result<(), PuzzleError> {
     if offset == 36 {
+        cancel.check()?;
         mem.insert(addr, val);
     } else if (floating_mask >> offset) & 0x1 == 1 {
         floating_write(
@@ -55,27 +88,29 @@ fn floating_write(
             floating_mask,
             offset + 1,
             &mut mem,
-        );
+            cancel,
+        )?;
         floating_write(
             val,
             addr & !(1 << offset),
             floating_mask,
             offset + 1,
             &mut mem,
-        );
+            cancel,
+        )?;
     } else {
-        floating_write(val, addr, floating_mask, offset + 1, &mut mem);
+        floating_write(val, addr, floating_mask, offset + 1, &mut mem, cancel)?;
     }
+    Ok(())
 }
 
 impl Day14 {
-    fn solve_part1(&self, input: Vec<String>) -> usize {
-        let program = get_program(&input);
+    pub fn solve_part1(&self, program: &[Op]) -> usize {
         let mut mem: HashMap<u64, u64> = HashMap::new();
         let mut clear_mask: u64 = 0;
         let mut set_mask: u64 = 0;
         for op in program {
-            match op {
+            match *op {
                 Op::Mask(clear_mask_op, set_mask_op, _) => {
                     clear_mask = !clear_mask_op;
                     set_mask = set_mask_op;
@@ -90,12 +125,11 @@ impl Day14 {
         mem.iter().fold(0, |acc, (_, v)| acc + *v) as usize
     }
 
-    fn solve_part2(&self, input: Vec<String>) -> usize {
-        let program = get_program(&input);
+    pub fn solve_part2(&self, program: &[Op]) -> Result<usize, PuzzleError> {
         let mut mem: HashMap<u64, u64> = HashMap::new();
         let mut floating_mask: u64 = 0;
         let mut set_mask: u64 = 0;
-        for op in &program {
+        for op in program {
             match op {
                 Op::Mask(_, set_mask_op, floating_mask_op) => {
                     set_mask = *set_mask_op;
@@ -103,21 +137,99 @@ impl Day14 {
                 }
                 Op::Write(mut addr, val) => {
                     addr |= set_mask;
-                    floating_write(*val, addr, floating_mask, 0, &mut mem);
+                    floating_write(*val, addr, floating_mask, 0, &mut mem, &self.context.cancel)?;
                 }
             }
         }
-        mem.iter().fold(0, |acc, (_, v)| acc + *v) as usize
+        Ok(mem.iter().fold(0, |acc, (_, v)| acc + *v) as usize)
     }
 }
 
 impl Puzzle for Day14 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(input.clone()).to_string(),
-            self.solve_part2(input.clone()).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.program = get_program(&read_lines(input)?)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&self.program).to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(&self.program)?.to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+// 'size' instructions, a new mask with up to 8 floating bits every few writes.
+impl Generator for Day14 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let mut lines = vec![];
+        while lines.len() < size.max(1) {
+            let mut mask: Vec<char> = (0..36)
+                .map(|_| if rng.chance(0.5) { '0' } else { '1' })
+                .collect();
+            for _ in 0..rng.range(0..=8) {
+                mask[rng.below(36)] = 'X';
+            }
+            lines.push(format!("mask = {}", mask.into_iter().collect::<String>()));
+            for _ in 0..rng.range(1..=5) {
+                lines.push(format!(
+                    "mem[{}] = {}",
+                    rng.below(1 << 16),
+                    rng.below(1 << 30)
+                ));
+            }
+        }
+        Generated::from_lines(lines)
+    }
+}
+
+// Apply the masks one bit at a time, and write to the floating addresses by counting through all
+// values of the floating bits.
+impl Reference for Day14 {
+    fn reference(&self, part: u8) -> Option<Answer> {
+        let mut mem: HashMap<u64, u64> = HashMap::new();
+        let (mut clear, mut set, mut floating) = (0, 0, 0);
+        for op in &self.program {
+            match *op {
+                Op::Mask(clear_op, set_op, floating_op) => {
+                    clear = clear_op;
+                    set = set_op;
+                    floating = floating_op;
+                }
+                Op::Write(addr, val) if part == 1 => {
+                    let mut val = val;
+                    for bit in 0..36 {
+                        if clear >> bit & 1 == 1 {
+                            val &= !(1 << bit);
+                        }
+                        if set >> bit & 1 == 1 {
+                            val |= 1 << bit;
+                        }
+                    }
+                    mem.insert(addr, val);
+                }
+                Op::Write(addr, val) => {
+                    let bits: Vec<u64> = (0..36).filter(|bit| floating >> bit & 1 == 1).collect();
+                    for n in 0..1u64 << bits.len() {
+                        let mut addr = addr | set;
+                        for (i, bit) in bits.iter().enumerate() {
+                            if n >> i & 1 == 1 {
+                                addr |= 1 << bit;
+                            } else {
+                                addr &= !(1 << bit);
+                            }
+                        }
+                        mem.insert(addr, val);
+                    }
+                }
+            }
+        }
+        Some(Ok(mem.values().sum::<u64>().to_string()))
     }
 }
 
@@ -128,16 +240,19 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day14 {}.solve_part1(
-                vec!(
-                    "mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X",
-                    "mem[8] = 11",
-                    "mem[7] = 101",
-                    "mem[8] = 0"
+            Day14::default().solve_part1(
+                &get_program(
+                    &vec!(
+                        "mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X",
+                        "mem[8] = 11",
+                        "mem[7] = 101",
+                        "mem[8] = 0"
+                    )
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
                 )
-                .iter()
-                .map(|x| x.to_string())
-                .collect()
+                .unwrap()
             ),
             165
         );
@@ -145,18 +260,35 @@ mod tests {
     #[test]
     fn part2_example1() {
         assert_eq!(
-            Day14 {}.solve_part2(
-                vec!(
-                    "mask = 000000000000000000000000000000X1001X",
-                    "mem[42] = 100",
-                    "mask = 00000000000000000000000000000000X0XX",
-                    "mem[26] = 1"
+            Day14::default().solve_part2(
+                &get_program(
+                    &vec!(
+                        "mask = 000000000000000000000000000000X1001X",
+                        "mem[42] = 100",
+                        "mask = 00000000000000000000000000000000X0XX",
+                        "mem[26] = 1"
+                    )
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
                 )
-                .iter()
-                .map(|x| x.to_string())
-                .collect()
+                .unwrap()
             ),
-            208
+            Ok(208)
         );
     }
+
+    #[test]
+    fn cancelled() {
+        let cancel = Cancel::new();
+        cancel.cancel();
+        let mut day = Day14::default();
+        day.set_context(Context {
+            cancel,
+            ..Context::default()
+        });
+        day.parse_str("mask = 000000000000000000000000000000X1001X\nmem[42] = 100\n")
+            .unwrap();
+        assert_eq!(day.part2(), Err(PuzzleError::Timeout));
+    }
 }
@@ -1,10 +1,51 @@
+//! Day 15: Rambunctious Recitation. [`last_spoken`] plays the memory game for any number of
+//! turns, set with the 'part1_turns' and 'part2_turns' parameters.
+
 use std::collections::HashMap;
-use std::vec;
 
-use crate::puzzle::{io, File, Puzzle};
-pub struct Day15;
+use crate::cancel::Cancel;
+use crate::generate::{Generated, Generator, Rng};
+use crate::params::Param;
+use crate::progress::Progress;
+use crate::puzzle::{parse_token, read_lines, single_line, BufRead, Context, Puzzle, PuzzleError};
+use crate::reference::{Answer, Reference};
+
+#[derive(Default)]
+pub struct Day15 {
+    numbers: Vec<usize>,
+    context: Context,
+}
+
+const PART1_TURNS: Param = Param::integer("part1_turns", 2020, 1, "The turn of part 1's answer");
+const PART2_TURNS: Param = Param::integer(
+    "part2_turns",
+    30000000,
+    1,
+    "The turn of part 2's answer",
+);
 
-fn last_spoken(input: &Vec<usize>, nof_turns: usize) -> usize {
+register_puzzle! {
+    puzzle: Day15,
+    year: 2020,
+    day: 15,
+    title: "Rambunctious Recitation",
+    input: SingleLine,
+    reads_input: true,
+    params: &[PART1_TURNS, PART2_TURNS],
+    reference: true,
+}
+
+/// Get the number spoken on turn 'nof_turns' when starting with the numbers in 'input'. Gives up
+/// with the error of 'cancel' once it is cancelled, and reports the turns played to 'progress'.
+pub fn last_spoken(
+    input: &[usize],
+    nof_turns: usize,
+    cancel: &Cancel,
+    progress: &Progress,
+) -> Result<usize, PuzzleError> {
+    if nof_turns <= input.len() {
+        return Ok(input[nof_turns - 1]);
+    }
     let mut numbers = HashMap::new();
     let mut starting_no: usize = 1;
     let mut last_spoken: usize = 0;
@@ -16,32 +57,94 @@ fn last_spoken(input: &Vec<usize>, nof_turns: usize) -> usize {
         starting_no += 1;
     }
     for turn in starting_no..nof_turns + 1 {
+        cancel.check()?;
+        progress.update(turn, Some(nof_turns));
         if let Some(prev_turn) = numbers.insert(last_spoken, turn - 1) {
             last_spoken = turn - 1 - prev_turn;
         } else {
             last_spoken = 0;
         }
     }
-    last_spoken
+    Ok(last_spoken)
 }
 
 impl Day15 {
-    fn solve_part1(&self, input: Vec<usize>) -> usize {
-        last_spoken(&input, 2020)
+    fn turns(&self, param: &Param) -> usize {
+        self.context.params.integer(param) as usize
     }
 
-    fn solve_part2(&self, input: Vec<usize>) -> usize {
-        last_spoken(&input, 30000000)
+    pub fn solve_part1(&self, input: Vec<usize>) -> Result<usize, PuzzleError> {
+        last_spoken(
+            &input,
+            self.turns(&PART1_TURNS),
+            &self.context.cancel,
+            &self.context.progress,
+        )
+    }
+
+    pub fn solve_part2(&self, input: Vec<usize>) -> Result<usize, PuzzleError> {
+        last_spoken(
+            &input,
+            self.turns(&PART2_TURNS),
+            &self.context.cancel,
+            &self.context.progress,
+        )
     }
 }
 
 impl Puzzle for Day15 {
-    fn solve(&self, _lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input = vec![1, 0, 18, 10, 19, 6];
-        return (
-            self.solve_part1(input.clone()).to_string(),
-            self.solve_part2(input.clone()).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        let lines = read_lines(input)?;
+        let line = single_line(&lines)?;
+        self.numbers = line
+            .split(',')
+            .map(|token| parse_token(0, line, token))
+            .collect::<Result<Vec<usize>, PuzzleError>>()?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(self.numbers.clone())?.to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(self.numbers.clone())?.to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+// 'size' distinct starting numbers.
+impl Generator for Day15 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let mut numbers: Vec<usize> = (0..size.max(1) * 3).collect();
+        rng.shuffle(&mut numbers);
+        numbers.truncate(size.max(1));
+        let line: Vec<String> = numbers.iter().map(|x| x.to_string()).collect();
+        Generated::from_lines(vec![line.join(",")])
+    }
+}
+
+// Look back through everything spoken so far for the previous time the last number was spoken.
+// Far too slow for the second part.
+impl Reference for Day15 {
+    fn reference(&self, part: u8) -> Option<Answer> {
+        if part != 1 {
+            return None;
+        }
+        let turns = self.turns(&PART1_TURNS);
+        let mut spoken = self.numbers.clone();
+        while spoken.len() < turns {
+            let (last, before) = spoken.split_last().unwrap();
+            let next = match before.iter().rposition(|n| n == last) {
+                Some(turn) => before.len() - turn,
+                None => 0,
+            };
+            spoken.push(next);
+        }
+        Some(Ok(spoken[turns - 1].to_string()))
     }
 }
 
@@ -51,13 +154,28 @@ mod tests {
 
     #[test]
     fn part1_example1() {
-        assert_eq!(Day15 {}.solve_part1(vec!(0, 3, 6)), 436);
+        assert_eq!(Day15::default().solve_part1(vec!(0, 3, 6)), Ok(436));
+    }
+
+    #[test]
+    fn malformed_numbers() {
+        assert_eq!(
+            Day15::default().parse_str("1,0,x8,10\n").err(),
+            Some(PuzzleError::Parse {
+                line: 1,
+                column: 5,
+                text: "x8".to_string(),
+                reason: "invalid digit found in string".to_string(),
+            })
+        );
+        assert!(Day15::default().parse_str("").is_err());
+        assert!(Day15::default().parse_str("1,2\n3,4\n").is_err());
     }
 
     // Disable this unit test since its too slow to run for every build.
     // #[test]
     // fn part2_example1() {
     //     assert_eq!(
-    //         Day15 {}.solve_part2(vec!(0,3,6)), 175594);
+    //         Day15::default().solve_part2(vec!(0,3,6)), Ok(175594));
     // }
 }
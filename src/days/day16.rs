@@ -1,18 +1,34 @@
+//! Day 16: Ticket Translation. [`parse_input`] reads the ticket [`Notes`]: the field rules, your
+//! ticket and the nearby tickets.
+
 use std::vec;
 
-use crate::puzzle::{io, File, Puzzle};
-pub struct Day16;
+use crate::generate::{Generated, Generator, Rng};
+use crate::graph::{Bipartite, MatchingError};
+use crate::json::Value;
+use crate::parse::{key_value, named_sections, numbers};
+use crate::puzzle::{read_lines, BufRead, Context, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day16 {
+    notes: Notes,
+    context: Context,
+}
 
-enum ParseState {
-    Field,
-    MyTicket,
-    NearbyTickets,
+register_puzzle! {
+    puzzle: Day16,
+    year: 2020,
+    day: 16,
+    title: "Ticket Translation",
+    input: Sections,
+    reads_input: true,
 }
 
+/// A ticket field and the inclusive ranges of values that are valid for it.
 #[derive(Debug, Clone)]
-struct Field {
-    name: String,
-    valid_ranges: Vec<(usize, usize)>,
+pub struct Field {
+    pub name: String,
+    pub valid_ranges: Vec<(usize, usize)>,
 }
 
 impl Field {
@@ -22,19 +38,25 @@ impl Field {
             valid_ranges: vec![],
         }
     }
-    fn parse(data: &str) -> Field {
-        let mut split1 = data.split(":");
-        let mut field = Field::new(split1.next().unwrap());
-        let ranges_split = split1.next().unwrap().split("or");
-        for range in ranges_split {
-            let mut range_split = range.split("-");
-            let min = range_split.next().unwrap().trim().parse::<usize>().unwrap();
-            let max = range_split.next().unwrap().trim().parse::<usize>().unwrap();
-            field.valid_ranges.push((min, max));
+    fn parse(line: usize, data: &str) -> Result<Field, PuzzleError> {
+        let (name, ranges) = key_value(line, data, data, ':')?;
+        let mut field = Field::new(name);
+        for range in ranges.split(" or ") {
+            match numbers(line, data, range, "-")?[..] {
+                [min, max] => field.valid_ranges.push((min, max)),
+                _ => {
+                    return Err(PuzzleError::malformed(
+                        line,
+                        data,
+                        range,
+                        "expected min-max",
+                    ))
+                }
+            }
         }
-        field
+        Ok(field)
     }
-    fn is_valid_value(&self, val: usize) -> bool {
+    pub fn is_valid_value(&self, val: usize) -> bool {
         for (min, max) in &self.valid_ranges {
             if val >= *min && val <= *max {
                 return true;
@@ -44,48 +66,47 @@ impl Field {
     }
 }
 
-fn parse_input(input: &Vec<String>) -> (Vec<Field>, Vec<usize>, Vec<Vec<usize>>) {
+fn parse_ticket(line: usize, data: &str) -> Result<Vec<usize>, PuzzleError> {
+    numbers(line, data, data, ",")
+}
+
+/// The fields, your ticket and the nearby tickets.
+pub type Notes = (Vec<Field>, Vec<usize>, Vec<Vec<usize>>);
+
+/// Parse the three blank line separated sections of the notes.
+pub fn parse_input(input: &[String]) -> Result<Notes, PuzzleError> {
+    let sections = named_sections(input, &["", "your ticket", "nearby tickets"])?;
     let mut fields = vec![];
+    for (i, line) in sections[0].lines() {
+        fields.push(Field::parse(i, line)?);
+    }
     let mut my_ticket = vec![];
+    for (i, line) in sections[1].lines() {
+        my_ticket = parse_ticket(i, line)?;
+    }
     let mut nearby_tickets: Vec<Vec<usize>> = vec![];
-    let mut state = ParseState::Field;
-    for line in input {
-        if line == "" {
-            continue;
-        }
-        match state {
-            ParseState::Field => match &line[..] {
-                "your ticket:" => state = ParseState::MyTicket,
-                _ => fields.push(Field::parse(&line)),
-            },
-            ParseState::MyTicket => match &line[..] {
-                "nearby tickets:" => state = ParseState::NearbyTickets,
-                _ => {
-                    my_ticket = line
-                        .split(",")
-                        .map(|x| x.parse::<usize>().unwrap())
-                        .collect()
-                }
-            },
-            ParseState::NearbyTickets => {
-                nearby_tickets.push(
-                    line.split(",")
-                        .map(|x| x.parse::<usize>().unwrap())
-                        .collect(),
-                );
-            }
+    for (i, line) in sections[2].lines() {
+        let ticket = parse_ticket(i, line)?;
+        if ticket.len() != my_ticket.len() {
+            return Err(PuzzleError::malformed(
+                i,
+                line,
+                line,
+                "wrong number of values",
+            ));
         }
+        nearby_tickets.push(ticket);
     }
-    (fields, my_ticket, nearby_tickets)
+    Ok((fields, my_ticket, nearby_tickets))
 }
 
 impl Day16 {
-    fn solve_part1(&self, input: Vec<String>) -> usize {
-        let (fields, _, nearby_tickets) = parse_input(&input);
+    pub fn solve_part1(&self, notes: &Notes) -> usize {
+        let (fields, _, nearby_tickets) = notes;
         let mut ticket_scanning_error_rate = 0;
-        for ticket in &nearby_tickets {
+        for ticket in nearby_tickets {
             'value_loop: for val in ticket {
-                for field in &fields {
+                for field in fields {
                     if field.is_valid_value(*val) {
                         continue 'value_loop;
                     }
@@ -96,70 +117,67 @@ impl Day16 {
         ticket_scanning_error_rate
     }
 
-    fn solve_part2(&self, input: Vec<String>, field_name: &str) -> usize {
-        // Parse input
-        let (fields, my_ticket, mut nearby_tickets) = parse_input(&input);
+    pub fn solve_part2(&self, notes: &Notes, field_name: &str) -> Result<usize, PuzzleError> {
+        let (fields, my_ticket, nearby_tickets) = notes;
 
-        // Initialize each position in the vector with all possible candidate fields.
-        let mut positions = vec![fields.clone(); my_ticket.len()];
+        // Leave out the tickets with values that are not valid for any field.
+        let valid_tickets: Vec<&Vec<usize>> = nearby_tickets
+            .iter()
+            .filter(|ticket| {
+                ticket
+                    .iter()
+                    .all(|val| fields.iter().any(|field| field.is_valid_value(*val)))
+            })
+            .collect();
 
-        // Clean out invalid tickets from nearby tickets vector.
-        nearby_tickets.retain(|ticket| {
-            'value_loop: for val in ticket {
-                for field in &fields {
-                    if field.is_valid_value(*val) {
-                        continue 'value_loop;
-                    }
+        // A field is a candidate for a position if all values at that position are valid for it.
+        let mut candidates = Bipartite::new(my_ticket.len(), fields.len());
+        for pos in 0..my_ticket.len() {
+            for (i, field) in fields.iter().enumerate() {
+                if valid_tickets
+                    .iter()
+                    .all(|ticket| field.is_valid_value(ticket[pos]))
+                {
+                    candidates.add_edge(pos, i);
                 }
-                return false;
-            }
-            return true;
-        });
-
-        // Go through nearby tickets and remove position candidates for which values are not valid.
-        for ticket in &nearby_tickets {
-            for (pos, val) in ticket.iter().enumerate() {
-                positions[pos].retain(|field| field.is_valid_value(*val));
             }
         }
-
-        // When there is a single candidate left in one position, remove that candidate from all other positions.
-        // Keep doing this until no changes are made.
-        let mut go_again = true;
-        while go_again {
-            go_again = false;
-            for (i, candidates) in positions.clone().iter().enumerate() {
-                if candidates.len() == 1 {
-                    let field_found = &candidates[0];
-                    for (i2, candidates2) in positions.iter_mut().enumerate() {
-                        if i != i2 {
-                            candidates2.retain(|field| {
-                                if field.name != field_found.name {
-                                    true
-                                } else {
-                                    go_again = true;
-                                    false
-                                }
-                            });
-                        }
-                    }
-                }
+        let positions: Vec<&Field> = match candidates.unique_matching() {
+            Ok(matching) => matching.iter().map(|&i| &fields[i]).collect(),
+            Err(MatchingError::Unmatched(pos)) => {
+                return Err(PuzzleError::NoSolution(format!(
+                    "no field left for position {}",
+                    pos
+                )))
             }
-        }
+            Err(MatchingError::Ambiguous(positions)) => {
+                return Err(PuzzleError::NoSolution(format!(
+                    "several fields fit position {}",
+                    positions[0]
+                )))
+            }
+        };
 
-        // Assert that there is only a single field left for each position.
-        // Also map from vector of candidates to a single Field.
-        let positions: Vec<Field> = positions
-            .iter()
-            .map(|candidates| {
-                assert_eq!(1, candidates.len());
-                candidates[0].clone()
-            })
-            .collect();
+        if self.context.trace.is_recording() {
+            let rows = positions
+                .iter()
+                .zip(my_ticket)
+                .enumerate()
+                .map(|(pos, (field, val))| {
+                    vec![
+                        Value::Int(pos as i64),
+                        Value::from(&field.name[..]),
+                        Value::Int(*val as i64),
+                    ]
+                })
+                .collect();
+            let columns = ["position", "field", "my value"];
+            self.context.trace.table("fields", &columns, rows);
+        }
 
         // Go through my tickets values and multiply all fields where the field name starts with
         // the specified string.
-        my_ticket
+        Ok(my_ticket
             .iter()
             .zip(positions.iter())
             .fold(1, |acc, (val, field)| {
@@ -168,17 +186,142 @@ impl Day16 {
                 } else {
                     acc
                 }
-            })
+            }))
     }
 }
 
 impl Puzzle for Day16 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(input.clone()).to_string(),
-            self.solve_part2(input.clone(), "departure").to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.notes = parse_input(&read_lines(input)?)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&self.notes).to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(&self.notes, "departure")?.to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+// 20 fields and 'size' valid nearby tickets plus about one invalid ticket in five. Every field
+// gets a rank, and the valid values of a field are those of the fields ranked below it plus a few
+// of its own. The valid tickets have values of every field's own at its position, so the candidate
+// fields of a position are the fields ranked at or above the field really there, which can only be
+// matched in one way.
+impl Generator for Day16 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let mut names = [
+            "departure location",
+            "departure station",
+            "departure platform",
+            "departure track",
+            "departure date",
+            "departure time",
+            "arrival location",
+            "arrival station",
+            "arrival platform",
+            "arrival track",
+            "class",
+            "duration",
+            "price",
+            "route",
+            "row",
+            "seat",
+            "train",
+            "type",
+            "wagon",
+            "zone",
+        ];
+        let n = names.len();
+        // The fields by rank, and their ranges around a gap that is valid for no field.
+        rng.shuffle(&mut names);
+        let gap = rng.range(300..=500);
+        let gap = (gap, gap + rng.range(10..=50));
+        let mut ranges = vec![(rng.range(100..=150), rng.range(700..=800))];
+        for rank in 1..n {
+            let (low, high) = ranges[rank - 1];
+            ranges.push((low - rng.range(1..=3), high + rng.range(1..=3)));
+        }
+        let core = |rng: &mut Rng| {
+            if rng.chance(0.5) {
+                rng.range(ranges[0].0..=gap.0 - 1)
+            } else {
+                rng.range(gap.1 + 1..=ranges[0].1)
+            }
+        };
+
+        // The rank of the field at each position.
+        let mut layout: Vec<usize> = (0..n).collect();
+        rng.shuffle(&mut layout);
+        let mut tickets: Vec<Vec<i64>> = (0..size.max(1))
+            .map(|_| (0..n).map(|_| core(rng)).collect())
+            .collect();
+        for (pos, &rank) in layout.iter().enumerate().filter(|(_, &rank)| rank > 0) {
+            let ((low, high), (prev_low, prev_high)) = (ranges[rank], ranges[rank - 1]);
+            let own = if rng.chance(0.5) {
+                rng.range(low..=prev_low - 1)
+            } else {
+                rng.range(prev_high + 1..=high)
+            };
+            let ticket = rng.below(tickets.len());
+            tickets[ticket][pos] = own;
+        }
+        let mut error_rate = 0;
+        for _ in 0..size / 5 {
+            let mut ticket: Vec<i64> = (0..n).map(|_| core(rng)).collect();
+            let invalid = if rng.chance(0.5) {
+                rng.range(gap.0..=gap.1)
+            } else {
+                rng.range(ranges[n - 1].1 + 1..=999)
+            };
+            ticket[rng.below(n)] = invalid;
+            error_rate += invalid;
+            tickets.push(ticket);
+        }
+        rng.shuffle(&mut tickets);
+
+        let mine: Vec<i64> = (0..n).map(|_| rng.range(50..=999)).collect();
+        let departure: i64 = layout
+            .iter()
+            .zip(&mine)
+            .filter(|(&rank, _)| names[rank].starts_with("departure"))
+            .map(|(_, value)| value)
+            .product();
+        let mut fields: Vec<String> = (0..n)
+            .map(|rank| {
+                let (low, high) = ranges[rank];
+                format!(
+                    "{}: {}-{} or {}-{}",
+                    names[rank],
+                    low,
+                    gap.0 - 1,
+                    gap.1 + 1,
+                    high
+                )
+            })
+            .collect();
+        rng.shuffle(&mut fields);
+        let ticket_line = |ticket: &Vec<i64>| {
+            ticket
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+        let mut lines = fields;
+        lines.push(String::new());
+        lines.push("your ticket:".to_string());
+        lines.push(ticket_line(&mine));
+        lines.push(String::new());
+        lines.push("nearby tickets:".to_string());
+        lines.extend(tickets.iter().map(ticket_line));
+        Generated::from_lines(lines).with_answers(error_rate, departure)
     }
 }
 
@@ -189,24 +332,27 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day16 {}.solve_part1(
-                vec!(
-                    "class: 1-3 or 5-7",
-                    "row: 6-11 or 33-44",
-                    "seat: 13-40 or 45-50",
-                    "",
-                    "your ticket:",
-                    "7,1,14",
-                    "",
-                    "nearby tickets:",
-                    "7,3,47",
-                    "40,4,50",
-                    "55,2,20",
-                    "38,6,12"
+            Day16::default().solve_part1(
+                &parse_input(
+                    &vec!(
+                        "class: 1-3 or 5-7",
+                        "row: 6-11 or 33-44",
+                        "seat: 13-40 or 45-50",
+                        "",
+                        "your ticket:",
+                        "7,1,14",
+                        "",
+                        "nearby tickets:",
+                        "7,3,47",
+                        "40,4,50",
+                        "55,2,20",
+                        "38,6,12"
+                    )
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
                 )
-                .iter()
-                .map(|x| x.to_string())
-                .collect()
+                .unwrap()
             ),
             71
         );
@@ -215,26 +361,58 @@ mod tests {
     #[test]
     fn part2_example1() {
         assert_eq!(
-            Day16 {}.solve_part2(
-                vec!(
-                    "class: 0-1 or 4-19",
-                    "row: 0-5 or 8-19",
-                    "seat: 0-13 or 16-19",
-                    "",
-                    "your ticket:",
-                    "11,12,13",
-                    "",
-                    "nearby tickets:",
-                    "3,9,18",
-                    "15,1,5",
-                    "5,14,9",
+            Day16::default()
+                .solve_part2(
+                    &parse_input(
+                        &vec!(
+                            "class: 0-1 or 4-19",
+                            "row: 0-5 or 8-19",
+                            "seat: 0-13 or 16-19",
+                            "",
+                            "your ticket:",
+                            "11,12,13",
+                            "",
+                            "nearby tickets:",
+                            "3,9,18",
+                            "15,1,5",
+                            "5,14,9",
+                        )
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<String>>()
+                    )
+                    .unwrap(),
+                    "class"
                 )
-                .iter()
-                .map(|x| x.to_string())
-                .collect(),
-                "class"
-            ),
+                .unwrap(),
             12
         );
     }
+
+    #[test]
+    fn part2_ambiguous_fields() {
+        // Both fields fit both positions.
+        let notes = parse_input(
+            &[
+                "a: 0-9 or 20-29",
+                "b: 0-9 or 30-39",
+                "",
+                "your ticket:",
+                "1,2",
+                "",
+                "nearby tickets:",
+                "3,4",
+            ]
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>(),
+        )
+        .unwrap();
+        assert_eq!(
+            Day16::default().solve_part2(&notes, "a"),
+            Err(PuzzleError::NoSolution(
+                "several fields fit position 0".to_string()
+            ))
+        );
+    }
 }
@@ -1,135 +1,123 @@
+//! Day 17: Conway Cubes. The active cubes of the initial slice are booted for six cycles, or as
+//! many as the 'cycles' parameter says, of a cellular automaton on the three or four dimensional
+//! [`Lattice`].
+
 use std::collections::HashSet;
 
-use crate::puzzle::{io, File, Puzzle};
-pub struct Day17;
+use crate::automaton::{Automaton, Lattice, Rule};
+use crate::cancel::Cancel;
+use crate::generate::{Generated, Generator, Rng};
+use crate::params::Param;
+use crate::puzzle::{read_lines, BufRead, Context, Puzzle, PuzzleError};
 
-enum Dimensions {
-    D3,
-    D4,
+#[derive(Default)]
+pub struct Day17 {
+    cubes: HashSet<(i64, i64)>,
+    context: Context,
 }
 
-fn neighbours(x: i64, y: i64, z: i64, w: i64, cubes: &HashSet<(i64, i64, i64, i64)>) -> usize {
-    let mut neighbours = 0;
-    for x2 in x - 1..x + 2 {
-        for y2 in y - 1..y + 2 {
-            for z2 in z - 1..z + 2 {
-                for w2 in w - 1..w + 2 {
-                    if x == x2 && y == y2 && z == z2 && w == w2 {
-                        continue;
-                    }
-                    if let Some(_) = cubes.get(&(x2, y2, z2, w2)) {
-                        neighbours += 1;
-                    }
-                }
-            }
-        }
-    }
-    neighbours
+const CYCLES: Param = Param::integer("cycles", 6, 0, "The number of boot cycles");
+
+register_puzzle! {
+    puzzle: Day17,
+    year: 2020,
+    day: 17,
+    title: "Conway Cubes",
+    input: Grid,
+    reads_input: true,
+    params: &[CYCLES],
 }
 
-fn parse_input(input: &Vec<String>) -> HashSet<(i64, i64, i64, i64)> {
+/// Get the (x, y) coordinates of the active '#' cubes of the initial slice.
+pub fn parse_input(input: &[String]) -> Result<HashSet<(i64, i64)>, PuzzleError> {
     let mut cubes = HashSet::new();
     for (y, line) in input.iter().enumerate() {
-        for (x, c) in line.chars().enumerate() {
+        for (x, c) in line.char_indices() {
             match c {
                 '.' => (),
                 '#' => {
-                    cubes.insert((x as i64, y as i64, 0i64, 0i64));
+                    cubes.insert((x as i64, y as i64));
+                }
+                _ => {
+                    return Err(PuzzleError::malformed(
+                        y,
+                        line,
+                        &line[x..],
+                        "illegal input character",
+                    ))
                 }
-                _ => panic!("Illegal input character"),
             }
         }
     }
-    cubes
+    Ok(cubes)
 }
 
-fn get_max(cubes: &HashSet<(i64, i64, i64, i64)>) -> (i64, i64, i64, i64) {
-    cubes.iter().fold(
-        (std::i64::MIN, std::i64::MIN, std::i64::MIN, std::i64::MIN),
-        |(maxx, maxy, maxz, maxw), (x, y, z, w)| {
-            (
-                std::cmp::max(maxx, *x),
-                std::cmp::max(maxy, *y),
-                std::cmp::max(maxz, *z),
-                std::cmp::max(maxw, *w),
-            )
-        },
-    )
+// Run 'cycles' boot cycles in N dimensions, with the initial slice at 0 in the other dimensions,
+// and count the active cubes.
+fn boot<const N: usize>(
+    cubes: &HashSet<(i64, i64)>,
+    cycles: usize,
+    cancel: &Cancel,
+) -> Result<usize, PuzzleError> {
+    let automaton = Automaton::new(Lattice::<N>::default(), Rule::new(&[3], &[2, 3]))
+        .cancellable(cancel);
+    let active = cubes
+        .iter()
+        .map(|(x, y)| {
+            let mut cube = [0; N];
+            cube[0] = *x;
+            cube[1] = *y;
+            cube
+        })
+        .collect();
+    Ok(automaton.run(active, cycles)?.len())
 }
 
-fn get_min(cubes: &HashSet<(i64, i64, i64, i64)>) -> (i64, i64, i64, i64) {
-    cubes.iter().fold(
-        (std::i64::MAX, std::i64::MAX, std::i64::MAX, std::i64::MAX),
-        |(minx, miny, minz, minw), (x, y, z, w)| {
-            (
-                std::cmp::min(minx, *x),
-                std::cmp::min(miny, *y),
-                std::cmp::min(minz, *z),
-                std::cmp::min(minw, *w),
-            )
-        },
-    )
-}
+impl Day17 {
+    fn cycles(&self) -> usize {
+        self.context.params.integer(&CYCLES) as usize
+    }
 
-fn boot(cubes: &mut HashSet<(i64, i64, i64, i64)>, dimensions: Dimensions) {
-    for _ in 0..6 {
-        let mut new_state = cubes.clone();
-        let (maxx, maxy, maxz, mut maxw) = get_max(&cubes);
-        let (minx, miny, minz, mut minw) = get_min(&cubes);
+    pub fn solve_part1(&self, cubes: HashSet<(i64, i64)>) -> Result<usize, PuzzleError> {
+        boot::<3>(&cubes, self.cycles(), &self.context.cancel)
+    }
 
-        match dimensions {
-            Dimensions::D3 => {
-                minw = 0;
-                maxw = 1;
-            }
-            Dimensions::D4 => {
-                minw -= 1;
-                maxw += 2;
-            }
-        }
-        for x in minx - 1..maxx + 2 {
-            for y in miny - 1..maxy + 2 {
-                for z in minz - 1..maxz + 2 {
-                    for w in minw..maxw {
-                        let neighbours = neighbours(x, y, z, w, &cubes);
-                        if let Some(_) = cubes.get(&(x, y, z, w)) {
-                            if neighbours < 2 || neighbours > 3 {
-                                new_state.remove(&(x, y, z, w));
-                            }
-                        } else {
-                            if neighbours == 3 {
-                                new_state.insert((x, y, z, w));
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        *cubes = new_state;
+    pub fn solve_part2(&self, cubes: HashSet<(i64, i64)>) -> Result<usize, PuzzleError> {
+        boot::<4>(&cubes, self.cycles(), &self.context.cancel)
     }
 }
 
-impl Day17 {
-    fn solve_part1(&self, input: Vec<String>) -> usize {
-        let mut cubes = parse_input(&input);
-        boot(&mut cubes, Dimensions::D3);
-        cubes.len()
+impl Puzzle for Day17 {
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.cubes = parse_input(&read_lines(input)?)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(self.cubes.clone())?.to_string())
     }
 
-    fn solve_part2(&self, input: Vec<String>) -> usize {
-        let mut cubes = parse_input(&input);
-        boot(&mut cubes, Dimensions::D4);
-        cubes.len()
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(self.cubes.clone())?.to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
     }
 }
 
-impl Puzzle for Day17 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(input.clone()).to_string(),
-            self.solve_part2(input.clone()).to_string(),
-        );
+// A square starting slice of about 'size' cubes, with about one active cube in three.
+impl Generator for Day17 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let side = ((size as f64).sqrt() as usize).max(1);
+        let lines = (0..side)
+            .map(|_| {
+                (0..side)
+                    .map(|_| if rng.chance(0.35) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+        Generated::from_lines(lines)
     }
 }
 
@@ -140,13 +128,16 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day17 {}.solve_part1(
-                vec!(".#.", "..#", "###")
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect()
+            Day17::default().solve_part1(
+                parse_input(
+                    &vec!(".#.", "..#", "###")
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<String>>()
+                )
+                .unwrap()
             ),
-            112
+            Ok(112)
         );
     }
 
@@ -154,13 +145,16 @@ mod tests {
     // #[test]
     // fn part2_example1() {
     //     assert_eq!(
-    //         Day17 {}.solve_part2(
-    //             vec!(".#.", "..#", "###")
-    //                 .iter()
-    //                 .map(|x| x.to_string())
-    //                 .collect()
+    //         Day17::default().solve_part2(
+    //             parse_input(
+    //                 &vec!(".#.", "..#", "###")
+    //                     .iter()
+    //                     .map(|x| x.to_string())
+    //                     .collect::<Vec<String>>()
+    //             )
+    //             .unwrap()
     //         ),
-    //         848
+    //         Ok(848)
     //     );
     // }
 }
@@ -1,32 +1,65 @@
-use crate::puzzle::{io, File, Puzzle};
-pub struct Day18;
+//! Day 18: Operation Order. Evaluates expressions of single digits, '+', '*' and parentheses
+//! either strictly left to right with [`calc_left_to_right`] or with addition before multiplication
+//! with [`calc_add_before_mul`].
+
+use crate::bigint::BigUint;
+use crate::cancel::Cancel;
+use crate::generate::{Generated, Generator, Rng};
+use crate::puzzle::{read_lines, BufRead, Context, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day18 {
+    expressions: Vec<String>,
+    context: Context,
+}
+
+register_puzzle! {
+    puzzle: Day18,
+    year: 2020,
+    day: 18,
+    title: "Operation Order",
+    input: Lines,
+    reads_input: true,
+}
 
 #[derive(Debug)]
 enum Op {
-    Val(i64),
+    Val(BigUint),
     Multiply,
     Add,
 }
 
-fn parse_op(c: char) -> Op {
-    match c {
-        '*' => Op::Multiply,
-        '+' => Op::Add,
-        _ => panic!("Unexpected operation!"),
+fn parse_op(line: usize, text: &str, op: &str) -> Result<Op, PuzzleError> {
+    match op {
+        "*" => Ok(Op::Multiply),
+        "+" => Ok(Op::Add),
+        _ => Err(PuzzleError::malformed(
+            line,
+            text,
+            op,
+            "unexpected operation",
+        )),
     }
 }
 
-fn get_ops<F>(expr: &str, calc: F) -> Vec<Op>
+// Split an expression into alternating values and operations. Parenthesized sub-expressions are
+// evaluated with 'calc'. 'expr' must be a slice of the input line 'text' so that errors can point
+// out the offending column.
+fn get_ops<F>(line: usize, text: &str, expr: &str, calc: F) -> Result<Vec<Op>, PuzzleError>
 where
-    F: Fn(&str) -> i64,
+    F: Fn(usize, &str, &str) -> Result<BigUint, PuzzleError>,
 {
     let mut operations = vec![];
+    let bytes = expr.as_bytes();
     let mut i = 0;
+    if expr.is_empty() {
+        return Err(PuzzleError::malformed(line, text, expr, "empty expression"));
+    }
     while i < expr.len() {
-        let (val, len) = if expr.chars().nth(i).unwrap() == '(' {
+        let (val, len) = if bytes[i] == b'(' {
             let mut opens = 1;
             let mut len = 0;
-            for (j, c) in expr[i + 1..].chars().enumerate() {
+            for (j, c) in expr[i + 1..].char_indices() {
                 match c {
                     '(' => opens += 1,
                     ')' => {
@@ -39,9 +72,28 @@ where
                     _ => (),
                 }
             }
-            (calc(&expr[i + 1..i + len]), len + 1)
+            if len == 0 {
+                return Err(PuzzleError::malformed(
+                    line,
+                    text,
+                    &expr[i..],
+                    "unbalanced parenthesis",
+                ));
+            }
+            (calc(line, text, &expr[i + 1..i + len])?, len + 1)
         } else {
-            (expr.chars().nth(i).unwrap().to_digit(10).unwrap() as i64, 1)
+            let c = expr[i..].chars().next().unwrap_or_default();
+            match c.to_digit(10) {
+                Some(digit) => (BigUint::from(u64::from(digit)), 1),
+                None => {
+                    return Err(PuzzleError::malformed(
+                        line,
+                        text,
+                        &expr[i..i + c.len_utf8()],
+                        "expected a digit or '('",
+                    ))
+                }
+            }
         };
         operations.push(Op::Val(val));
         i += len;
@@ -49,50 +101,64 @@ where
         if i >= expr.len() {
             break;
         }
-        operations.push(parse_op(expr.chars().nth(i + 1).unwrap()));
+        if i + 3 > expr.len() || bytes[i] != b' ' || bytes[i + 2] != b' ' {
+            return Err(PuzzleError::malformed(
+                line,
+                text,
+                &expr[i..],
+                "expected ' + ' or ' * '",
+            ));
+        }
+        operations.push(parse_op(line, text, &expr[i + 1..i + 2])?);
         i += 3;
+        if i >= expr.len() {
+            return Err(PuzzleError::malformed(
+                line,
+                text,
+                &expr[i..],
+                "missing operand",
+            ));
+        }
     }
-    operations
+    Ok(operations)
 }
 
-fn calc_left_to_right(expr: &str) -> i64 {
-    let operations = get_ops(&expr, calc_left_to_right);
-
-    let mut result = match operations[0] {
+// The value of the operand 'op', which must be a value.
+fn value(op: &Op) -> &BigUint {
+    match op {
         Op::Val(x) => x,
         _ => panic!("Unexpected operation!"),
-    };
+    }
+}
+
+/// Evaluate 'expr' from left to right. 'expr' must be a slice of the input line 'text' at the
+/// 0-based line index 'line', errors point out the offending column.
+pub fn calc_left_to_right(line: usize, text: &str, expr: &str) -> Result<BigUint, PuzzleError> {
+    let operations = get_ops(line, text, expr, calc_left_to_right)?;
+
+    let mut result = value(&operations[0]).clone();
     for i in (1..operations.len() - 1).step_by(2) {
-        let rhs = match operations[i + 1] {
-            Op::Val(x) => x,
-            _ => panic!("Unexpected operation!"),
-        };
+        let rhs = value(&operations[i + 1]);
         match operations[i] {
-            Op::Multiply => result = result * rhs,
-            Op::Add => result = result + rhs,
+            Op::Multiply => result = &result * rhs,
+            Op::Add => result = &result + rhs,
             _ => panic!("Unexpected operation!"),
         }
     }
-    result
+    Ok(result)
 }
 
-fn calc_add_before_mul(expr: &str) -> i64 {
-    let mut operations = get_ops(&expr, calc_add_before_mul);
+/// Evaluate 'expr' with addition taking precedence over multiplication, see
+/// [`calc_left_to_right`] for the arguments.
+pub fn calc_add_before_mul(line: usize, text: &str, expr: &str) -> Result<BigUint, PuzzleError> {
+    let mut operations = get_ops(line, text, expr, calc_add_before_mul)?;
 
     // First evaluate all 'add' operations.
     let mut i = 1;
     while i < operations.len() - 1 {
-        let a = match operations[i - 1] {
-            Op::Val(x) => x,
-            _ => panic!("Unexpected operation!"),
-        };
-        let b = match operations[i + 1] {
-            Op::Val(x) => x,
-            _ => panic!("Unexpected operation!"),
-        };
         match operations[i] {
             Op::Add => {
-                operations[i - 1] = Op::Val(a + b);
+                operations[i - 1] = Op::Val(value(&operations[i - 1]) + value(&operations[i + 1]));
                 operations.remove(i);
                 operations.remove(i);
             }
@@ -100,41 +166,89 @@ fn calc_add_before_mul(expr: &str) -> i64 {
         }
     }
 
-    // Now that only 'multiply' operations remain, filter out all Val(i64) then just multiply them with fold().
-    operations
+    // Now that only 'multiply' operations remain, multiply all values.
+    Ok(operations
         .iter()
-        .filter(|op| match op {
-            Op::Val(_) => true,
-            _ => false,
-        })
-        .map(|op| match op {
-            Op::Val(v) => *v,
-            _ => panic!("Unexpected operation!"),
-        })
-        .fold(1, |sum, x| sum * x)
+        .filter(|op| matches!(op, Op::Val(_)))
+        .fold(BigUint::from(1), |product, op| &product * value(op)))
+}
+
+/// Sum all expressions in 'input' using one of the evaluators above. Gives up with the error of
+/// 'cancel' once it is cancelled.
+pub fn sum_expressions<F>(
+    input: &[String],
+    calc: F,
+    cancel: &Cancel,
+) -> Result<BigUint, PuzzleError>
+where
+    F: Fn(usize, &str, &str) -> Result<BigUint, PuzzleError>,
+{
+    let mut sum = BigUint::zero();
+    for (i, expr) in input.iter().enumerate() {
+        cancel.check()?;
+        if let Some(pos) = expr.find(|c: char| !c.is_ascii()) {
+            return Err(PuzzleError::malformed(
+                i,
+                expr,
+                &expr[pos..],
+                "unexpected character",
+            ));
+        }
+        sum = sum + calc(i, expr, expr)?;
+    }
+    Ok(sum)
 }
 
 impl Day18 {
-    fn solve_part1(&self, input: Vec<String>) -> i64 {
-        input
-            .iter()
-            .fold(0, |sum, expr| sum + calc_left_to_right(&expr))
+    pub fn solve_part1(&self, input: &[String]) -> Result<BigUint, PuzzleError> {
+        sum_expressions(input, calc_left_to_right, &self.context.cancel)
     }
 
-    fn solve_part2(&self, input: Vec<String>) -> i64 {
-        input
-            .iter()
-            .fold(0, |sum, expr| sum + calc_add_before_mul(&expr))
+    pub fn solve_part2(&self, input: &[String]) -> Result<BigUint, PuzzleError> {
+        sum_expressions(input, calc_add_before_mul, &self.context.cancel)
     }
 }
 
 impl Puzzle for Day18 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(input.clone()).to_string(),
-            self.solve_part2(input.clone()).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.expressions = read_lines(input)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&self.expressions)?.to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(&self.expressions)?.to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+// A random expression of up to 7 operands, each a digit or, up to 3 levels deep, a parenthesized
+// expression.
+fn random_expression(rng: &mut Rng, depth: usize) -> String {
+    let mut expr = String::new();
+    for i in 0..rng.range(2..=7) {
+        if i > 0 {
+            expr += if rng.chance(0.5) { " + " } else { " * " };
+        }
+        if depth < 3 && rng.chance(0.2) {
+            expr += &format!("({})", random_expression(rng, depth + 1));
+        } else {
+            expr += &rng.range(1..=9).to_string();
+        }
+    }
+    expr
+}
+
+// 'size' random expressions.
+impl Generator for Day18 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        Generated::from_lines((0..size).map(|_| random_expression(rng, 0)).collect())
     }
 }
 
@@ -149,54 +263,127 @@ mod tests {
     #[test]
     fn part1_examples() {
         assert_eq!(
-            Day18 {}.solve_part1(string_vec!("1 + 2 * 3 + 4 * 5 + 6")),
-            71
+            Day18::default()
+                .solve_part1(&string_vec!("1 + 2 * 3 + 4 * 5 + 6"))
+                .unwrap(),
+            BigUint::from(71)
+        );
+        assert_eq!(
+            Day18::default()
+                .solve_part1(&string_vec!("1 + (2 * 3) + (4 * (5 + 6))"))
+                .unwrap(),
+            BigUint::from(51)
         );
         assert_eq!(
-            Day18 {}.solve_part1(string_vec!("1 + (2 * 3) + (4 * (5 + 6))")),
-            51
+            Day18::default()
+                .solve_part1(&string_vec!("2 * 3 + (4 * 5)"))
+                .unwrap(),
+            BigUint::from(26)
         );
-        assert_eq!(Day18 {}.solve_part1(string_vec!("2 * 3 + (4 * 5)")), 26);
         assert_eq!(
-            Day18 {}.solve_part1(string_vec!("5 + (8 * 3 + 9 + 3 * 4 * 3)")),
-            437
+            Day18::default()
+                .solve_part1(&string_vec!("5 + (8 * 3 + 9 + 3 * 4 * 3)"))
+                .unwrap(),
+            BigUint::from(437)
         );
         assert_eq!(
-            Day18 {}.solve_part1(string_vec!("5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))")),
-            12240
+            Day18::default()
+                .solve_part1(&string_vec!("5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))"))
+                .unwrap(),
+            BigUint::from(12240)
         );
         assert_eq!(
-            Day18 {}.solve_part1(string_vec!(
-                "((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2"
-            )),
-            13632
+            Day18::default()
+                .solve_part1(&string_vec!(
+                    "((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2"
+                ))
+                .unwrap(),
+            BigUint::from(13632)
         );
     }
 
     #[test]
     fn part2_examples() {
         assert_eq!(
-            Day18 {}.solve_part2(string_vec!("1 + 2 * 3 + 4 * 5 + 6")),
-            231
+            Day18::default()
+                .solve_part2(&string_vec!("1 + 2 * 3 + 4 * 5 + 6"))
+                .unwrap(),
+            BigUint::from(231)
+        );
+        assert_eq!(
+            Day18::default()
+                .solve_part2(&string_vec!("1 + (2 * 3) + (4 * (5 + 6))"))
+                .unwrap(),
+            BigUint::from(51)
         );
         assert_eq!(
-            Day18 {}.solve_part2(string_vec!("1 + (2 * 3) + (4 * (5 + 6))")),
-            51
+            Day18::default()
+                .solve_part2(&string_vec!("2 * 3 + (4 * 5)"))
+                .unwrap(),
+            BigUint::from(46)
         );
-        assert_eq!(Day18 {}.solve_part2(string_vec!("2 * 3 + (4 * 5)")), 46);
         assert_eq!(
-            Day18 {}.solve_part2(string_vec!("5 + (8 * 3 + 9 + 3 * 4 * 3)")),
-            1445
+            Day18::default()
+                .solve_part2(&string_vec!("5 + (8 * 3 + 9 + 3 * 4 * 3)"))
+                .unwrap(),
+            BigUint::from(1445)
         );
         assert_eq!(
-            Day18 {}.solve_part2(string_vec!("5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))")),
-            669060
+            Day18::default()
+                .solve_part2(&string_vec!("5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))"))
+                .unwrap(),
+            BigUint::from(669060)
         );
         assert_eq!(
-            Day18 {}.solve_part2(string_vec!(
-                "((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2"
-            )),
-            23340
+            Day18::default()
+                .solve_part2(&string_vec!(
+                    "((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2"
+                ))
+                .unwrap(),
+            BigUint::from(23340)
         );
     }
+
+    #[test]
+    fn malformed_expressions() {
+        let day = Day18::default();
+        for (expr, column) in &[("1 + (2 * 3", 5), ("1 + a", 5), ("1 +", 2), ("2 * ()", 6)] {
+            match day.solve_part1(&string_vec!(expr)) {
+                Err(PuzzleError::Parse {
+                    line, column: c, ..
+                }) => {
+                    assert_eq!((line, c), (1, *column), "{}", expr)
+                }
+                _ => panic!("Expected a parse error for '{}'", expr),
+            }
+        }
+        // The evaluators are public and must not panic on characters outside ASCII either.
+        for expr in &["1 + é", "(é) + 1", "1 + (2 * é)"] {
+            assert!(calc_left_to_right(0, expr, expr).is_err(), "{}", expr);
+            assert!(calc_add_before_mul(0, expr, expr).is_err(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn beyond_64_bits() {
+        let expr = [["9"; 30].join(" * ")];
+        let expected = "42391158275216203514294433201";
+        let day = Day18::default();
+        assert_eq!(day.solve_part1(&expr).unwrap().to_string(), expected);
+        assert_eq!(day.solve_part2(&expr).unwrap().to_string(), expected);
+    }
+
+    #[test]
+    fn cancelled() {
+        let cancel = Cancel::new();
+        cancel.cancel();
+        let mut day = Day18::default();
+        day.set_context(Context {
+            cancel,
+            ..Context::default()
+        });
+        day.parse_str("1 + 2 * 3\n").unwrap();
+        assert_eq!(day.part1(), Err(PuzzleError::Timeout));
+        assert_eq!(day.part2(), Err(PuzzleError::Timeout));
+    }
 }
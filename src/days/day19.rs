@@ -1,11 +1,28 @@
+//! Day 19: Monster Messages. [`parse_input`] splits the input into rules by id and messages, and
+//! [`match_rule`] checks if a message fully matches a rule.
+
 use std::collections::HashMap;
 
-use crate::puzzle::{io, File, Puzzle};
-pub struct Day19;
+use crate::cancel::Cancel;
+use crate::generate::{Generated, Generator, Rng};
+use crate::graph::Digraph;
+use crate::parse::{key_value, named_sections};
+use crate::puzzle::{parse_token, read_lines, BufRead, Context, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day19 {
+    rules: HashMap<usize, String>,
+    messages: Vec<String>,
+    context: Context,
+}
 
-enum ParseState {
-    Rules,
-    Messages,
+register_puzzle! {
+    puzzle: Day19,
+    year: 2020,
+    day: 19,
+    title: "Monster Messages",
+    input: Sections,
+    reads_input: true,
 }
 
 // Check if an expression partly or fully matches a rule. Returns 'Some' vector of the match lengths
@@ -53,7 +70,8 @@ fn get_matches(expr: &str, rule_id: usize, rules: &HashMap<usize, String>) -> Op
     }
 }
 
-fn match_rule(expr: &str, rule_id: usize, rules: &HashMap<usize, String>) -> bool {
+/// Check if all of 'expr' matches the rule 'rule_id'.
+pub fn match_rule(expr: &str, rule_id: usize, rules: &HashMap<usize, String>) -> bool {
     if let Some(match_lengths) = get_matches(&expr, rule_id, &rules) {
         for len in match_lengths {
             if len == expr.len() {
@@ -64,61 +82,296 @@ fn match_rule(expr: &str, rule_id: usize, rules: &HashMap<usize, String>) -> boo
     return false;
 }
 
-fn parse_input(input: &Vec<String>) -> (HashMap<usize, String>, Vec<String>) {
-    let mut parse_state = ParseState::Rules;
+/// Parse the rules and the messages, separated by a blank line.
+pub fn parse_input(input: &[String]) -> Result<(HashMap<usize, String>, Vec<String>), PuzzleError> {
+    let sections = named_sections(input, &["", ""])?;
     let mut rules = HashMap::new();
-    let mut messages = vec![];
-    for line in input {
-        if line == "" {
-            parse_state = ParseState::Messages;
-            continue;
-        }
-        match parse_state {
-            ParseState::Rules => {
-                let tokens: Vec<&str> = line.split(":").collect();
-                let id = tokens[0].parse::<usize>().unwrap();
-                let expr = tokens[1].trim().to_string();
-                rules.insert(id, expr);
+    let mut lines = HashMap::new();
+    let mut references = vec![];
+    // Edges to the rule each alternative starts with.
+    let mut starts = Digraph::new();
+    for (i, line) in sections[0].lines() {
+        let (id, expr) = key_value(i, line, line, ':')?;
+        let id = parse_token::<usize>(i, line, id)?;
+        if expr.contains('"') {
+            if expr.len() != 3 || !expr.starts_with('"') || !expr.ends_with('"') {
+                return Err(PuzzleError::malformed(i, line, expr, "expected \"<char>\""));
+            }
+        } else {
+            for alternative in expr.split('|') {
+                match alternative.split_whitespace().next() {
+                    Some(first) => starts.add_edge(id, parse_token::<usize>(i, line, first)?, ()),
+                    None => {
+                        return Err(PuzzleError::malformed(
+                            i,
+                            line,
+                            alternative,
+                            "empty alternative",
+                        ))
+                    }
+                }
+            }
+            for sub_rule in expr.split(['|', ' ']) {
+                if !sub_rule.is_empty() {
+                    references.push((i, parse_token::<usize>(i, line, sub_rule)?, sub_rule));
+                }
             }
-            ParseState::Messages => messages.push(line.to_string()),
         }
+        rules.insert(id, expr.to_string());
+        lines.insert(id, i);
     }
-    (rules, messages)
+    for (i, id, sub_rule) in references {
+        if !rules.contains_key(&id) {
+            return Err(PuzzleError::malformed(
+                i,
+                &input[i],
+                sub_rule,
+                "undefined rule",
+            ));
+        }
+    }
+    // A rule that starts with itself, directly or through other rules, would be matched forever.
+    if let Some(cycle) = starts.find_cycle() {
+        let i = lines[starts.node(cycle[0])];
+        return Err(PuzzleError::malformed(
+            i,
+            &input[i],
+            &input[i],
+            "left-recursive rule",
+        ));
+    }
+    Ok((rules, sections[1].lines.to_vec()))
+}
+
+fn require_rules(rules: &HashMap<usize, String>, ids: &[usize]) -> Result<(), PuzzleError> {
+    for id in ids {
+        if !rules.contains_key(id) {
+            return Err(PuzzleError::NoSolution(format!(
+                "rule {} is not defined",
+                id
+            )));
+        }
+    }
+    Ok(())
+}
+
+// The number of messages that fully match rule 0. Gives up with the error of 'cancel' once it is
+// cancelled.
+fn count_matches(
+    rules: &HashMap<usize, String>,
+    messages: &[String],
+    cancel: &Cancel,
+) -> Result<usize, PuzzleError> {
+    let mut nof_matches = 0;
+    for message in messages {
+        cancel.check()?;
+        if match_rule(message, 0, rules) {
+            nof_matches += 1;
+        }
+    }
+    Ok(nof_matches)
 }
 
 impl Day19 {
-    fn solve_part1(&self, input: Vec<String>) -> usize {
-        let (rules, messages) = parse_input(&input);
-        messages.iter().fold(0, |nof_matches, message| {
-            if match_rule(message, 0, &rules) {
-                nof_matches + 1
-            } else {
-                nof_matches
-            }
-        })
+    pub fn solve_part1(
+        &self,
+        rules: &HashMap<usize, String>,
+        messages: &[String],
+    ) -> Result<usize, PuzzleError> {
+        require_rules(rules, &[0])?;
+        count_matches(rules, messages, &self.context.cancel)
     }
 
-    fn solve_part2(&self, input: Vec<String>) -> usize {
-        let (mut rules, messages) = parse_input(&input);
+    pub fn solve_part2(
+        &self,
+        rules: &HashMap<usize, String>,
+        messages: &[String],
+    ) -> Result<usize, PuzzleError> {
+        require_rules(rules, &[0, 31, 42])?;
+        let mut rules = rules.clone();
         rules.insert(8, "42 | 42 8".to_string());
         rules.insert(11, "42 31 | 42 11 31".to_string());
-        messages.iter().fold(0, |nof_matches, message| {
-            if match_rule(message, 0, &rules) {
-                nof_matches + 1
-            } else {
-                nof_matches
-            }
-        })
+        count_matches(&rules, messages, &self.context.cancel)
     }
 }
 
 impl Puzzle for Day19 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(input.clone()).to_string(),
-            self.solve_part2(input.clone()).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        let (rules, messages) = parse_input(&read_lines(input)?)?;
+        self.rules = rules;
+        self.messages = messages;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&self.rules, &self.messages)?.to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(&self.rules, &self.messages)?.to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+// A binary trie over the blocks of rules 42 and 31, each leaf holding the rule that matches all
+// blocks below it.
+enum Trie {
+    Leaf(usize),
+    Split(Box<Trie>, Box<Trie>),
+}
+
+impl Trie {
+    fn random(rng: &mut Rng, depth: usize) -> Trie {
+        if depth == 0 || rng.chance(0.3) {
+            Trie::Leaf(*rng.choose(&[42, 31]))
+        } else {
+            Trie::Split(
+                Box::new(Trie::random(rng, depth - 1)),
+                Box::new(Trie::random(rng, depth - 1)),
+            )
+        }
+    }
+
+    fn rule_of(&self, block: &[u8]) -> usize {
+        match self {
+            Trie::Leaf(rule) => *rule,
+            Trie::Split(a, b) => {
+                if block[0] == b'a' {
+                    a.rule_of(&block[1..])
+                } else {
+                    b.rule_of(&block[1..])
+                }
+            }
+        }
+    }
+
+    // Add the rules matching the blocks of 'rule' below this node, 'depth' characters from the end
+    // of the block, and return the id of the top one. 'rules' starts with the rules for "a", "b"
+    // and "a" or "b".
+    fn add_rules(
+        &self,
+        rule: usize,
+        depth: usize,
+        rules: &mut Vec<(usize, String)>,
+    ) -> Option<usize> {
+        let add = |rules: &mut Vec<(usize, String)>, text: String| {
+            // The ids below 50 are left for the rules the puzzle names.
+            let id = 50 + rules.len();
+            rules.push((id, text));
+            id
+        };
+        match self {
+            Trie::Leaf(leaf) if *leaf != rule => None,
+            Trie::Leaf(_) => {
+                // Any 'depth' characters.
+                let any = rules[2].0;
+                let mut id = any;
+                for _ in 1..depth {
+                    id = add(rules, format!("{} {}", any, id));
+                }
+                Some(id)
+            }
+            Trie::Split(a, b) => {
+                let mut alternatives = vec![];
+                for (c, child) in [(rules[0].0, a), (rules[1].0, b)].iter() {
+                    if depth == 1 {
+                        if let Trie::Leaf(leaf) = ***child {
+                            if leaf == rule {
+                                alternatives.push(c.to_string());
+                            }
+                        }
+                    } else if let Some(id) = child.add_rules(rule, depth - 1, rules) {
+                        alternatives.push(format!("{} {}", c, id));
+                    }
+                }
+                if alternatives.is_empty() {
+                    None
+                } else {
+                    Some(add(rules, alternatives.join(" | ")))
+                }
+            }
+        }
+    }
+}
+
+// 'size' messages of blocks of the same length, every block matching either rule 42 or rule 31.
+// The messages that match are built from blocks of the right rules, so the answers are known.
+impl Generator for Day19 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let len = rng.range(3..=6) as usize;
+        let trie = loop {
+            let trie = Trie::Split(
+                Box::new(Trie::random(rng, len - 1)),
+                Box::new(Trie::random(rng, len - 1)),
+            );
+            // Both rules must match some block.
+            let blocks: Vec<usize> = (0..1 << len)
+                .map(|n| {
+                    let block: Vec<u8> = (0..len)
+                        .map(|i| if n >> i & 1 == 0 { b'a' } else { b'b' })
+                        .collect();
+                    trie.rule_of(&block)
+                })
+                .collect();
+            if blocks.contains(&42) && blocks.contains(&31) {
+                break trie;
+            }
+        };
+        let mut rules = vec![
+            (50, "\"a\"".to_string()),
+            (51, "\"b\"".to_string()),
+            (52, "50 | 51".to_string()),
+        ];
+        let rule42 = trie.add_rules(42, len, &mut rules).unwrap();
+        let rule31 = trie.add_rules(31, len, &mut rules).unwrap();
+        rules.extend(vec![
+            (0, "8 11".to_string()),
+            (8, "42".to_string()),
+            (11, "42 31".to_string()),
+            (42, rule42.to_string()),
+            (31, rule31.to_string()),
+        ]);
+
+        let block = |rng: &mut Rng, rule: usize| loop {
+            let block: Vec<u8> = (0..len).map(|_| *rng.choose(b"ab")).collect();
+            if trie.rule_of(&block) == rule {
+                return String::from_utf8(block).unwrap();
+            }
+        };
+        let (mut part1, mut part2) = (0, 0);
+        let mut messages = vec![];
+        for _ in 0..size.max(1) {
+            let blocks: Vec<usize> = match rng.below(3) {
+                0 => vec![42, 42, 31],
+                1 => {
+                    let n31 = rng.range(1..=4) as usize;
+                    let n42 = n31 + rng.range(1..=4) as usize;
+                    [vec![42; n42], vec![31; n31]].concat()
+                }
+                _ => (0..rng.range(1..=8))
+                    .map(|_| *rng.choose(&[42, 31]))
+                    .collect(),
+            };
+            let n42 = blocks.iter().take_while(|&&rule| rule == 42).count();
+            let n31 = blocks.len() - n42;
+            if blocks[n42..].iter().all(|&rule| rule == 31) && n31 >= 1 && n42 > n31 {
+                part2 += 1;
+                part1 += (n42 == 2 && n31 == 1) as usize;
+            }
+            messages.push(blocks.iter().map(|&rule| block(rng, rule)).collect());
+        }
+
+        rng.shuffle(&mut rules);
+        let mut lines: Vec<String> = rules
+            .iter()
+            .map(|(id, rule)| format!("{}: {}", id, rule))
+            .collect();
+        lines.push(String::new());
+        lines.extend(messages);
+        Generated::from_lines(lines).with_answers(part1, part2)
     }
 }
 
@@ -132,153 +385,188 @@ mod tests {
 
     #[test]
     fn part1_example1() {
-        assert_eq!(
-            Day19 {}.solve_part1(string_vec!(
-                "0: 1 2",
-                "1: \"a\"",
-                "2: 1 3 | 3 1",
-                "3: \"b\"",
-                "",
-                "aab",
-                "aba",
-                "abab",
-                "a",
-                "abb"
-            )),
-            2
-        );
+        let (rules, messages) = parse_input(&string_vec!(
+            "0: 1 2",
+            "1: \"a\"",
+            "2: 1 3 | 3 1",
+            "3: \"b\"",
+            "",
+            "aab",
+            "aba",
+            "abab",
+            "a",
+            "abb"
+        ))
+        .unwrap();
+        assert_eq!(Day19::default().solve_part1(&rules, &messages), Ok(2));
     }
 
     #[test]
     fn part1_example2() {
-        assert_eq!(
-            Day19 {}.solve_part1(string_vec!(
-                "0: 4 1 5",
-                "1: 2 3 | 3 2",
-                "2: 4 4 | 5 5",
-                "3: 4 5 | 5 4",
-                "4: \"a\"",
-                "5: \"b\"",
-                "",
-                "ababbb",
-                "bababa",
-                "abbbab",
-                "aaabbb",
-                "aaaabbb"
-            )),
-            2
-        );
+        let (rules, messages) = parse_input(&string_vec!(
+            "0: 4 1 5",
+            "1: 2 3 | 3 2",
+            "2: 4 4 | 5 5",
+            "3: 4 5 | 5 4",
+            "4: \"a\"",
+            "5: \"b\"",
+            "",
+            "ababbb",
+            "bababa",
+            "abbbab",
+            "aaabbb",
+            "aaaabbb"
+        ))
+        .unwrap();
+        assert_eq!(Day19::default().solve_part1(&rules, &messages), Ok(2));
     }
 
     #[test]
     fn part1_example3() {
-        assert_eq!(
-            Day19 {}.solve_part1(string_vec!(
-                "42: 9 14 | 10 1",
-                "9: 14 27 | 1 26",
-                "10: 23 14 | 28 1",
-                "1: \"a\"",
-                "11: 42 31",
-                "5: 1 14 | 15 1",
-                "19: 14 1 | 14 14",
-                "12: 24 14 | 19 1",
-                "16: 15 1 | 14 14",
-                "31: 14 17 | 1 13",
-                "6: 14 14 | 1 14",
-                "2: 1 24 | 14 4",
-                "0: 8 11",
-                "13: 14 3 | 1 12",
-                "15: 1 | 14",
-                "17: 14 2 | 1 7",
-                "23: 25 1 | 22 14",
-                "28: 16 1",
-                "4: 1 1",
-                "20: 14 14 | 1 15",
-                "3: 5 14 | 16 1",
-                "27: 1 6 | 14 18",
-                "14: \"b\"",
-                "21: 14 1 | 1 14",
-                "25: 1 1 | 1 14",
-                "22: 14 14",
-                "8: 42",
-                "26: 14 22 | 1 20",
-                "18: 15 15",
-                "7: 14 5 | 1 21",
-                "24: 14 1",
-                "",
-                "abbbbbabbbaaaababbaabbbbabababbbabbbbbbabaaaa",
-                "bbabbbbaabaabba",
-                "babbbbaabbbbbabbbbbbaabaaabaaa",
-                "aaabbbbbbaaaabaababaabababbabaaabbababababaaa",
-                "bbbbbbbaaaabbbbaaabbabaaa",
-                "bbbababbbbaaaaaaaabbababaaababaabab",
-                "ababaaaaaabaaab",
-                "ababaaaaabbbaba",
-                "baabbaaaabbaaaababbaababb",
-                "abbbbabbbbaaaababbbbbbaaaababb",
-                "aaaaabbaabaaaaababaa",
-                "aaaabbaaaabbaaa",
-                "aaaabbaabbaaaaaaabbbabbbaaabbaabaaa",
-                "babaaabbbaaabaababbaabababaaab",
-                "aabbbbbaabbbaaaaaabbbbbababaaaaabbaaabba"
-            )),
-            3
-        );
+        let (rules, messages) = parse_input(&string_vec!(
+            "42: 9 14 | 10 1",
+            "9: 14 27 | 1 26",
+            "10: 23 14 | 28 1",
+            "1: \"a\"",
+            "11: 42 31",
+            "5: 1 14 | 15 1",
+            "19: 14 1 | 14 14",
+            "12: 24 14 | 19 1",
+            "16: 15 1 | 14 14",
+            "31: 14 17 | 1 13",
+            "6: 14 14 | 1 14",
+            "2: 1 24 | 14 4",
+            "0: 8 11",
+            "13: 14 3 | 1 12",
+            "15: 1 | 14",
+            "17: 14 2 | 1 7",
+            "23: 25 1 | 22 14",
+            "28: 16 1",
+            "4: 1 1",
+            "20: 14 14 | 1 15",
+            "3: 5 14 | 16 1",
+            "27: 1 6 | 14 18",
+            "14: \"b\"",
+            "21: 14 1 | 1 14",
+            "25: 1 1 | 1 14",
+            "22: 14 14",
+            "8: 42",
+            "26: 14 22 | 1 20",
+            "18: 15 15",
+            "7: 14 5 | 1 21",
+            "24: 14 1",
+            "",
+            "abbbbbabbbaaaababbaabbbbabababbbabbbbbbabaaaa",
+            "bbabbbbaabaabba",
+            "babbbbaabbbbbabbbbbbaabaaabaaa",
+            "aaabbbbbbaaaabaababaabababbabaaabbababababaaa",
+            "bbbbbbbaaaabbbbaaabbabaaa",
+            "bbbababbbbaaaaaaaabbababaaababaabab",
+            "ababaaaaaabaaab",
+            "ababaaaaabbbaba",
+            "baabbaaaabbaaaababbaababb",
+            "abbbbabbbbaaaababbbbbbaaaababb",
+            "aaaaabbaabaaaaababaa",
+            "aaaabbaaaabbaaa",
+            "aaaabbaabbaaaaaaabbbabbbaaabbaabaaa",
+            "babaaabbbaaabaababbaabababaaab",
+            "aabbbbbaabbbaaaaaabbbbbababaaaaabbaaabba"
+        ))
+        .unwrap();
+        assert_eq!(Day19::default().solve_part1(&rules, &messages), Ok(3));
     }
 
     #[test]
     fn part2_example1() {
+        let (rules, messages) = parse_input(&string_vec!(
+            "42: 9 14 | 10 1",
+            "9: 14 27 | 1 26",
+            "10: 23 14 | 28 1",
+            "1: \"a\"",
+            "11: 42 31",
+            "5: 1 14 | 15 1",
+            "19: 14 1 | 14 14",
+            "12: 24 14 | 19 1",
+            "16: 15 1 | 14 14",
+            "31: 14 17 | 1 13",
+            "6: 14 14 | 1 14",
+            "2: 1 24 | 14 4",
+            "0: 8 11",
+            "13: 14 3 | 1 12",
+            "15: 1 | 14",
+            "17: 14 2 | 1 7",
+            "23: 25 1 | 22 14",
+            "28: 16 1",
+            "4: 1 1",
+            "20: 14 14 | 1 15",
+            "3: 5 14 | 16 1",
+            "27: 1 6 | 14 18",
+            "14: \"b\"",
+            "21: 14 1 | 1 14",
+            "25: 1 1 | 1 14",
+            "22: 14 14",
+            "8: 42",
+            "26: 14 22 | 1 20",
+            "18: 15 15",
+            "7: 14 5 | 1 21",
+            "24: 14 1",
+            "",
+            "abbbbbabbbaaaababbaabbbbabababbbabbbbbbabaaaa",
+            "bbabbbbaabaabba",
+            "babbbbaabbbbbabbbbbbaabaaabaaa",
+            "aaabbbbbbaaaabaababaabababbabaaabbababababaaa",
+            "bbbbbbbaaaabbbbaaabbabaaa",
+            "bbbababbbbaaaaaaaabbababaaababaabab",
+            "ababaaaaaabaaab",
+            "ababaaaaabbbaba",
+            "baabbaaaabbaaaababbaababb",
+            "abbbbabbbbaaaababbbbbbaaaababb",
+            "aaaaabbaabaaaaababaa",
+            "aaaabbaaaabbaaa",
+            "aaaabbaabbaaaaaaabbbabbbaaabbaabaaa",
+            "babaaabbbaaabaababbaabababaaab",
+            "aabbbbbaabbbaaaaaabbbbbababaaaaabbaaabba"
+        ))
+        .unwrap();
+        assert_eq!(Day19::default().solve_part2(&rules, &messages), Ok(12));
+    }
+
+    #[test]
+    fn malformed_rules() {
         assert_eq!(
-            Day19 {}.solve_part2(string_vec!(
-                "42: 9 14 | 10 1",
-                "9: 14 27 | 1 26",
-                "10: 23 14 | 28 1",
-                "1: \"a\"",
-                "11: 42 31",
-                "5: 1 14 | 15 1",
-                "19: 14 1 | 14 14",
-                "12: 24 14 | 19 1",
-                "16: 15 1 | 14 14",
-                "31: 14 17 | 1 13",
-                "6: 14 14 | 1 14",
-                "2: 1 24 | 14 4",
-                "0: 8 11",
-                "13: 14 3 | 1 12",
-                "15: 1 | 14",
-                "17: 14 2 | 1 7",
-                "23: 25 1 | 22 14",
-                "28: 16 1",
-                "4: 1 1",
-                "20: 14 14 | 1 15",
-                "3: 5 14 | 16 1",
-                "27: 1 6 | 14 18",
-                "14: \"b\"",
-                "21: 14 1 | 1 14",
-                "25: 1 1 | 1 14",
-                "22: 14 14",
-                "8: 42",
-                "26: 14 22 | 1 20",
-                "18: 15 15",
-                "7: 14 5 | 1 21",
-                "24: 14 1",
-                "",
-                "abbbbbabbbaaaababbaabbbbabababbbabbbbbbabaaaa",
-                "bbabbbbaabaabba",
-                "babbbbaabbbbbabbbbbbaabaaabaaa",
-                "aaabbbbbbaaaabaababaabababbabaaabbababababaaa",
-                "bbbbbbbaaaabbbbaaabbabaaa",
-                "bbbababbbbaaaaaaaabbababaaababaabab",
-                "ababaaaaaabaaab",
-                "ababaaaaabbbaba",
-                "baabbaaaabbaaaababbaababb",
-                "abbbbabbbbaaaababbbbbbaaaababb",
-                "aaaaabbaabaaaaababaa",
-                "aaaabbaaaabbaaa",
-                "aaaabbaabbaaaaaaabbbabbbaaabbaabaaa",
-                "babaaabbbaaabaababbaabababaaab",
-                "aabbbbbaabbbaaaaaabbbbbababaaaaabbaaabba"
-            )),
-            12
+            Day19::default().parse_str("0: 0\n\na\n").err(),
+            Some(PuzzleError::Parse {
+                line: 1,
+                column: 1,
+                text: "0: 0".to_string(),
+                reason: "left-recursive rule".to_string(),
+            })
         );
+        // Through another rule, and only the first rule of an alternative counts.
+        assert!(Day19::default()
+            .parse_str("0: 1 2\n1: \"a\" | 0 1\n2: 1 | 2\n\na\n")
+            .is_err());
+        assert!(Day19::default()
+            .parse_str("0: 1 0 | 1\n1: \"a\"\n\na\n")
+            .is_ok());
+        assert!(matches!(
+            Day19::default().parse_str("0: 1 |\n1: \"a\"\n\na\n"),
+            Err(PuzzleError::Parse { column: 7, .. })
+        ));
+    }
+
+    #[test]
+    fn cancelled() {
+        let cancel = Cancel::new();
+        cancel.cancel();
+        let mut day = Day19::default();
+        day.set_context(Context {
+            cancel,
+            ..Context::default()
+        });
+        day.parse_str("0: 1 2\n1: \"a\"\n2: \"b\"\n31: 1\n42: 2\n\nab\n")
+            .unwrap();
+        assert_eq!(day.part1(), Err(PuzzleError::Timeout));
+        assert_eq!(day.part2(), Err(PuzzleError::Timeout));
     }
 }
@@ -1,38 +1,81 @@
-use crate::puzzle::{io, File, Puzzle};
-pub struct Day2;
+//! Day 2: Password Philosophy. Each input line is parsed into a [`Policy`] by [`parse_input`]
+//! and the solvers count the passwords that follow the old and the new interpretation of it.
+
+use crate::generate::{Generated, Generator, Rng};
+use crate::puzzle::{next_token, parse_token, read_lines, BufRead, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day2 {
+    policies: Vec<Policy>,
+}
+
+register_puzzle! {
+    puzzle: Day2,
+    year: 2020,
+    day: 2,
+    title: "Password Philosophy",
+    input: Lines,
+    reads_input: true,
+}
+
+/// A password together with the policy it was created under. 'a' and 'b' are the two
+/// numbers of the policy and 'c' its letter.
+pub struct Policy {
+    pub a: usize,
+    pub b: usize,
+    pub c: u8,
+    pub pwd: String,
+}
+
+/// Parse lines such as "1-3 a: abcde" into policies.
+pub fn parse_input(input: &[String]) -> Result<Vec<Policy>, PuzzleError> {
+    let mut policies = vec![];
+    for (i, line) in input.iter().enumerate() {
+        let mut tokens = line.split(' ');
+        let mut range = next_token(&mut tokens, i, line)?.split('-');
+        let a = parse_token(i, line, next_token(&mut range, i, line)?)?;
+        let b = parse_token(i, line, next_token(&mut range, i, line)?)?;
+        let c = next_token(&mut tokens, i, line)?;
+        if c.is_empty() {
+            return Err(PuzzleError::malformed(i, line, c, "missing letter"));
+        }
+        let pwd = next_token(&mut tokens, i, line)?;
+        policies.push(Policy {
+            a,
+            b,
+            c: c.as_bytes()[0],
+            pwd: pwd.to_string(),
+        });
+    }
+    Ok(policies)
+}
 
 impl Day2 {
-    fn solve_part1(&self, input: &Vec<String>) -> i64 {
+    pub fn solve_part1(&self, input: &[Policy]) -> i64 {
         let mut nof_ok_passwords: i64 = 0;
-        for line in input {
-            let mut tokens = line.split(" ");
-            let mut range = tokens.next().unwrap().split("-");
-            let min = range.next().unwrap().parse::<usize>().unwrap();
-            let max = range.next().unwrap().parse::<usize>().unwrap();
-            let c = tokens.next().unwrap().as_bytes()[0];
-            let pwd = tokens.next().unwrap();
-            let count = pwd.as_bytes().iter().filter(|x| **x == c).count();
-            if count >= min && count <= max {
+        for policy in input {
+            let count = policy
+                .pwd
+                .as_bytes()
+                .iter()
+                .filter(|x| **x == policy.c)
+                .count();
+            if count >= policy.a && count <= policy.b {
                 nof_ok_passwords += 1;
             }
         }
         nof_ok_passwords
     }
 
-    fn solve_part2(&self, input: &Vec<String>) -> i64 {
+    pub fn solve_part2(&self, input: &[Policy]) -> i64 {
         let mut nof_ok_passwords: i64 = 0;
-        for line in input {
-            let mut tokens = line.split(" ");
-            let mut indexes = tokens.next().unwrap().split("-");
-            let index1 = indexes.next().unwrap().parse::<usize>().unwrap() - 1;
-            let index2 = indexes.next().unwrap().parse::<usize>().unwrap() - 1;
-            let c = tokens.next().unwrap().as_bytes()[0];
-            let pwd = tokens.next().unwrap();
+        for policy in input {
+            let pwd = policy.pwd.as_bytes();
             let mut count = 0;
-            if pwd.as_bytes()[index1] == c {
+            if policy.a > 0 && pwd.get(policy.a - 1) == Some(&policy.c) {
                 count += 1;
             }
-            if pwd.as_bytes()[index2] == c {
+            if policy.b > 0 && pwd.get(policy.b - 1) == Some(&policy.c) {
                 count += 1;
             }
             if count == 1 {
@@ -44,12 +87,51 @@ impl Day2 {
 }
 
 impl Puzzle for Day2 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(&input).to_string(),
-            self.solve_part2(&input).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.policies = parse_input(&read_lines(input)?)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&self.policies).to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(&self.policies).to_string())
+    }
+}
+
+// 'size' random policies and passwords.
+impl Generator for Day2 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let (mut old, mut new) = (0, 0);
+        let mut lines = vec![];
+        for _ in 0..size {
+            let a = rng.range(1..=10) as usize;
+            let b = a + rng.range(1..=10) as usize;
+            let c = b'a' + rng.below(26) as u8;
+            // Favour the policy letter so that both valid and invalid passwords are common.
+            let pwd: Vec<u8> = (0..rng.range((b as i64 - 4).max(1)..=b as i64 + 4))
+                .map(|_| {
+                    if rng.chance(0.4) {
+                        c
+                    } else {
+                        b'a' + rng.below(26) as u8
+                    }
+                })
+                .collect();
+            let count = pwd.iter().filter(|&&x| x == c).count();
+            old += (a <= count && count <= b) as usize;
+            new += ((pwd.get(a - 1) == Some(&c)) != (pwd.get(b - 1) == Some(&c))) as usize;
+            lines.push(format!(
+                "{}-{} {}: {}",
+                a,
+                b,
+                c as char,
+                String::from_utf8(pwd).unwrap()
+            ));
+        }
+        Generated::from_lines(lines).with_answers(old, new)
     }
 }
 
@@ -60,11 +142,14 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day2 {}.solve_part1(
-                &vec!("1-3 a: abcde", "1-3 b: cdefg", "2-9 c: ccccccccc")
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect()
+            Day2::default().solve_part1(
+                &parse_input(
+                    &vec!("1-3 a: abcde", "1-3 b: cdefg", "2-9 c: ccccccccc")
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<String>>()
+                )
+                .unwrap()
             ),
             2
         );
@@ -73,13 +158,24 @@ mod tests {
     #[test]
     fn part2_example1() {
         assert_eq!(
-            Day2 {}.solve_part2(
-                &vec!("1-3 a: abcde", "1-3 b: cdefg", "2-9 c: ccccccccc")
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect()
+            Day2::default().solve_part2(
+                &parse_input(
+                    &vec!("1-3 a: abcde", "1-3 b: cdefg", "2-9 c: ccccccccc")
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<String>>()
+                )
+                .unwrap()
             ),
             1
         );
     }
+
+    #[test]
+    fn malformed_range() {
+        match parse_input(&["1-x a: abcde".to_string()]) {
+            Err(PuzzleError::Parse { line, column, .. }) => assert_eq!((line, column), (1, 3)),
+            _ => panic!("Expected a parse error"),
+        }
+    }
 }
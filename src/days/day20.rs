@@ -1,114 +1,103 @@
-use crate::puzzle::{io, File, Puzzle};
+//! Day 20: Jurassic Jigsaw. [`parse_input`] reads the image [`Tile`]s, which are arranged so
+//! that all borders line up before the sea monsters are searched for.
+
+use crate::cancel::Cancel;
+use crate::generate::{Generated, Generator, Rng};
+use crate::grid::Grid;
+use crate::json::Value;
+use crate::parse::sections;
+use crate::progress::Progress;
+use crate::puzzle::{parse_token, read_lines, BufRead, Context, Puzzle, PuzzleError};
+use std::collections::HashSet;
 use std::fmt;
-pub struct Day20;
 
+#[derive(Default)]
+pub struct Day20 {
+    size: usize,
+    tiles: Vec<Tile>,
+    context: Context,
+}
+
+register_puzzle! {
+    puzzle: Day20,
+    year: 2020,
+    day: 20,
+    title: "Jurassic Jigsaw",
+    input: Sections,
+    reads_input: true,
+}
+
+/// A square tile of '.' and '#' pixels.
 #[derive(Clone)]
-struct Tile {
-    p: Vec<Vec<char>>,
-    id: usize,
+pub struct Tile {
+    pub p: Grid<char>,
+    pub id: usize,
 }
 
 impl Tile {
-    fn new(id: usize, pixels: Vec<Vec<char>>) -> Self {
-        Self { p: pixels, id: id }
+    pub fn new(id: usize, pixels: Grid<char>) -> Self {
+        Self { p: pixels, id }
     }
 
     fn pixels_active(&self) -> usize {
-        let mut count = 0;
-        for y in 0..self.p.len() {
-            for x in 0..self.p[0].len() {
-                if self.p[y][x] == '#' {
-                    count += 1;
-                }
-            }
-        }
-        count
+        self.p.count(&'#')
     }
 
     fn rotate_clockwise(&self, turns: usize) -> Tile {
-        let size_x = self.p[0].len();
-        let size_y = self.p.len();
         let mut result = self.clone();
         for _ in 0..turns {
-            let mut next = result.clone();
-            for (y, row) in result.p.iter().enumerate() {
-                for (x, p) in row.iter().enumerate() {
-                    next.p[(size_x - 1) - ((size_x - 1) - x)][(size_y - 1) - y] = *p;
-                }
-            }
-            result = next;
+            result.p = result.p.rotate_clockwise();
         }
         result
     }
 
-    fn flip_horizontal(&self) -> Tile {
-        let size_y = self.p.len();
-        let mut result = self.clone();
-        for (y, row) in self.p.iter().enumerate() {
-            for (x, p) in row.iter().enumerate() {
-                result.p[(size_y - 1) - y][x] = *p;
-            }
-        }
-        result
+    fn flip_vertical(&self) -> Tile {
+        Tile::new(self.id, self.p.flip_vertical())
     }
 
-    fn left_id(&self) -> u32 {
+    // Encode a border as a number, one bit per pixel.
+    fn border_id<I>(pixels: I) -> u32
+    where
+        I: Iterator<Item = char>,
+    {
         let mut id = 0;
-        for (i, row) in self.p.iter().enumerate() {
-            if row[0] == '#' {
+        for (i, p) in pixels.enumerate() {
+            if p == '#' {
                 id |= 1 << i;
             }
         }
         id
     }
 
+    fn left_id(&self) -> u32 {
+        Tile::border_id((0..self.p.height()).map(|y| self.p[(0, y)]))
+    }
+
     fn right_id(&self) -> u32 {
-        let mut id = 0;
-        for (i, row) in self.p.iter().enumerate() {
-            if row[row.len() - 1] == '#' {
-                id |= 1 << i;
-            }
-        }
-        id
+        let x = self.p.width() - 1;
+        Tile::border_id((0..self.p.height()).map(|y| self.p[(x, y)]))
     }
 
     fn top_id(&self) -> u32 {
-        let mut id = 0;
-        for (i, p) in self.p[0].iter().enumerate() {
-            if *p == '#' {
-                id |= 1 << i;
-            }
-        }
-        id
+        Tile::border_id(self.p.row(0).iter().cloned())
     }
 
     fn bottom_id(&self) -> u32 {
-        let mut id = 0;
-        for (i, p) in self.p[self.p.len() - 1].iter().enumerate() {
-            if *p == '#' {
-                id |= 1 << i;
-            }
-        }
-        id
+        Tile::border_id(self.p.row(self.p.height() - 1).iter().cloned())
     }
 
     fn contains(&self, pattern: &Tile) -> usize {
         let mut count = 0;
-        if pattern.p.len() > self.p.len() {
+        if pattern.p.height() > self.p.height() || pattern.p.width() > self.p.width() {
             return 0;
         }
-        if pattern.p[0].len() > self.p[0].len() {
-            return 0;
-        }
-        let xoffsets = self.p[0].len() - pattern.p[0].len();
-        let yoffsets = self.p.len() - pattern.p.len();
+        let xoffsets = self.p.width() - pattern.p.width();
+        let yoffsets = self.p.height() - pattern.p.height();
         for yoffset in 0..yoffsets {
             'next: for xoffset in 0..xoffsets {
-                for y in 0..pattern.p.len() {
-                    for x in 0..pattern.p[0].len() {
-                        if pattern.p[y][x] == '#' && self.p[y + yoffset][x + xoffset] != '#' {
-                            continue 'next;
-                        }
+                for ((x, y), p) in pattern.p.iter() {
+                    if *p == '#' && self.p[(x + xoffset, y + yoffset)] != '#' {
+                        continue 'next;
                     }
                 }
                 count += 1;
@@ -120,34 +109,38 @@ impl Tile {
 
 impl fmt::Display for Tile {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Tile {}:\n", self.id)?;
-        for row in &self.p {
-            for c in row {
-                write!(f, "{}", *c)?;
-            }
-            write!(f, "\n")?;
-        }
-        Ok(())
+        writeln!(f, "Tile {}:", self.id)?;
+        write!(f, "{}", self.p)
     }
 }
 
+const SEA_MONSTER: [&str; 3] = [
+    "                  # ",
+    "#    ##    ##    ###",
+    " #  #  #  #  #  #   ",
+];
+
 fn place(
     pos: usize,
-    tiles: &Vec<Tile>,
-    image: &Vec<Vec<Option<Tile>>>,
-) -> Option<Vec<Vec<Option<Tile>>>> {
+    tiles: &[Tile],
+    image: &[Vec<Option<Tile>>],
+    cancel: &Cancel,
+    progress: &Progress,
+) -> Result<Option<Vec<Vec<Option<Tile>>>>, PuzzleError> {
     let size = image.len();
     let y = pos / size;
     let x = pos % size;
 
-    if tiles.len() == 0 {
-        return Some(image.clone());
+    if tiles.is_empty() {
+        return Ok(Some(image.to_vec()));
     }
+    cancel.check()?;
+    progress.advance(1);
     for i in 0..tiles.len() {
         for turns in 0..4 {
             for flip in 0..2 {
                 let tile = if flip == 1 {
-                    tiles[i].flip_horizontal().rotate_clockwise(turns)
+                    tiles[i].flip_vertical().rotate_clockwise(turns)
                 } else {
                     tiles[i].rotate_clockwise(turns)
                 };
@@ -175,52 +168,100 @@ fn place(
                 {
                     continue;
                 }
-                let mut tiles_left = tiles.clone();
+                let mut tiles_left = tiles.to_vec();
                 tiles_left.remove(i);
-                let mut updated_image = image.clone();
+                let mut updated_image = image.to_vec();
                 updated_image[y][x] = Some(tile);
-                if let Some(result) = place(pos + 1, &tiles_left, &updated_image) {
-                    return Some(result);
+                let placed = place(pos + 1, &tiles_left, &updated_image, cancel, progress)?;
+                if let Some(result) = placed {
+                    return Ok(Some(result));
                 }
             }
         }
     }
-    return None;
+    Ok(None)
 }
 
-fn parse_input(input: &Vec<String>) -> (usize, Vec<Tile>) {
+/// Parse the tiles and get the number of tiles along each side of the square image.
+pub fn parse_input(input: &[String]) -> Result<(usize, Vec<Tile>), PuzzleError> {
     let mut tiles = vec![];
-    let mut id = 0;
-    let mut rows = vec![];
-    for line in input {
-        if line.contains("Tile") {
-            id = line
-                .split(" ")
-                .nth(1)
-                .unwrap()
-                .split(":")
-                .nth(0)
-                .unwrap()
-                .parse::<usize>()
-                .unwrap();
-        } else if line == "" {
-            tiles.push(Tile::new(id, rows));
-            rows = vec![];
-        } else {
+    let mut tile_size = 0;
+    for section in sections(input) {
+        let id = match section.header {
+            Some((i, header)) => match header.strip_prefix("Tile ") {
+                Some(id) => parse_token(i, &input[i], id)?,
+                None => {
+                    return Err(PuzzleError::malformed(
+                        i,
+                        &input[i],
+                        header,
+                        "expected 'Tile'",
+                    ))
+                }
+            },
+            None => {
+                let i = section.body.start;
+                return Err(PuzzleError::malformed(
+                    i,
+                    &input[i],
+                    &input[i],
+                    "expected 'Tile <id>:'",
+                ));
+            }
+        };
+        let mut rows: Vec<Vec<char>> = vec![];
+        for (i, line) in section.body.lines() {
+            if tile_size == 0 {
+                tile_size = line.len();
+                if tile_size > 32 {
+                    return Err(PuzzleError::malformed(i, line, line, "tile is too large"));
+                }
+            }
+            if line.len() != tile_size || rows.len() == tile_size {
+                return Err(PuzzleError::malformed(i, line, line, "tile is not square"));
+            }
+            if let Some(pos) = line.find(|c| c != '.' && c != '#') {
+                return Err(PuzzleError::malformed(
+                    i,
+                    line,
+                    &line[pos..],
+                    "unexpected character",
+                ));
+            }
             rows.push(line.chars().collect());
         }
+        tiles.push(Tile::new(id, Grid::from_rows(rows).unwrap()));
     }
-    if !rows.is_empty() {
-        tiles.push(Tile::new(id, rows));
+    if let Some(tile) = tiles.iter().find(|tile| tile.p.height() != tile_size) {
+        return Err(PuzzleError::NoSolution(format!(
+            "tile {} is not square",
+            tile.id
+        )));
     }
     let size = (tiles.len() as f64).sqrt() as usize;
-    (size, tiles)
+    if size == 0 || size * size != tiles.len() {
+        return Err(PuzzleError::NoSolution(format!(
+            "{} tiles cannot form a square image",
+            tiles.len()
+        )));
+    }
+    Ok((size, tiles))
+}
+
+fn arrange(
+    size: usize,
+    tiles: &[Tile],
+    context: &Context,
+) -> Result<Vec<Vec<Option<Tile>>>, PuzzleError> {
+    let empty = vec![vec![None; size]; size];
+    let image = place(0, tiles, &empty, &context.cancel, &context.progress)?;
+    image.ok_or_else(|| PuzzleError::no_solution("the tiles cannot be arranged into an image"))
 }
 
 fn merge_tiles(image: &Vec<Vec<Option<Tile>>>) -> Tile {
     let size = image.len();
     let mut rows = vec![];
-    let tile_size = image[0][0].as_ref().unwrap().p.len();
+    let tile_size = image[0][0].as_ref().unwrap().p.height();
     for y in 1..size * tile_size {
         if y % tile_size == 0 || (y + 1) % tile_size == 0 {
             continue;
@@ -233,43 +274,48 @@ fn merge_tiles(image: &Vec<Vec<Option<Tile>>>) -> Tile {
             }
             let tile_x = x / tile_size;
             let tile = image[tile_y][tile_x].as_ref().unwrap();
-            columns.push(tile.p[y % tile_size][x % tile_size]);
+            columns.push(tile.p[(x % tile_size, y % tile_size)]);
         }
         rows.push(columns);
     }
-    Tile::new(0, rows)
+    Tile::new(0, Grid::from_rows(rows).unwrap())
 }
 
 impl Day20 {
-    fn solve_part1(&self, input: Vec<String>) -> usize {
-        let (size, tiles) = parse_input(&input);
-        let image = place(0, &tiles, &mut vec![vec![None; size]; size]).unwrap();
-        image[0][0].as_ref().unwrap().id
+    pub fn solve_part1(&self, size: usize, tiles: &[Tile]) -> Result<usize, PuzzleError> {
+        let image = arrange(size, tiles, &self.context)?;
+        if self.context.trace.is_recording() {
+            let ids = image
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|tile| Value::Int(tile.as_ref().unwrap().id as i64))
+                        .collect()
+                })
+                .collect();
+            self.context.trace.grid("tile arrangement", ids);
+        }
+        Ok(image[0][0].as_ref().unwrap().id
             * image[size - 1][0].as_ref().unwrap().id
             * image[0][size - 1].as_ref().unwrap().id
-            * image[size - 1][size - 1].as_ref().unwrap().id
+            * image[size - 1][size - 1].as_ref().unwrap().id)
     }
 
-    fn solve_part2(&self, input: Vec<String>) -> usize {
-        let (size, tiles) = parse_input(&input);
-        let image = place(0, &tiles, &mut vec![vec![None; size]; size]).unwrap();
+    pub fn solve_part2(&self, size: usize, tiles: &[Tile]) -> Result<usize, PuzzleError> {
+        let image = arrange(size, tiles, &self.context)?;
         let image_tile = merge_tiles(&image);
-        let monster_pattern: Vec<Vec<char>> = vec![
-            "                  # ",
-            "#    ##    ##    ###",
-            " #  #  #  #  #  #   ",
-        ]
-        .iter()
-        .map(|x| x.to_string().chars().collect())
-        .collect();
-        let monster = Tile::new(0, monster_pattern);
-
-        let mut nof_monsters = std::usize::MIN;
+        let monster_pattern: Vec<Vec<char>> = SEA_MONSTER
+            .iter()
+            .map(|x| x.to_string().chars().collect())
+            .collect();
+        let monster = Tile::new(0, Grid::from_rows(monster_pattern).unwrap());
+
+        let mut nof_monsters = usize::MIN;
         let mut rough_waters = 0;
         for turns in 0..4 {
             for flip in 0..2 {
                 let changed_image = if flip == 1 {
-                    image_tile.flip_horizontal().rotate_clockwise(turns)
+                    image_tile.flip_vertical().rotate_clockwise(turns)
                 } else {
                     image_tile.rotate_clockwise(turns)
                 };
@@ -281,17 +327,140 @@ impl Day20 {
                 }
             }
         }
-        rough_waters
+        self.context
+            .trace
+            .value("sea monsters", nof_monsters as i64);
+        Ok(rough_waters)
     }
 }
 
 impl Puzzle for Day20 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(input.clone()).to_string(),
-            self.solve_part2(input.clone()).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        let (size, tiles) = parse_input(&read_lines(input)?)?;
+        self.size = size;
+        self.tiles = tiles;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(self.size, &self.tiles)?.to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(self.size, &self.tiles)?.to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+// About 'size' tiles, between 3 by 3 and 10 by 10 of them. The image is drawn first, with sea
+// monsters that do not overlap, and then cut into tiles whose borders are all different, even
+// when flipped, so that there is only one way to put them back together. The background is sparse
+// enough that it is unlikely to hold a sea monster of its own.
+impl Generator for Day20 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let side = ((size as f64).sqrt() as usize).clamp(3, 10);
+        let pixels = side * 8;
+        let mut image = vec![vec![false; pixels]; pixels];
+        for row in image.iter_mut() {
+            for pixel in row.iter_mut() {
+                *pixel = rng.chance(0.25);
+            }
+        }
+        let monster: Vec<(usize, usize)> = SEA_MONSTER
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.match_indices('#').map(move |(x, _)| (x, y)))
+            .collect();
+        let (width, height) = (SEA_MONSTER[0].len(), SEA_MONSTER.len());
+        let mut monsters: Vec<(usize, usize)> = vec![];
+        for _ in 0..pixels * pixels / 100 {
+            // Keep clear of the last row and column the monster could be in, which are not
+            // searched.
+            let (x, y) = (rng.below(pixels - width), rng.below(pixels - height));
+            if monsters
+                .iter()
+                .all(|(mx, my)| x.abs_diff(*mx) >= width || y.abs_diff(*my) >= height)
+            {
+                monsters.push((x, y));
+                for (dx, dy) in &monster {
+                    image[y + dy][x + dx] = true;
+                }
+            }
+        }
+        let rough_waters =
+            image.iter().flatten().filter(|pixel| **pixel).count() - monsters.len() * monster.len();
+
+        // Tiles overlap their neighbours by one row or column, the borders, which fill every
+        // ninth row and column of the lattice.
+        let lattice_size = side * 9 + 1;
+        let mut lattice = vec![vec![false; lattice_size]; lattice_size];
+        for y in 0..pixels {
+            for x in 0..pixels {
+                lattice[y / 8 * 9 + y % 8 + 1][x / 8 * 9 + x % 8 + 1] = image[y][x];
+            }
+        }
+        for y in (0..lattice_size).step_by(9) {
+            for x in (0..lattice_size).step_by(9) {
+                lattice[y][x] = rng.chance(0.5);
+            }
+        }
+        let mut borders = HashSet::new();
+        let mut border = |rng: &mut Rng, first: bool, last: bool| loop {
+            let mut pixels = vec![first];
+            pixels.extend((0..8).map(|_| rng.chance(0.5)));
+            pixels.push(last);
+            let flipped: Vec<bool> = pixels.iter().rev().copied().collect();
+            if !borders.contains(&flipped) && borders.insert(pixels.clone()) {
+                borders.insert(flipped);
+                return pixels;
+            }
+        };
+        for line in (0..lattice_size).step_by(9) {
+            for start in (0..lattice_size - 1).step_by(9) {
+                let row = border(rng, lattice[line][start], lattice[line][start + 9]);
+                lattice[line][start..=start + 9].copy_from_slice(&row);
+                let column = border(rng, lattice[start][line], lattice[start + 9][line]);
+                for (i, pixel) in column.into_iter().enumerate() {
+                    lattice[start + i][line] = pixel;
+                }
+            }
+        }
+
+        let mut ids: Vec<usize> = (1000..10000).collect();
+        rng.shuffle(&mut ids);
+        ids.truncate(side * side);
+        let mut tiles = vec![];
+        for (i, id) in ids.iter().enumerate() {
+            let (x, y) = (i % side * 9, i / side * 9);
+            let rows: Vec<Vec<char>> = lattice[y..=y + 9]
+                .iter()
+                .map(|row| {
+                    row[x..=x + 9]
+                        .iter()
+                        .map(|pixel| if *pixel { '#' } else { '.' })
+                        .collect()
+                })
+                .collect();
+            let mut tile = Tile::new(*id, Grid::from_rows(rows).unwrap());
+            if rng.chance(0.5) {
+                tile = tile.flip_vertical();
+            }
+            tiles.push(tile.rotate_clockwise(rng.below(4)));
+        }
+        let corners = ids[0] * ids[side - 1] * ids[side * (side - 1)] * ids[side * side - 1];
+        rng.shuffle(&mut tiles);
+
+        let mut lines = vec![];
+        for tile in &tiles {
+            lines.push(format!("Tile {}:", tile.id));
+            lines.extend((0..tile.p.height()).map(|y| tile.p.row(y).iter().collect::<String>()));
+            lines.push(String::new());
+        }
+        lines.pop();
+        Generated::from_lines(lines).with_answers(corners, rough_waters)
     }
 }
 
@@ -305,233 +474,234 @@ mod tests {
 
     #[test]
     fn part1_example1() {
+        let (size, tiles) = parse_input(&string_vec!(
+            "Tile 2311:",
+            "..##.#..#.",
+            "##..#.....",
+            "#...##..#.",
+            "####.#...#",
+            "##.##.###.",
+            "##...#.###",
+            ".#.#.#..##",
+            "..#....#..",
+            "###...#.#.",
+            "..###..###",
+            "",
+            "Tile 1951:",
+            "#.##...##.",
+            "#.####...#",
+            ".....#..##",
+            "#...######",
+            ".##.#....#",
+            ".###.#####",
+            "###.##.##.",
+            ".###....#.",
+            "..#.#..#.#",
+            "#...##.#..",
+            "",
+            "Tile 1171:",
+            "####...##.",
+            "#..##.#..#",
+            "##.#..#.#.",
+            ".###.####.",
+            "..###.####",
+            ".##....##.",
+            ".#...####.",
+            "#.##.####.",
+            "####..#...",
+            ".....##...",
+            "",
+            "Tile 1427:",
+            "###.##.#..",
+            ".#..#.##..",
+            ".#.##.#..#",
+            "#.#.#.##.#",
+            "....#...##",
+            "...##..##.",
+            "...#.#####",
+            ".#.####.#.",
+            "..#..###.#",
+            "..##.#..#.",
+            "",
+            "Tile 1489:",
+            "##.#.#....",
+            "..##...#..",
+            ".##..##...",
+            "..#...#...",
+            "#####...#.",
+            "#..#.#.#.#",
+            "...#.#.#..",
+            "##.#...##.",
+            "..##.##.##",
+            "###.##.#..",
+            "",
+            "Tile 2473:",
+            "#....####.",
+            "#..#.##...",
+            "#.##..#...",
+            "######.#.#",
+            ".#...#.#.#",
+            ".#########",
+            ".###.#..#.",
+            "########.#",
+            "##...##.#.",
+            "..###.#.#.",
+            "",
+            "Tile 2971:",
+            "..#.#....#",
+            "#...###...",
+            "#.#.###...",
+            "##.##..#..",
+            ".#####..##",
+            ".#..####.#",
+            "#..#.#..#.",
+            "..####.###",
+            "..#.#.###.",
+            "...#.#.#.#",
+            "",
+            "Tile 2729:",
+            "...#.#.#.#",
+            "####.#....",
+            "..#.#.....",
+            "....#..#.#",
+            ".##..##.#.",
+            ".#.####...",
+            "####.#.#..",
+            "##.####...",
+            "##..#.##..",
+            "#.##...##.",
+            "",
+            "Tile 3079:",
+            "#.#.#####.",
+            ".#..######",
+            "..#.......",
+            "######....",
+            "####.#..#.",
+            ".#...#.##.",
+            "#.#####.##",
+            "..#.###...",
+            "..#.......",
+            "..#.###..."
+        ))
+        .unwrap();
         assert_eq!(
-            Day20 {}.solve_part1(string_vec!(
-                "Tile 2311:",
-                "..##.#..#.",
-                "##..#.....",
-                "#...##..#.",
-                "####.#...#",
-                "##.##.###.",
-                "##...#.###",
-                ".#.#.#..##",
-                "..#....#..",
-                "###...#.#.",
-                "..###..###",
-                "",
-                "Tile 1951:",
-                "#.##...##.",
-                "#.####...#",
-                ".....#..##",
-                "#...######",
-                ".##.#....#",
-                ".###.#####",
-                "###.##.##.",
-                ".###....#.",
-                "..#.#..#.#",
-                "#...##.#..",
-                "",
-                "Tile 1171:",
-                "####...##.",
-                "#..##.#..#",
-                "##.#..#.#.",
-                ".###.####.",
-                "..###.####",
-                ".##....##.",
-                ".#...####.",
-                "#.##.####.",
-                "####..#...",
-                ".....##...",
-                "",
-                "Tile 1427:",
-                "###.##.#..",
-                ".#..#.##..",
-                ".#.##.#..#",
-                "#.#.#.##.#",
-                "....#...##",
-                "...##..##.",
-                "...#.#####",
-                ".#.####.#.",
-                "..#..###.#",
-                "..##.#..#.",
-                "",
-                "Tile 1489:",
-                "##.#.#....",
-                "..##...#..",
-                ".##..##...",
-                "..#...#...",
-                "#####...#.",
-                "#..#.#.#.#",
-                "...#.#.#..",
-                "##.#...##.",
-                "..##.##.##",
-                "###.##.#..",
-                "",
-                "Tile 2473:",
-                "#....####.",
-                "#..#.##...",
-                "#.##..#...",
-                "######.#.#",
-                ".#...#.#.#",
-                ".#########",
-                ".###.#..#.",
-                "########.#",
-                "##...##.#.",
-                "..###.#.#.",
-                "",
-                "Tile 2971:",
-                "..#.#....#",
-                "#...###...",
-                "#.#.###...",
-                "##.##..#..",
-                ".#####..##",
-                ".#..####.#",
-                "#..#.#..#.",
-                "..####.###",
-                "..#.#.###.",
-                "...#.#.#.#",
-                "",
-                "Tile 2729:",
-                "...#.#.#.#",
-                "####.#....",
-                "..#.#.....",
-                "....#..#.#",
-                ".##..##.#.",
-                ".#.####...",
-                "####.#.#..",
-                "##.####...",
-                "##..#.##..",
-                "#.##...##.",
-                "",
-                "Tile 3079:",
-                "#.#.#####.",
-                ".#..######",
-                "..#.......",
-                "######....",
-                "####.#..#.",
-                ".#...#.##.",
-                "#.#####.##",
-                "..#.###...",
-                "..#.......",
-                "..#.###..."
-            )),
-            20899048083289
+            Day20::default().solve_part1(size, &tiles),
+            Ok(20899048083289)
         );
     }
 
     #[test]
     fn part2_example1() {
-        assert_eq!(
-            Day20 {}.solve_part2(string_vec!(
-                "Tile 2311:",
-                "..##.#..#.",
-                "##..#.....",
-                "#...##..#.",
-                "####.#...#",
-                "##.##.###.",
-                "##...#.###",
-                ".#.#.#..##",
-                "..#....#..",
-                "###...#.#.",
-                "..###..###",
-                "",
-                "Tile 1951:",
-                "#.##...##.",
-                "#.####...#",
-                ".....#..##",
-                "#...######",
-                ".##.#....#",
-                ".###.#####",
-                "###.##.##.",
-                ".###....#.",
-                "..#.#..#.#",
-                "#...##.#..",
-                "",
-                "Tile 1171:",
-                "####...##.",
-                "#..##.#..#",
-                "##.#..#.#.",
-                ".###.####.",
-                "..###.####",
-                ".##....##.",
-                ".#...####.",
-                "#.##.####.",
-                "####..#...",
-                ".....##...",
-                "",
-                "Tile 1427:",
-                "###.##.#..",
-                ".#..#.##..",
-                ".#.##.#..#",
-                "#.#.#.##.#",
-                "....#...##",
-                "...##..##.",
-                "...#.#####",
-                ".#.####.#.",
-                "..#..###.#",
-                "..##.#..#.",
-                "",
-                "Tile 1489:",
-                "##.#.#....",
-                "..##...#..",
-                ".##..##...",
-                "..#...#...",
-                "#####...#.",
-                "#..#.#.#.#",
-                "...#.#.#..",
-                "##.#...##.",
-                "..##.##.##",
-                "###.##.#..",
-                "",
-                "Tile 2473:",
-                "#....####.",
-                "#..#.##...",
-                "#.##..#...",
-                "######.#.#",
-                ".#...#.#.#",
-                ".#########",
-                ".###.#..#.",
-                "########.#",
-                "##...##.#.",
-                "..###.#.#.",
-                "",
-                "Tile 2971:",
-                "..#.#....#",
-                "#...###...",
-                "#.#.###...",
-                "##.##..#..",
-                ".#####..##",
-                ".#..####.#",
-                "#..#.#..#.",
-                "..####.###",
-                "..#.#.###.",
-                "...#.#.#.#",
-                "",
-                "Tile 2729:",
-                "...#.#.#.#",
-                "####.#....",
-                "..#.#.....",
-                "....#..#.#",
-                ".##..##.#.",
-                ".#.####...",
-                "####.#.#..",
-                "##.####...",
-                "##..#.##..",
-                "#.##...##.",
-                "",
-                "Tile 3079:",
-                "#.#.#####.",
-                ".#..######",
-                "..#.......",
-                "######....",
-                "####.#..#.",
-                ".#...#.##.",
-                "#.#####.##",
-                "..#.###...",
-                "..#.......",
-                "..#.###..."
-            )),
-            273
-        );
+        let (size, tiles) = parse_input(&string_vec!(
+            "Tile 2311:",
+            "..##.#..#.",
+            "##..#.....",
+            "#...##..#.",
+            "####.#...#",
+            "##.##.###.",
+            "##...#.###",
+            ".#.#.#..##",
+            "..#....#..",
+            "###...#.#.",
+            "..###..###",
+            "",
+            "Tile 1951:",
+            "#.##...##.",
+            "#.####...#",
+            ".....#..##",
+            "#...######",
+            ".##.#....#",
+            ".###.#####",
+            "###.##.##.",
+            ".###....#.",
+            "..#.#..#.#",
+            "#...##.#..",
+            "",
+            "Tile 1171:",
+            "####...##.",
+            "#..##.#..#",
+            "##.#..#.#.",
+            ".###.####.",
+            "..###.####",
+            ".##....##.",
+            ".#...####.",
+            "#.##.####.",
+            "####..#...",
+            ".....##...",
+            "",
+            "Tile 1427:",
+            "###.##.#..",
+            ".#..#.##..",
+            ".#.##.#..#",
+            "#.#.#.##.#",
+            "....#...##",
+            "...##..##.",
+            "...#.#####",
+            ".#.####.#.",
+            "..#..###.#",
+            "..##.#..#.",
+            "",
+            "Tile 1489:",
+            "##.#.#....",
+            "..##...#..",
+            ".##..##...",
+            "..#...#...",
+            "#####...#.",
+            "#..#.#.#.#",
+            "...#.#.#..",
+            "##.#...##.",
+            "..##.##.##",
+            "###.##.#..",
+            "",
+            "Tile 2473:",
+            "#....####.",
+            "#..#.##...",
+            "#.##..#...",
+            "######.#.#",
+            ".#...#.#.#",
+            ".#########",
+            ".###.#..#.",
+            "########.#",
+            "##...##.#.",
+            "..###.#.#.",
+            "",
+            "Tile 2971:",
+            "..#.#....#",
+            "#...###...",
+            "#.#.###...",
+            "##.##..#..",
+            ".#####..##",
+            ".#..####.#",
+            "#..#.#..#.",
+            "..####.###",
+            "..#.#.###.",
+            "...#.#.#.#",
+            "",
+            "Tile 2729:",
+            "...#.#.#.#",
+            "####.#....",
+            "..#.#.....",
+            "....#..#.#",
+            ".##..##.#.",
+            ".#.####...",
+            "####.#.#..",
+            "##.####...",
+            "##..#.##..",
+            "#.##...##.",
+            "",
+            "Tile 3079:",
+            "#.#.#####.",
+            ".#..######",
+            "..#.......",
+            "######....",
+            "####.#..#.",
+            ".#...#.##.",
+            "#.#####.##",
+            "..#.###...",
+            "..#.......",
+            "..#.###..."
+        ))
+        .unwrap();
+        assert_eq!(Day20::default().solve_part2(size, &tiles), Ok(273));
     }
 }
@@ -1,152 +1,216 @@
-use std::collections::HashMap;
+//! Day 21: Allergen Assessment. Each [`Food`] lists its ingredients and the allergens known to
+//! be in it.
 
-use crate::puzzle::{io, File, Puzzle};
+use std::collections::{BTreeMap, HashSet};
 
-pub struct Day21;
+use crate::generate::{Generated, Generator, Rng};
+use crate::graph::{Bipartite, MatchingError};
+use crate::json::Value;
+use crate::puzzle::{read_lines, BufRead, Context, Puzzle, PuzzleError};
 
-fn parse_input(input: &Vec<String>) -> Vec<(Vec<String>, Vec<String>)> {
+#[derive(Default)]
+pub struct Day21 {
+    foods: Vec<Food>,
+    context: Context,
+}
+
+register_puzzle! {
+    puzzle: Day21,
+    year: 2020,
+    day: 21,
+    title: "Allergen Assessment",
+    input: Lines,
+    reads_input: true,
+}
+
+/// The ingredients and the allergens of a food.
+pub type Food = (Vec<String>, Vec<String>);
+
+/// Parse lines such as "mxmxvkd kfcds (contains dairy, fish)".
+pub fn parse_input(input: &[String]) -> Result<Vec<Food>, PuzzleError> {
     let mut foods = vec![];
-    for line in input {
-        let split: Vec<&str> = line.split("(contains").collect();
-        let food_ingredients: Vec<String> =
-            split[0].trim().split(" ").map(|x| x.to_string()).collect();
-        let allergens: Vec<String> = split[1]
+    for (i, line) in input.iter().enumerate() {
+        let mut split = line.splitn(2, "(contains");
+        let food_ingredients: Vec<String> = split
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|x| x.to_string())
+            .collect();
+        let allergens = split.next().ok_or_else(|| {
+            PuzzleError::malformed(i, line, &line[line.len()..], "missing '(contains'")
+        })?;
+        let allergens: Vec<String> = allergens
             .trim()
-            .split(")")
-            .nth(0)
-            .unwrap()
+            .strip_suffix(')')
+            .ok_or_else(|| PuzzleError::malformed(i, line, allergens, "missing ')'"))?
             .split(", ")
             .map(|x| x.to_string())
             .collect();
         foods.push((food_ingredients, allergens));
     }
-    foods
+    Ok(foods)
+}
+
+// The ingredients that may contain each allergen, those in every food that lists it, with the
+// allergens sorted by name.
+fn allergen_candidates(foods: &[Food]) -> BTreeMap<&str, HashSet<&str>> {
+    let mut candidates: BTreeMap<&str, HashSet<&str>> = BTreeMap::new();
+    for (food_ingredients, allergens) in foods {
+        let food_ingredients: HashSet<&str> = food_ingredients.iter().map(|x| &x[..]).collect();
+        for allergen in allergens {
+            candidates
+                .entry(allergen)
+                .and_modify(|ingredients| ingredients.retain(|x| food_ingredients.contains(x)))
+                .or_insert_with(|| food_ingredients.clone());
+        }
+    }
+    candidates
 }
 
 impl Day21 {
-    fn solve_part1(&self, input: Vec<String>) -> usize {
-        let mut ingredients: HashMap<String, Vec<String>> = HashMap::new();
-        let foods = parse_input(&input);
-
-        // Populate a hash table with allergen candidates for each ingredient.
-        for (food_ingredients, allergens) in &foods {
-            for ingredient in food_ingredients {
-                if let Some(ingredient_allergens) = ingredients.get_mut(ingredient) {
-                    for allergen in allergens {
-                        if !ingredient_allergens.contains(&allergen) {
-                            ingredient_allergens.push(allergen.clone());
-                        }
-                    }
-                } else {
-                    ingredients.insert(ingredient.clone(), allergens.clone());
-                }
+    pub fn solve_part1(&self, foods: &[Food]) -> usize {
+        // The safe ingredients are the ones that cannot contain any allergen.
+        let unsafe_ingredients: HashSet<&str> = allergen_candidates(foods)
+            .into_values()
+            .flatten()
+            .collect();
+        foods
+            .iter()
+            .flat_map(|(ingredients, _)| ingredients)
+            .filter(|ingredient| !unsafe_ingredients.contains(&ingredient[..]))
+            .count()
+    }
+
+    pub fn solve_part2(&self, foods: &[Food]) -> Result<String, PuzzleError> {
+        let candidates = allergen_candidates(foods);
+        let mut ingredients: Vec<&str> = candidates.values().flatten().copied().collect();
+        ingredients.sort_unstable();
+        ingredients.dedup();
+
+        // Match every allergen with exactly one of its candidate ingredients.
+        let mut graph = Bipartite::new(candidates.len(), ingredients.len());
+        for (allergen, allergen_ingredients) in candidates.values().enumerate() {
+            for ingredient in allergen_ingredients {
+                graph.add_edge(allergen, ingredients.binary_search(ingredient).unwrap());
             }
         }
-
-        // When an allergen is found in one food, remove that allergen candidate from all ingredients not in that food.
-        for (food_ingredients, allergens) in &foods {
-            for allergen in allergens {
-                for (ingredient, ingredient_allergens) in &mut ingredients {
-                    if !food_ingredients.contains(&ingredient) {
-                        ingredient_allergens.retain(|allergen2| allergen2 != allergen);
-                    }
+        let allergens: Vec<&str> = candidates.keys().copied().collect();
+        match graph.unique_matching() {
+            Ok(matching) => {
+                if self.context.trace.is_recording() {
+                    let rows = allergens
+                        .iter()
+                        .zip(&matching)
+                        .map(|(allergen, &i)| {
+                            vec![Value::from(*allergen), Value::from(ingredients[i])]
+                        })
+                        .collect();
+                    let columns = ["allergen", "ingredient"];
+                    self.context.trace.table("allergens", &columns, rows);
                 }
+                Ok(matching
+                    .iter()
+                    .map(|&i| ingredients[i])
+                    .collect::<Vec<&str>>()
+                    .join(","))
             }
+            Err(MatchingError::Unmatched(allergen)) => Err(PuzzleError::NoSolution(format!(
+                "no ingredient left for {}",
+                allergens[allergen]
+            ))),
+            Err(MatchingError::Ambiguous(ambiguous)) => Err(PuzzleError::NoSolution(format!(
+                "the ingredient with {} cannot be determined",
+                allergens[ambiguous[0]]
+            ))),
         }
+    }
+}
 
-        // The safe ingredients are now the ingredients with an empty allergen candidate list.
-        let safe_ingredients: Vec<String> = ingredients
-            .iter()
-            .filter(|(_, allergens)| allergens.is_empty())
-            .map(|(ingredient, _)| ingredient.clone())
-            .collect();
+impl Puzzle for Day21 {
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.foods = parse_input(&read_lines(input)?)?;
+        Ok(())
+    }
 
-        // Count how often the safe ingredients appear in the food recipes.
-        foods.iter().fold(0, |count, (ingredients, _)| {
-            count
-                + ingredients.iter().fold(0, |inner_count, ingredient| {
-                    if safe_ingredients.contains(ingredient) {
-                        inner_count + 1
-                    } else {
-                        inner_count
-                    }
-                })
-        })
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&self.foods).to_string())
     }
 
-    fn solve_part2(&self, input: Vec<String>) -> String {
-        let mut ingredients: HashMap<String, Vec<String>> = HashMap::new();
-        let foods = parse_input(&input);
-
-        // Populate a hash table with allergen candidates for each ingredient.
-        for (food_ingredients, allergens) in &foods {
-            for ingredient in food_ingredients {
-                if let Some(ingredient_allergens) = ingredients.get_mut(ingredient) {
-                    for allergen in allergens {
-                        if !ingredient_allergens.contains(&allergen) {
-                            ingredient_allergens.push(allergen.clone());
-                        }
-                    }
-                } else {
-                    ingredients.insert(ingredient.clone(), allergens.clone());
-                }
-            }
-        }
+    fn part2(&self) -> Result<String, PuzzleError> {
+        self.solve_part2(&self.foods)
+    }
 
-        // When an allergen is found in one food, remove that allergen candidate from all ingredients not in that food.
-        for (food_ingredients, allergens) in &foods {
-            for allergen in allergens {
-                for (ingredient, ingredient_allergens) in &mut ingredients {
-                    if !food_ingredients.contains(&ingredient) {
-                        ingredient_allergens.retain(|x| x != allergen);
-                    }
-                }
-            }
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+const ALLERGENS: [&str; 8] = [
+    "dairy", "eggs", "fish", "nuts", "peanuts", "sesame", "soy", "wheat",
+];
+
+// 'size' foods, made of ingredients with made up names, at most 8 of which contain an allergen.
+// Every allergen is listed by two foods that have no other ingredient in common, so only one
+// ingredient can contain it and all the others are known to be safe.
+impl Generator for Day21 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let allergens = (size / 4).clamp(1, ALLERGENS.len());
+        let mut names = HashSet::new();
+        while names.len() < allergens + 5 + size / 2 {
+            let len = rng.range(4..=8);
+            names.insert((0..len).map(|_| (b'a' + rng.below(26) as u8) as char).collect());
         }
+        let mut ingredients: Vec<String> = names.into_iter().collect();
+        ingredients.sort_unstable();
+        rng.shuffle(&mut ingredients);
+        // Ingredient i contains allergen i, if there is one.
+        let contains_allergen = |ingredient: usize| ingredient < allergens;
 
-        // When an ingredient only has one allergen candidate left it can be removed from all
-        // other ingredients candidate lists. Keep cleaning up the candidate lists like this until
-        // all allergens are found.
-        ingredients.retain(|_, allergens| !allergens.is_empty());
-        let mut go_again = true;
-        while go_again {
-            go_again = false;
-            for (ingredient, allergens) in ingredients.clone() {
-                if allergens.len() == 1 {
-                    for (ingredient2, allergens2) in &mut ingredients {
-                        if *ingredient2 != ingredient {
-                            let len_before = allergens2.len();
-                            allergens2.retain(|allergens3| *allergens3 != allergens[0]);
-                            if allergens2.len() != len_before {
-                                go_again = true;
-                            }
-                        }
-                    }
-                }
+        let mut foods: Vec<(Vec<usize>, Vec<usize>)> = vec![];
+        for allergen in 0..allergens {
+            let mut others: Vec<usize> = (0..ingredients.len())
+                .filter(|&i| i != allergen)
+                .collect();
+            rng.shuffle(&mut others);
+            let (first, second) = others.split_at(others.len() / 2);
+            for half in [first, second].iter() {
+                let mut food = vec![allergen];
+                food.extend(half.iter().filter(|_| rng.chance(0.5)));
+                foods.push((food, vec![allergen]));
             }
         }
-
-        // Create a sorted ingredient vector and return a String version of it.
-        let mut ingredients_vector: Vec<_> = ingredients.iter().collect();
-        ingredients_vector.sort_by(|x, y| x.1[0].cmp(&y.1[0]));
-        let mut result = String::new();
-        for (i, (ingredient, _)) in ingredients_vector.iter().enumerate() {
-            if i > 0 {
-                result += ",";
+        while foods.len() < size {
+            let listed: Vec<usize> = (0..allergens).filter(|_| rng.chance(0.3)).collect();
+            if listed.is_empty() {
+                continue;
             }
-            result += ingredient;
+            let mut food = listed.clone();
+            food.extend((allergens..ingredients.len()).filter(|_| rng.chance(0.3)));
+            food.extend((0..allergens).filter(|i| !listed.contains(i) && rng.chance(0.2)));
+            foods.push((food, listed));
         }
-        result
-    }
-}
+        rng.shuffle(&mut foods);
 
-impl Puzzle for Day21 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(input.clone()).to_string(),
-            self.solve_part2(input.clone()).to_string(),
-        );
+        let safe = foods
+            .iter()
+            .flat_map(|(food, _)| food)
+            .filter(|&&i| !contains_allergen(i))
+            .count();
+        let mut dangerous: Vec<usize> = (0..allergens).collect();
+        dangerous.sort_by_key(|&i| ALLERGENS[i]);
+        let dangerous: Vec<&str> = dangerous.iter().map(|&i| &ingredients[i][..]).collect();
+
+        let lines = foods
+            .iter_mut()
+            .map(|(food, listed)| {
+                rng.shuffle(food);
+                let food: Vec<&str> = food.iter().map(|&i| &ingredients[i][..]).collect();
+                let listed: Vec<&str> = listed.iter().map(|&i| ALLERGENS[i]).collect();
+                format!("{} (contains {})", food.join(" "), listed.join(", "))
+            })
+            .collect();
+        Generated::from_lines(lines).with_answers(safe, dangerous.join(","))
     }
 }
 
@@ -161,12 +225,15 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day21 {}.solve_part1(string_vec!(
-                "mxmxvkd kfcds sqjhc nhms (contains dairy, fish)",
-                "trh fvjkl sbzzf mxmxvkd (contains dairy)",
-                "sqjhc fvjkl (contains soy)",
-                "sqjhc mxmxvkd sbzzf (contains fish)"
-            )),
+            Day21::default().solve_part1(
+                &parse_input(&string_vec!(
+                    "mxmxvkd kfcds sqjhc nhms (contains dairy, fish)",
+                    "trh fvjkl sbzzf mxmxvkd (contains dairy)",
+                    "sqjhc fvjkl (contains soy)",
+                    "sqjhc mxmxvkd sbzzf (contains fish)"
+                ))
+                .unwrap()
+            ),
             5
         );
     }
@@ -174,13 +241,34 @@ mod tests {
     #[test]
     fn part2_example1() {
         assert_eq!(
-            Day21 {}.solve_part2(string_vec!(
-                "mxmxvkd kfcds sqjhc nhms (contains dairy, fish)",
-                "trh fvjkl sbzzf mxmxvkd (contains dairy)",
-                "sqjhc fvjkl (contains soy)",
-                "sqjhc mxmxvkd sbzzf (contains fish)"
-            )),
+            Day21::default()
+                .solve_part2(
+                    &parse_input(&string_vec!(
+                        "mxmxvkd kfcds sqjhc nhms (contains dairy, fish)",
+                        "trh fvjkl sbzzf mxmxvkd (contains dairy)",
+                        "sqjhc fvjkl (contains soy)",
+                        "sqjhc mxmxvkd sbzzf (contains fish)"
+                    ))
+                    .unwrap()
+                )
+                .unwrap(),
             "mxmxvkd,sqjhc,fvjkl"
         );
     }
+
+    #[test]
+    fn part2_ambiguous_allergens() {
+        assert_eq!(
+            Day21::default().solve_part2(
+                &parse_input(&[
+                    "mxmxvkd sqjhc (contains dairy, fish)".to_string(),
+                    "mxmxvkd sqjhc kfcds (contains fish)".to_string()
+                ])
+                .unwrap()
+            ),
+            Err(PuzzleError::NoSolution(
+                "the ingredient with dairy cannot be determined".to_string()
+            ))
+        );
+    }
 }
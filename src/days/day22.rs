@@ -1,10 +1,38 @@
+//! Day 22: Crab Combat. [`play`] plays the game with the two decks from [`parse_input`], either
+//! the plain or the recursive version.
+
 use std::collections::HashSet;
 
-use crate::puzzle::{io, File, Puzzle};
+use crate::cancel::Cancel;
+use crate::generate::{Generated, Generator, Rng};
+use crate::parse::named_sections;
+use crate::progress::Progress;
+use crate::puzzle::{parse_token, read_lines, BufRead, Context, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day22 {
+    decks: Vec<Vec<usize>>,
+    context: Context,
+}
 
-pub struct Day22;
+register_puzzle! {
+    puzzle: Day22,
+    year: 2020,
+    day: 22,
+    title: "Crab Combat",
+    input: Sections,
+    reads_input: true,
+}
 
-fn play(decks: &mut Vec<Vec<usize>>, recursive: bool) -> usize {
+/// Play until one player has all cards and return the winner's index. The decks are left
+/// as they are at the end of the game. Gives up with the error of 'cancel' once it is cancelled.
+/// Every round played, including those of sub-games, is reported to 'progress'.
+pub fn play(
+    decks: &mut Vec<Vec<usize>>,
+    recursive: bool,
+    cancel: &Cancel,
+    progress: &Progress,
+) -> Result<usize, PuzzleError> {
     let mut dp: HashSet<Vec<Vec<usize>>> = HashSet::new();
     let mut winner = 0;
     loop {
@@ -16,6 +44,8 @@ fn play(decks: &mut Vec<Vec<usize>>, recursive: bool) -> usize {
         if decks[0].is_empty() || decks[1].is_empty() {
             break;
         }
+        cancel.check()?;
+        progress.advance(1);
         winner = 0;
         let card0 = decks[0].remove(0);
         let card1 = decks[1].remove(0);
@@ -23,7 +53,7 @@ fn play(decks: &mut Vec<Vec<usize>>, recursive: bool) -> usize {
             let mut new_deck = vec![];
             new_deck.push(decks[0][..card0].to_vec());
             new_deck.push(decks[1][..card1].to_vec());
-            winner = play(&mut new_deck, recursive);
+            winner = play(&mut new_deck, recursive, cancel, progress)?;
         } else if card1 > card0 {
             winner = 1;
         }
@@ -35,52 +65,71 @@ fn play(decks: &mut Vec<Vec<usize>>, recursive: bool) -> usize {
             decks[winner].push(card0);
         }
     }
-    winner
+    Ok(winner)
 }
 
-fn parse_input(input: &Vec<String>) -> Vec<Vec<usize>> {
-    let mut decks = vec![vec![]; 2];
-    let mut current_deck = 0;
-    for line in input {
-        if line.contains("Player 2:") {
-            current_deck = 1;
-            continue;
+/// Parse the two decks, top card first.
+pub fn parse_input(input: &[String]) -> Result<Vec<Vec<usize>>, PuzzleError> {
+    let mut decks = vec![];
+    for section in named_sections(input, &["Player 1", "Player 2"])? {
+        let mut deck = vec![];
+        for (i, line) in section.lines() {
+            deck.push(parse_token(i, line, line)?);
         }
-        if line == "" || line.contains("Player 1:") {
-            continue;
-        }
-        decks[current_deck].push(line.parse::<usize>().unwrap())
+        decks.push(deck);
     }
-    decks
+    Ok(decks)
 }
 
 impl Day22 {
-    fn solve_part1(&self, input: Vec<String>) -> usize {
-        let mut decks = parse_input(&input);
-        let winner = play(&mut decks, false);
-        decks[winner]
+    pub fn solve_part1(&self, mut decks: Vec<Vec<usize>>) -> Result<usize, PuzzleError> {
+        let winner = play(&mut decks, false, &self.context.cancel, &self.context.progress)?;
+        Ok(decks[winner]
             .iter()
             .enumerate()
-            .fold(0, |sum, (i, card)| sum + card * (decks[winner].len() - i))
+            .fold(0, |sum, (i, card)| sum + card * (decks[winner].len() - i)))
     }
 
-    fn solve_part2(&self, input: Vec<String>) -> usize {
-        let mut decks = parse_input(&input);
-        let winner = play(&mut decks, true);
-        decks[winner]
+    pub fn solve_part2(&self, mut decks: Vec<Vec<usize>>) -> Result<usize, PuzzleError> {
+        let winner = play(&mut decks, true, &self.context.cancel, &self.context.progress)?;
+        Ok(decks[winner]
             .iter()
             .enumerate()
-            .fold(0, |sum, (i, card)| sum + card * (decks[winner].len() - i))
+            .fold(0, |sum, (i, card)| sum + card * (decks[winner].len() - i)))
     }
 }
 
 impl Puzzle for Day22 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(input.clone()).to_string(),
-            self.solve_part2(input.clone()).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.decks = parse_input(&read_lines(input)?)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(self.decks.clone())?.to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(self.decks.clone())?.to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+// The cards 1 to 'size', at least 4 and at most 50, shuffled and dealt into two decks.
+impl Generator for Day22 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let mut cards: Vec<usize> = (1..=size.clamp(4, 50)).collect();
+        rng.shuffle(&mut cards);
+        let (first, second) = cards.split_at(cards.len() / 2);
+        let mut lines = vec!["Player 1:".to_string()];
+        lines.extend(first.iter().map(|card| card.to_string()));
+        lines.push(String::new());
+        lines.push("Player 2:".to_string());
+        lines.extend(second.iter().map(|card| card.to_string()));
+        Generated::from_lines(lines)
     }
 }
 
@@ -95,44 +144,50 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day22 {}.solve_part1(string_vec!(
-                "Player 1:",
-                "9",
-                "2",
-                "6",
-                "3",
-                "1",
-                "",
-                "Player 2:",
-                "5",
-                "8",
-                "4",
-                "7",
-                "10"
-            )),
-            306
+            Day22::default().solve_part1(
+                parse_input(&string_vec!(
+                    "Player 1:",
+                    "9",
+                    "2",
+                    "6",
+                    "3",
+                    "1",
+                    "",
+                    "Player 2:",
+                    "5",
+                    "8",
+                    "4",
+                    "7",
+                    "10"
+                ))
+                .unwrap()
+            ),
+            Ok(306)
         );
     }
 
     #[test]
     fn part2_example1() {
         assert_eq!(
-            Day22 {}.solve_part2(string_vec!(
-                "Player 1:",
-                "9",
-                "2",
-                "6",
-                "3",
-                "1",
-                "",
-                "Player 2:",
-                "5",
-                "8",
-                "4",
-                "7",
-                "10"
-            )),
-            291
+            Day22::default().solve_part2(
+                parse_input(&string_vec!(
+                    "Player 1:",
+                    "9",
+                    "2",
+                    "6",
+                    "3",
+                    "1",
+                    "",
+                    "Player 2:",
+                    "5",
+                    "8",
+                    "4",
+                    "7",
+                    "10"
+                ))
+                .unwrap()
+            ),
+            Ok(291)
         );
     }
 }
@@ -1,10 +1,54 @@
-use crate::puzzle::{io, File, Puzzle};
+//! Day 23: Crab Cups. The cup labels are checked by [`parse_cups`]; the solvers take the number of
+//! moves to play, and part 2 the number of cups, which come from the puzzle parameters.
 
-pub struct Day23;
+use crate::bigint::BigUint;
+use crate::cancel::Cancel;
+use crate::generate::{Generated, Generator, Rng};
+use crate::params::Param;
+use crate::progress::Progress;
+use crate::puzzle::{read_lines, single_line, BufRead, Context, Puzzle, PuzzleError};
+use crate::reference::{Answer, Reference};
 
-fn play(cups: &mut Vec<usize>, mut cur: usize, turns: usize) {
+#[derive(Default)]
+pub struct Day23 {
+    cups: String,
+    context: Context,
+}
+
+const PART1_MOVES: Param = Param::integer("part1_moves", 100, 0, "The number of moves in part 1");
+const PART2_MOVES: Param =
+    Param::integer("part2_moves", 10000000, 0, "The number of moves in part 2");
+// Every cup takes a word of memory, the limit keeps that under a gigabyte.
+const PART2_CUPS: Param = Param::integer_range(
+    "part2_cups",
+    1000000,
+    5,
+    100000000,
+    "The number of cups in part 2, at least the number of labels",
+);
+
+register_puzzle! {
+    puzzle: Day23,
+    year: 2020,
+    day: 23,
+    title: "Crab Cups",
+    input: SingleLine,
+    reads_input: true,
+    params: &[PART1_MOVES, PART2_MOVES, PART2_CUPS],
+    reference: true,
+}
+
+fn play(
+    cups: &mut [usize],
+    mut cur: usize,
+    turns: usize,
+    cancel: &Cancel,
+    progress: &Progress,
+) -> Result<(), PuzzleError> {
     let max = cups.len() - 1;
-    for _ in 0..turns {
+    for turn in 0..turns {
+        cancel.check()?;
+        progress.update(turn, Some(turns));
         let mut removals = vec![];
 
         let mut next = cups[cur];
@@ -16,28 +60,75 @@ fn play(cups: &mut Vec<usize>, mut cur: usize, turns: usize) {
 
         let mut dest = 0;
         for i in 1..max + 1 {
-            dest = cur as i32 - (i as i32);
-            if dest <= 0 {
-                dest = max as i32 + dest;
-            }
-            if !removals.contains(&(dest as usize)) {
+            dest = if cur > i { cur - i } else { max + cur - i };
+            if !removals.contains(&dest) {
                 break;
             }
         }
 
-        let tmp = cups[dest as usize];
-        cups[dest as usize] = removals[0];
+        let tmp = cups[dest];
+        cups[dest] = removals[0];
         cups[removals[2]] = tmp;
         cur = cups[cur];
     }
+    Ok(())
+}
+
+/// The cup labels must be the digits 1 to N, each used once, with room for the three cups picked
+/// up and a destination cup.
+pub fn parse_cups(line: &str) -> Result<String, PuzzleError> {
+    let mut seen = vec![];
+    for (i, c) in line.char_indices() {
+        let token = &line[i..i + c.len_utf8()];
+        match c.to_digit(10) {
+            Some(label) if label >= 1 && label as usize <= line.len() => {
+                if seen.contains(&label) {
+                    return Err(PuzzleError::malformed(
+                        0,
+                        line,
+                        token,
+                        "duplicate cup label",
+                    ));
+                }
+                seen.push(label);
+            }
+            _ => {
+                return Err(PuzzleError::malformed(
+                    0,
+                    line,
+                    token,
+                    "cup labels must be the digits 1 to the number of cups",
+                ))
+            }
+        }
+    }
+    if seen.len() < 5 {
+        return Err(PuzzleError::malformed(
+            0,
+            line,
+            line,
+            "at least 5 cups are needed",
+        ));
+    }
+    Ok(line.to_string())
+}
+
+// The labels of the cups in 'line', checked with parse_cups.
+fn labels(line: &str) -> Result<Vec<usize>, PuzzleError> {
+    Ok(parse_cups(line)?
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .map(|label| label as usize)
+        .collect())
 }
 
 impl Day23 {
-    fn solve_part1(&self, input: &str, turns: usize) -> String {
-        let input: Vec<usize> = input
-            .chars()
-            .map(|x| x.to_digit(10).unwrap() as usize)
-            .collect();
+    fn moves(&self, param: &Param) -> usize {
+        self.context.params.integer(param) as usize
+    }
+
+    pub fn solve_part1(&self, input: &str, turns: usize) -> Result<String, PuzzleError> {
+        let input = labels(input)?;
 
         // Build kind of a linked list vector where you can index a cup label to find the next cup.
         // This gives O(1) time complexity for insertions and removals.
@@ -46,7 +137,13 @@ impl Day23 {
             cups[input[i]] = input[(i + 1) % input.len()];
         }
 
-        play(&mut cups, input[0], turns);
+        play(
+            &mut cups,
+            input[0],
+            turns,
+            &self.context.cancel,
+            &self.context.progress,
+        )?;
 
         let mut result = String::new();
 
@@ -55,45 +152,119 @@ impl Day23 {
             result += &cups[next].to_string();
             next = cups[next];
         }
-        result
+        Ok(result)
     }
 
-    fn solve_part2(&self, input: &str, turns: usize) -> usize {
-        let input: Vec<usize> = input
-            .chars()
-            .map(|x| x.to_digit(10).unwrap() as usize)
-            .collect();
+    /// Play with the labelled cups followed by the cups 'labels + 1' to 'total', and multiply the
+    /// labels of the two cups after cup 1.
+    pub fn solve_part2(
+        &self,
+        input: &str,
+        turns: usize,
+        total: usize,
+    ) -> Result<BigUint, PuzzleError> {
+        let input = labels(input)?;
+        if total < input.len() {
+            return Err(PuzzleError::NoSolution(format!(
+                "{} cups are fewer than the {} labelled ones",
+                total,
+                input.len()
+            )));
+        }
 
         // Build kind of a linked list vector where you can index a cup label to find the next cup.
         // This gives O(1) time complexity for insertions and removals.
         let mut cups: Vec<usize> = vec![0; input.len() + 1];
-        for i in 0..cups.len() - 2 {
-            cups[input[i]] = input[(i + 1)];
+        for i in 0..input.len() - 1 {
+            cups[input[i]] = input[i + 1];
         }
-        cups[input[input.len() - 1]] = 10;
-        for i in 10..1000000 {
-            cups.push(i + 1);
+        if total > input.len() {
+            cups[input[input.len() - 1]] = input.len() + 1;
+            for i in input.len() + 1..total {
+                cups.push(i + 1);
+            }
+            cups.push(input[0]);
+        } else {
+            cups[input[input.len() - 1]] = input[0];
         }
-        cups.push(input[0]);
 
-        play(&mut cups, input[0], turns);
+        play(
+            &mut cups,
+            input[0],
+            turns,
+            &self.context.cancel,
+            &self.context.progress,
+        )?;
 
-        let mut result = String::new();
-        let mut next = 1;
-        for _ in 1..cups.len() - 1 {
-            result += &cups[next].to_string();
-            next = cups[next];
-        }
-        cups[1] as usize * cups[cups[1]] as usize
+        Ok(&BigUint::from(cups[1] as u64) * &BigUint::from(cups[cups[1]] as u64))
     }
 }
 
 impl Puzzle for Day23 {
-    fn solve(&self, _: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        return (
-            self.solve_part1("792845136", 100),
-            self.solve_part2("792845136", 10000000).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.cups = parse_cups(single_line(&read_lines(input)?)?)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        self.solve_part1(&self.cups, self.moves(&PART1_MOVES))
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        let total = self.context.params.integer(&PART2_CUPS) as usize;
+        Ok(self
+            .solve_part2(&self.cups, self.moves(&PART2_MOVES), total)?
+            .to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+// The cups are labelled with single digits, so 'size' only picks how many there are, from 5 to 9.
+impl Generator for Day23 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let mut cups: Vec<u32> = (1..=size.clamp(5, 9) as u32).collect();
+        rng.shuffle(&mut cups);
+        let line = cups
+            .iter()
+            .map(|&cup| std::char::from_digit(cup, 10).unwrap())
+            .collect();
+        Generated::from_lines(vec![line])
+    }
+}
+
+// Play the moves on a list of cups that is rotated so that the current cup is always the first
+// one. Far too slow for the second part.
+impl Reference for Day23 {
+    fn reference(&self, part: u8) -> Option<Answer> {
+        if part != 1 {
+            return None;
+        }
+        let mut cups: Vec<u32> = self.cups.chars().map(|c| c.to_digit(10).unwrap()).collect();
+        let max = cups.len() as u32;
+        for _ in 0..self.moves(&PART1_MOVES) {
+            let current = cups[0];
+            let picked: Vec<u32> = cups.drain(1..4).collect();
+            let mut destination = current;
+            loop {
+                destination = if destination == 1 {
+                    max
+                } else {
+                    destination - 1
+                };
+                if !picked.contains(&destination) {
+                    break;
+                }
+            }
+            let at = cups.iter().position(|cup| *cup == destination).unwrap();
+            cups.splice(at + 1..at + 1, picked);
+            cups.rotate_left(1);
+        }
+        let one = cups.iter().position(|cup| *cup == 1).unwrap();
+        cups.rotate_left(one);
+        Some(Ok(cups[1..].iter().map(|cup| cup.to_string()).collect()))
     }
 }
 
@@ -103,12 +274,47 @@ mod tests {
 
     #[test]
     fn part1_example1() {
-        assert_eq!(Day23 {}.solve_part1("389125467", 10), "92658374");
+        assert_eq!(
+            Day23::default().solve_part1("389125467", 10),
+            Ok("92658374".to_string())
+        );
+    }
+
+    #[test]
+    fn part2_without_extra_cups() {
+        // After 10 moves of the example the cups after cup 1 are 9 and 2.
+        let product = Day23::default().solve_part2("389125467", 10, 9).unwrap();
+        assert_eq!(product, BigUint::from(18));
+        assert!(Day23::default().solve_part2("389125467", 10, 8).is_err());
+    }
+
+    #[test]
+    fn malformed_cups() {
+        assert_eq!(
+            Day23::default().parse_str("38912a467").err(),
+            Some(PuzzleError::Parse {
+                line: 1,
+                column: 6,
+                text: "a".to_string(),
+                reason: "cup labels must be the digits 1 to the number of cups".to_string(),
+            })
+        );
+        assert!(Day23::default().parse_str("389125463").is_err());
+        assert!(Day23::default().parse_str("3912").is_err());
+        assert!(Day23::default().solve_part1("", 10).is_err());
+        assert!(Day23::default().solve_part1("38a", 10).is_err());
+        assert!(Day23::default().solve_part2("", 10, 9).is_err());
+        assert!(Day23::default().solve_part2("38a", 10, 9).is_err());
     }
 
     // Disable this unit test since its too slow to run for every build.
     // #[test]
     // fn part2_example1() {
-    //     assert_eq!(Day23 {}.solve_part2("389125467", 10000000), 149245887792);
+    //     assert_eq!(
+    //         Day23::default()
+    //             .solve_part2("389125467", 10000000, 1000000)
+    //             .unwrap(),
+    //         BigUint::from(149245887792)
+    //     );
     // }
 }
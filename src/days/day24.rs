@@ -1,11 +1,33 @@
-use std::collections::HashSet;
+//! Day 24: Lobby Layout. Each tile is given by the [`Direction`]s leading to it from the
+//! reference tile, the solvers flip the tiles and then run the daily flipping rules as a cellular
+//! automaton on the [`HexLattice`].
 
-use crate::puzzle::{io, File, Puzzle};
+use crate::automaton::{Automaton, Cells, HexLattice, Rule, HEX_DIRECTIONS};
+use crate::generate::{Generated, Generator, Rng};
+use crate::params::Param;
+use crate::puzzle::{read_lines, BufRead, Context, Puzzle, PuzzleError};
 
-pub struct Day24;
+#[derive(Default)]
+pub struct Day24 {
+    tiles: Vec<Vec<Direction>>,
+    context: Context,
+}
+
+const DAYS: Param = Param::integer("days", 100, 0, "The number of days of flipping in part 2");
+
+register_puzzle! {
+    puzzle: Day24,
+    year: 2020,
+    day: 24,
+    title: "Lobby Layout",
+    input: Lines,
+    reads_input: true,
+    params: &[DAYS],
+}
 
-#[derive(Debug)]
-enum Direction {
+/// A direction on the hexagonal grid.
+#[derive(Debug, Clone)]
+pub enum Direction {
     E,
     SE,
     SW,
@@ -14,166 +36,125 @@ enum Direction {
     NE,
 }
 
-const ALL_DIRECTIONS: [Direction; 6] = [
-    Direction::E,
-    Direction::W,
-    Direction::NE,
-    Direction::SW,
-    Direction::SE,
-    Direction::NW,
-];
-
-#[derive(PartialEq, Eq, Clone, Debug, Hash)]
-struct Tile(i64, i64, i64);
-
-impl Tile {
-    fn new(e_w: i64, nw_se: i64, ne_sw: i64) -> Self {
-        let mut tile = Self(e_w, nw_se, ne_sw);
-        tile.normalize();
-        tile
-    }
-
-    fn add_tile(&self, other: &Tile) -> Tile {
-        let mut result = Tile(self.0 + other.0, self.1 + other.1, self.2 + other.2);
-        result.normalize();
-        result
-    }
-
-    fn add_direction(&self, direction: &Direction) -> Tile {
-        let direction_tile = match direction {
-            Direction::NE => Tile(0, 0, 1),
-            Direction::SW => Tile(0, 0, -1),
-            Direction::SE => Tile(0, 1, 0),
-            Direction::NW => Tile(0, -1, 0),
-            Direction::E => Tile(1, 0, 0),
-            Direction::W => Tile(-1, 0, 0),
-        };
-        self.add_tile(&direction_tile)
-    }
-
-    fn normalize(&mut self) {
-        let diff = std::cmp::min(self.1, self.2);
-        self.1 -= diff;
-        self.2 -= diff;
-        self.0 += diff;
-    }
-}
-
-fn get_black_adjacent_tiles(tile: &Tile, floor: &HashSet<Tile>) -> usize {
-    let mut count = 0;
-    for direction in &ALL_DIRECTIONS {
-        let adjacent_tile = tile.add_direction(&direction);
-        if let Some(_) = floor.get(&adjacent_tile) {
-            count += 1;
+impl Direction {
+    /// The step to the neighbouring tile in this direction, in the axial coordinates of the
+    /// [`HexLattice`].
+    pub fn offset(&self) -> (i64, i64) {
+        match self {
+            Direction::E => HEX_DIRECTIONS[0],
+            Direction::SE => HEX_DIRECTIONS[1],
+            Direction::SW => HEX_DIRECTIONS[2],
+            Direction::W => HEX_DIRECTIONS[3],
+            Direction::NW => HEX_DIRECTIONS[4],
+            Direction::NE => HEX_DIRECTIONS[5],
         }
     }
-    count
 }
 
-fn parse_input(input: &Vec<String>) -> Vec<Vec<Direction>> {
+const KEYWORDS: [(Direction, &str); 6] = [
+    (Direction::SE, "se"),
+    (Direction::SW, "sw"),
+    (Direction::NW, "nw"),
+    (Direction::NE, "ne"),
+    (Direction::E, "e"),
+    (Direction::W, "w"),
+];
+
+/// Parse lines of directions such as "esenee".
+pub fn parse_input(input: &[String]) -> Result<Vec<Vec<Direction>>, PuzzleError> {
     let mut tiles = vec![];
-    for line in input {
+    for (n, line) in input.iter().enumerate() {
         let mut directions: Vec<Direction> = vec![];
-        let mut i = 0;
-        while i < line.len() {
-            let keyword = &line[i..std::cmp::min(i + 2, line.len())];
-            let direction = if keyword.starts_with("e") {
-                i += 1;
-                Direction::E
-            } else if keyword.starts_with("se") {
-                i += 2;
-                Direction::SE
-            } else if keyword.starts_with("sw") {
-                i += 2;
-                Direction::SW
-            } else if keyword.starts_with("w") {
-                i += 1;
-                Direction::W
-            } else if keyword.starts_with("nw") {
-                i += 2;
-                Direction::NW
-            } else if keyword.starts_with("ne") {
-                i += 2;
-                Direction::NE
-            } else {
-                panic!("Illegal direction {}!", &keyword);
+        let mut rest = line.as_str();
+        while !rest.is_empty() {
+            // Two-letter directions go first, so that "se" is not read as "s" and "e".
+            let (direction, keyword) = match KEYWORDS
+                .iter()
+                .find(|(_, keyword)| rest.starts_with(keyword))
+            {
+                Some((direction, keyword)) => (direction.clone(), *keyword),
+                None => {
+                    let c = rest.chars().next().unwrap();
+                    return Err(PuzzleError::malformed(
+                        n,
+                        line,
+                        &rest[..c.len_utf8()],
+                        "illegal direction",
+                    ));
+                }
             };
+            rest = &rest[keyword.len()..];
             directions.push(direction);
         }
         tiles.push(directions);
     }
-    tiles
+    Ok(tiles)
 }
 
-fn get_dest_tile(directions: &Vec<Direction>) -> Tile {
-    let mut tile = Tile::new(0, 0, 0);
-    for direction in directions {
-        tile = tile.add_direction(direction);
+fn get_dest_tile(directions: &[Direction]) -> (i64, i64) {
+    directions.iter().fold((0, 0), |(q, r), direction| {
+        let (dq, dr) = direction.offset();
+        (q + dq, r + dr)
+    })
+}
+
+// Flip the tile at the end of each line of directions and return the tiles that end up black.
+fn flip_tiles(tiles: &[Vec<Direction>]) -> Cells<(i64, i64)> {
+    let mut floor = Cells::default();
+    for tile_directions in tiles {
+        let tile = get_dest_tile(tile_directions);
+        if !floor.remove(&tile) {
+            floor.insert(tile);
+        }
     }
-    tile
+    floor
 }
 
 impl Day24 {
-    fn solve_part1(&self, input: Vec<String>) -> usize {
-        let mut floor = HashSet::new();
+    pub fn solve_part1(&self, tiles: &[Vec<Direction>]) -> usize {
+        flip_tiles(tiles).len()
+    }
 
-        let tiles = parse_input(&input);
-        for tile_directions in &tiles {
-            let tile = get_dest_tile(&tile_directions);
-            if let Some(_) = floor.get(&tile) {
-                floor.remove(&tile);
-            } else {
-                floor.insert(tile);
-            }
-        }
-        floor.len()
+    /// A black tile with zero or more than 2 black neighbours is flipped to white, a white tile
+    /// with exactly 2 black neighbours is flipped to black.
+    pub fn solve_part2(&self, tiles: &[Vec<Direction>]) -> Result<usize, PuzzleError> {
+        let automaton = Automaton::new(HexLattice, Rule::new(&[2], &[1, 2]))
+            .cancellable(&self.context.cancel);
+        let days = self.context.params.integer(&DAYS) as usize;
+        Ok(automaton.run(flip_tiles(tiles), days)?.len())
     }
+}
 
-    fn solve_part2(&self, input: Vec<String>) -> usize {
-        let mut floor = HashSet::new();
+impl Puzzle for Day24 {
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.tiles = parse_input(&read_lines(input)?)?;
+        Ok(())
+    }
 
-        let tiles = parse_input(&input);
-        for tile_directions in &tiles {
-            let tile = get_dest_tile(&tile_directions);
-            if let Some(_) = floor.get(&tile) {
-                floor.remove(&tile);
-            } else {
-                floor.insert(tile);
-            }
-        }
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&self.tiles).to_string())
+    }
 
-        for _ in 0..100 {
-            let mut new_floor = floor.clone();
-            for black_tile in &floor {
-                let black_adjacent_tiles = get_black_adjacent_tiles(black_tile, &floor);
-                if black_adjacent_tiles == 0 || black_adjacent_tiles > 2 {
-                    new_floor.remove(black_tile);
-                }
-                for direction in &ALL_DIRECTIONS {
-                    let adjacent_tile = black_tile.add_direction(direction);
-                    match floor.get(&adjacent_tile) {
-                        Some(_) => (),
-                        None => {
-                            if get_black_adjacent_tiles(&adjacent_tile, &floor) == 2 {
-                                new_floor.insert(adjacent_tile);
-                            }
-                        }
-                    }
-                }
-            }
-            floor = new_floor;
-        }
-        floor.len()
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(&self.tiles)?.to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
     }
 }
 
-impl Puzzle for Day24 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(input.clone()).to_string(),
-            self.solve_part2(input.clone()).to_string(),
-        );
+// 'size' paths of 10 to 30 steps.
+impl Generator for Day24 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let lines = (0..size.max(1))
+            .map(|_| {
+                (0..rng.range(10..=30))
+                    .map(|_| *rng.choose(&["e", "se", "sw", "w", "nw", "ne"]))
+                    .collect()
+            })
+            .collect();
+        Generated::from_lines(lines)
     }
 }
 
@@ -188,28 +169,31 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day24 {}.solve_part1(string_vec!(
-                "sesenwnenenewseeswwswswwnenewsewsw",
-                "neeenesenwnwwswnenewnwwsewnenwseswesw",
-                "seswneswswsenwwnwse",
-                "nwnwneseeswswnenewneswwnewseswneseene",
-                "swweswneswnenwsewnwneneseenw",
-                "eesenwseswswnenwswnwnwsewwnwsene",
-                "sewnenenenesenwsewnenwwwse",
-                "wenwwweseeeweswwwnwwe",
-                "wsweesenenewnwwnwsenewsenwwsesesenwne",
-                "neeswseenwwswnwswswnw",
-                "nenwswwsewswnenenewsenwsenwnesesenew",
-                "enewnwewneswsewnwswenweswnenwsenwsw",
-                "sweneswneswneneenwnewenewwneswswnese",
-                "swwesenesewenwneswnwwneseswwne",
-                "enesenwswwswneneswsenwnewswseenwsese",
-                "wnwnesenesenenwwnenwsewesewsesesew",
-                "nenewswnwewswnenesenwnesewesw",
-                "eneswnwswnwsenenwnwnwwseeswneewsenese",
-                "neswnwewnwnwseenwseesewsenwsweewe",
-                "wseweeenwnesenwwwswnew"
-            )),
+            Day24::default().solve_part1(
+                &parse_input(&string_vec!(
+                    "sesenwnenenewseeswwswswwnenewsewsw",
+                    "neeenesenwnwwswnenewnwwsewnenwseswesw",
+                    "seswneswswsenwwnwse",
+                    "nwnwneseeswswnenewneswwnewseswneseene",
+                    "swweswneswnenwsewnwneneseenw",
+                    "eesenwseswswnenwswnwnwsewwnwsene",
+                    "sewnenenenesenwsewnenwwwse",
+                    "wenwwweseeeweswwwnwwe",
+                    "wsweesenenewnwwnwsenewsenwwsesesenwne",
+                    "neeswseenwwswnwswswnw",
+                    "nenwswwsewswnenenewsenwsenwnesesenew",
+                    "enewnwewneswsewnwswenweswnenwsenwsw",
+                    "sweneswneswneneenwnewenewwneswswnese",
+                    "swwesenesewenwneswnwwneseswwne",
+                    "enesenwswwswneneswsenwnewswseenwsese",
+                    "wnwnesenesenenwwnenwsewesewsesesew",
+                    "nenewswnwewswnenesenwnesewesw",
+                    "eneswnwswnwsenenwnwnwwseeswneewsenese",
+                    "neswnwewnwnwseenwseesewsenwsweewe",
+                    "wseweeenwnesenwwwswnew"
+                ))
+                .unwrap()
+            ),
             10
         );
     }
@@ -217,49 +201,79 @@ mod tests {
     #[test]
     fn part1_get_dest() {
         assert_eq!(
-            get_dest_tile(&parse_input(&string_vec!("esenee"))[0]),
-            Tile::new(3, 0, 0)
+            get_dest_tile(&parse_input(&string_vec!("esenee")).unwrap()[0]),
+            (3, 0)
         );
         assert_eq!(
-            get_dest_tile(&parse_input(&string_vec!("wswnww"))[0]),
-            Tile::new(-3, 0, 0)
+            get_dest_tile(&parse_input(&string_vec!("wswnww")).unwrap()[0]),
+            (-3, 0)
         );
         assert_eq!(
-            get_dest_tile(&parse_input(&string_vec!("nwwswee"))[0]),
-            Tile::new(0, 0, 0)
+            get_dest_tile(&parse_input(&string_vec!("nwwswee")).unwrap()[0]),
+            (0, 0)
         );
         assert_eq!(
-            get_dest_tile(&parse_input(&string_vec!("esew"))[0]),
-            Tile::new(0, 1, 0)
+            get_dest_tile(&parse_input(&string_vec!("esew")).unwrap()[0]),
+            (0, 1)
         );
     }
 
     #[test]
     fn part2_example1() {
         assert_eq!(
-            Day24 {}.solve_part2(string_vec!(
-                "sesenwnenenewseeswwswswwnenewsewsw",
-                "neeenesenwnwwswnenewnwwsewnenwseswesw",
-                "seswneswswsenwwnwse",
-                "nwnwneseeswswnenewneswwnewseswneseene",
-                "swweswneswnenwsewnwneneseenw",
-                "eesenwseswswnenwswnwnwsewwnwsene",
-                "sewnenenenesenwsewnenwwwse",
-                "wenwwweseeeweswwwnwwe",
-                "wsweesenenewnwwnwsenewsenwwsesesenwne",
-                "neeswseenwwswnwswswnw",
-                "nenwswwsewswnenenewsenwsenwnesesenew",
-                "enewnwewneswsewnwswenweswnenwsenwsw",
-                "sweneswneswneneenwnewenewwneswswnese",
-                "swwesenesewenwneswnwwneseswwne",
-                "enesenwswwswneneswsenwnewswseenwsese",
-                "wnwnesenesenenwwnenwsewesewsesesew",
-                "nenewswnwewswnenesenwnesewesw",
-                "eneswnwswnwsenenwnwnwwseeswneewsenese",
-                "neswnwewnwnwseenwseesewsenwsweewe",
-                "wseweeenwnesenwwwswnew"
-            )),
-            2208
+            Day24::default().solve_part2(
+                &parse_input(&string_vec!(
+                    "sesenwnenenewseeswwswswwnenewsewsw",
+                    "neeenesenwnwwswnenewnwwsewnenwseswesw",
+                    "seswneswswsenwwnwse",
+                    "nwnwneseeswswnenewneswwnewseswneseene",
+                    "swweswneswnenwsewnwneneseenw",
+                    "eesenwseswswnenwswnwnwsewwnwsene",
+                    "sewnenenenesenwsewnenwwwse",
+                    "wenwwweseeeweswwwnwwe",
+                    "wsweesenenewnwwnwsenewsenwwsesesenwne",
+                    "neeswseenwwswnwswswnw",
+                    "nenwswwsewswnenenewsenwsenwnesesenew",
+                    "enewnwewneswsewnwswenweswnenwsenwsw",
+                    "sweneswneswneneenwnewenewwneswswnese",
+                    "swwesenesewenwneswnwwneseswwne",
+                    "enesenwswwswneneswsenwnewswseenwsese",
+                    "wnwnesenesenenwwnenwsewesewsesesew",
+                    "nenewswnwewswnenesenwnesewesw",
+                    "eneswnwswnwsenenwnwnwwseeswneewsenese",
+                    "neswnwewnwnwseenwseesewsenwsweewe",
+                    "wseweeenwnesenwwwswnew"
+                ))
+                .unwrap()
+            ),
+            Ok(2208)
+        );
+    }
+
+    #[test]
+    fn malformed_directions() {
+        assert_eq!(
+            Day24::default().parse_str("esew\nsexnw\n").err(),
+            Some(PuzzleError::Parse {
+                line: 2,
+                column: 3,
+                text: "x".to_string(),
+                reason: "illegal direction".to_string(),
+            })
+        );
+        // A multibyte character is reported rather than cut in half.
+        assert_eq!(
+            Day24::default().parse_str("a\u{e9}\n").err(),
+            Some(PuzzleError::Parse {
+                line: 1,
+                column: 1,
+                text: "a".to_string(),
+                reason: "illegal direction".to_string(),
+            })
         );
+        assert!(matches!(
+            Day24::default().parse_str("e\u{e9}\n"),
+            Err(PuzzleError::Parse { column: 2, .. })
+        ));
     }
 }
@@ -1,45 +1,88 @@
-use crate::puzzle::{io, File, Puzzle};
+//! Day 25: Combo Breaker. Finds the encryption key from the two public keys. There is no second
+//! part, [`Day25::solve_part2`] always returns 0.
 
-pub struct Day25;
+use crate::generate::{Generated, Generator, Rng};
+use crate::math;
+use crate::puzzle::{parse_token, read_lines, BufRead, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day25 {
+    pub_keys: Vec<usize>,
+}
+
+register_puzzle! {
+    puzzle: Day25,
+    year: 2020,
+    day: 25,
+    title: "Combo Breaker",
+    input: Numbers,
+    reads_input: true,
+}
+
+/// The modulus of the handshake transformation.
+const MODULUS: u64 = 20201227;
+
+/// The subject number that the public keys are computed from.
+const SUBJECT: u64 = 7;
 
 impl Day25 {
-    fn solve_part1(&self, input: Vec<String>) -> usize {
-        let pub_keys: Vec<usize> = input.iter().map(|x| x.parse::<usize>().unwrap()).collect();
-        let mut subject = 7;
-        let mut i = 1;
-        let mut val = i;
-        loop {
-            val = val * subject;
-            val = val % 20201227;
-            if pub_keys[1] == val {
-                break;
+    /// Find the loop size of the door from its public key, as the discrete logarithm of the key,
+    /// and transform the card's public key with it.
+    pub fn solve_part1(&self, pub_keys: &[usize]) -> Result<usize, PuzzleError> {
+        let (card_key, door_key) = match pub_keys {
+            [card_key, door_key] => (*card_key as u64, *door_key as u64),
+            _ => {
+                return Err(PuzzleError::NoSolution(format!(
+                    "expected 2 public keys, found {}",
+                    pub_keys.len()
+                )))
             }
-            i += 1;
-        }
-        let loop_size = i;
-        subject = pub_keys[0];
-        i = 1;
-        val = i;
-        for _ in 0..loop_size {
-            val = val * subject;
-            val = val % 20201227;
-            i += 1;
-        }
-        val
+        };
+        let loop_size = math::discrete_log(SUBJECT, door_key, MODULUS)
+            .ok_or_else(|| PuzzleError::no_solution("no loop size gives the public key"))?;
+        Ok(math::mod_pow(card_key, loop_size, MODULUS) as usize)
     }
 
-    fn solve_part2(&self, _input: Vec<String>) -> usize {
+    pub fn solve_part2(&self, _pub_keys: &[usize]) -> usize {
         0
     }
 }
 
 impl Puzzle for Day25 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(input.clone()).to_string(),
-            self.solve_part2(input.clone()).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.pub_keys = read_lines(input)?
+            .iter()
+            .enumerate()
+            .map(|(i, l)| parse_token::<usize>(i, l, l))
+            .collect::<Result<Vec<usize>, PuzzleError>>()?;
+        if self.pub_keys.len() != 2 {
+            return Err(PuzzleError::NoSolution(format!(
+                "expected 2 public keys, found {}",
+                self.pub_keys.len()
+            )));
+        }
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&self.pub_keys)?.to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(&self.pub_keys).to_string())
+    }
+}
+
+// Two public keys from random loop sizes, 'size' is not used. The encryption key follows from the
+// loop sizes without a discrete logarithm.
+impl Generator for Day25 {
+    fn generate(&self, _size: usize, rng: &mut Rng) -> Generated {
+        let card_loop = rng.range(1..=MODULUS as i64 - 2) as u64;
+        let door_loop = rng.range(1..=MODULUS as i64 - 2) as u64;
+        let card_key = math::mod_pow(SUBJECT, card_loop, MODULUS);
+        let door_key = math::mod_pow(SUBJECT, door_loop, MODULUS);
+        Generated::from_lines(vec![card_key.to_string(), door_key.to_string()])
+            .with_answers(math::mod_pow(card_key, door_loop, MODULUS), 0)
     }
 }
 
@@ -47,15 +90,13 @@ impl Puzzle for Day25 {
 mod tests {
     use super::*;
 
-    macro_rules! string_vec {
-        ($($x:expr),*) => (vec![$($x.to_string()),*]);
-    }
-
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day25 {}.solve_part1(string_vec!("5764801", "17807724")),
-            14897079
+            Day25::default().solve_part1(&[5764801, 17807724]),
+            Ok(14897079)
         );
+        assert!(Day25::default().solve_part1(&[5764801, 20201227]).is_err());
+        assert!(Day25::default().solve_part1(&[5764801]).is_err());
     }
 }
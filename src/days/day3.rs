@@ -1,44 +1,86 @@
-use crate::puzzle::{io, File, Puzzle};
-pub struct Day3;
+//! Day 3: Toboggan Trajectory. The map is a grid of '.' and '#' that repeats to the right,
+//! [`get_trees`] counts the trees hit on a slope.
 
-fn get_trees(input: &Vec<String>, step_right: usize, step_down: usize) -> usize {
-    let mut trees = 0;
-    for (i, line) in input.iter().enumerate() {
-        if i != 0 && (i % step_down) == 0 {
-            if line
-                .chars()
-                .nth(((i / step_down) * step_right) % line.len())
-                .unwrap()
-                == '#'
-            {
-                trees += 1;
-            }
-        }
-    }
-    trees
+use crate::generate::{Generated, Generator, Rng};
+use crate::grid::Grid;
+use crate::puzzle::{read_lines, BufRead, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day3 {
+    map: Grid<char>,
+}
+
+register_puzzle! {
+    puzzle: Day3,
+    year: 2020,
+    day: 3,
+    title: "Toboggan Trajectory",
+    input: Grid,
+    reads_input: true,
+}
+
+/// Count the trees hit going from the top left corner to the bottom, moving 'step_right'
+/// and 'step_down' squares at a time.
+pub fn get_trees(map: &Grid<char>, step_right: usize, step_down: usize) -> usize {
+    (step_down..map.height())
+        .step_by(step_down)
+        .filter(|y| map.get_wrapping((y / step_down * step_right) as i64, *y as i64) == &'#')
+        .count()
+}
+
+/// Parse the map of open squares '.' and trees '#'.
+pub fn parse_input(input: &[String]) -> Result<Grid<char>, PuzzleError> {
+    Grid::from_lines(input, ".#")
 }
 
 impl Day3 {
-    fn solve_part1(&self, input: &Vec<String>) -> usize {
-        get_trees(input, 3, 1)
+    pub fn solve_part1(&self, map: &Grid<char>) -> usize {
+        get_trees(map, 3, 1)
     }
 
-    fn solve_part2(&self, input: &Vec<String>) -> usize {
-        get_trees(input, 1, 1)
-            * get_trees(input, 3, 1)
-            * get_trees(input, 5, 1)
-            * get_trees(input, 7, 1)
-            * get_trees(input, 1, 2)
+    pub fn solve_part2(&self, map: &Grid<char>) -> usize {
+        get_trees(map, 1, 1)
+            * get_trees(map, 3, 1)
+            * get_trees(map, 5, 1)
+            * get_trees(map, 7, 1)
+            * get_trees(map, 1, 2)
     }
 }
 
 impl Puzzle for Day3 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(&input).to_string(),
-            self.solve_part2(&input).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.map = parse_input(&read_lines(input)?)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&self.map).to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(&self.map).to_string())
+    }
+}
+
+// A map 31 squares wide and 'size' rows high with about one tree in four squares.
+impl Generator for Day3 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let rows: Vec<Vec<bool>> = (0..size.max(1))
+            .map(|_| (0..31).map(|_| rng.chance(0.25)).collect())
+            .collect();
+        let trees = |right: usize, down: usize| {
+            (1..)
+                .map(|step| (step * right % 31, step * down))
+                .take_while(|&(_, y)| y < rows.len())
+                .filter(|&(x, y)| rows[y][x])
+                .count()
+        };
+        let product = trees(1, 1) * trees(3, 1) * trees(5, 1) * trees(7, 1) * trees(1, 2);
+        let lines = rows
+            .iter()
+            .map(|row| row.iter().map(|&tree| if tree { '#' } else { '.' }).collect())
+            .collect();
+        Generated::from_lines(lines).with_answers(trees(3, 1), product)
     }
 }
 
@@ -49,8 +91,8 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day3 {}.solve_part1(
-                &vec!(
+            Day3::default().solve_part1(
+                &parse_input(&vec!(
                     "..##.......",
                     "#...#...#..",
                     ".#....#..#.",
@@ -65,7 +107,8 @@ mod tests {
                 )
                 .iter()
                 .map(|x| x.to_string())
-                .collect()
+                .collect::<Vec<String>>())
+                .unwrap()
             ),
             7
         );
@@ -74,8 +117,8 @@ mod tests {
     #[test]
     fn part2_example1() {
         assert_eq!(
-            Day3 {}.solve_part2(
-                &vec!(
+            Day3::default().solve_part2(
+                &parse_input(&vec!(
                     "..##.......",
                     "#...#...#..",
                     ".#....#..#.",
@@ -90,7 +133,8 @@ mod tests {
                 )
                 .iter()
                 .map(|x| x.to_string())
-                .collect()
+                .collect::<Vec<String>>())
+                .unwrap()
             ),
             336
         );
@@ -1,5 +1,24 @@
-use crate::puzzle::{io, File, Puzzle};
-pub struct Day4;
+//! Day 4: Passport Processing. [`parse_input`] collects the key/value pairs of each passport and
+//! [`nof_valid_passports`] counts the passports with all required fields, optionally checking the
+//! values as well.
+
+use crate::generate::{Generated, Generator, Rng};
+use crate::parse::{groups, records};
+use crate::puzzle::{read_lines, BufRead, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day4 {
+    passports: Vec<Passport>,
+}
+
+register_puzzle! {
+    puzzle: Day4,
+    year: 2020,
+    day: 4,
+    title: "Passport Processing",
+    input: Sections,
+    reads_input: true,
+}
 
 enum Field {
     BYR = 0,
@@ -27,7 +46,10 @@ fn is_hgt_valid(value: &str) -> bool {
         None => return false,
     };
     let (num, unit) = value.split_at(index);
-    let num = num.parse::<usize>().unwrap();
+    let num = match num.parse::<usize>() {
+        Ok(val) => val,
+        Err(_) => return false,
+    };
     if unit == "cm" && num >= 150 && num <= 193 {
         return true;
     }
@@ -41,7 +63,7 @@ fn is_hcl_valid(value: &str) -> bool {
     let valid_chars = [
         '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f',
     ];
-    if value.chars().nth(0).unwrap() != '#' {
+    if !value.starts_with('#') {
         return false;
     }
     if value.len() != 7 {
@@ -117,50 +139,132 @@ fn validate_entry(key: &str, value: &str, validate_value: bool) -> Option<Field>
     }
 }
 
-fn nof_valid_passports(input: &Vec<String>, validate_values: bool) -> usize {
+/// The key/value pairs of a passport, in input order.
+pub type Passport = Vec<(String, String)>;
+
+/// Parse the blank line separated passports, each made of 'key:value' pairs.
+pub fn parse_input(input: &[String]) -> Result<Vec<Passport>, PuzzleError> {
+    let mut passports = vec![];
+    for group in groups(input) {
+        let pairs = records(&group, ':')?;
+        passports.push(
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        );
+    }
+    Ok(passports)
+}
+
+/// Count the passports with all required fields. With 'validate_values' set each value
+/// must also be valid for its field.
+pub fn nof_valid_passports(passports: &[Passport], validate_values: bool) -> usize {
     let mut nof_ok_passports: usize = 0;
-    let mut fields: u8 = 0;
-    for line in input {
-        if line.is_empty() {
-            if fields == 0xff || fields == 0x7f {
-                nof_ok_passports += 1;
-            }
-            fields = 0;
-        } else {
-            let tokens = line.split(" ");
-            for token in tokens {
-                let mut key_value = token.split(":");
-                let key = key_value.next().unwrap();
-                let value = key_value.next().unwrap();
-                if let Some(key_id) = validate_entry(key, value, validate_values) {
-                    fields |= 1 << key_id as u8;
-                }
+    for passport in passports {
+        let mut fields: u8 = 0;
+        for (key, value) in passport {
+            if let Some(key_id) = validate_entry(key, value, validate_values) {
+                fields |= 1 << key_id as u8;
             }
         }
-    }
-    if fields == 0xff || fields == 0x7f {
-        nof_ok_passports += 1;
+        if fields == 0xff || fields == 0x7f {
+            nof_ok_passports += 1;
+        }
     }
     nof_ok_passports
 }
 
 impl Day4 {
-    fn solve_part1(&self, input: &Vec<String>) -> usize {
+    pub fn solve_part1(&self, input: &[Passport]) -> usize {
         nof_valid_passports(input, false)
     }
 
-    fn solve_part2(&self, input: &Vec<String>) -> usize {
+    pub fn solve_part2(&self, input: &[Passport]) -> usize {
         nof_valid_passports(input, true)
     }
 }
 
 impl Puzzle for Day4 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let input: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(&input).to_string(),
-            self.solve_part2(&input).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.passports = parse_input(&read_lines(input)?)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&self.passports).to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(&self.passports).to_string())
+    }
+}
+
+// 'size' passports, each either missing a required field, with all fields but one invalid value,
+// or valid. The fields are shuffled and spread over one or more lines.
+impl Generator for Day4 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        const KEYS: [&str; 8] = ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"];
+        const COLORS: [&str; 7] = ["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
+        let (mut complete, mut valid) = (0, 0);
+        let mut lines = vec![];
+        for n in 0..size {
+            let kind = rng.below(3);
+            let mut fields: Vec<(&str, String)> = vec![
+                ("byr", rng.range(1920..=2002).to_string()),
+                ("iyr", rng.range(2010..=2020).to_string()),
+                ("eyr", rng.range(2020..=2030).to_string()),
+                if rng.chance(0.5) {
+                    ("hgt", format!("{}cm", rng.range(150..=193)))
+                } else {
+                    ("hgt", format!("{}in", rng.range(59..=76)))
+                },
+                ("hcl", format!("#{:06x}", rng.below(1 << 24))),
+                ("ecl", rng.choose(&COLORS).to_string()),
+                ("pid", format!("{:09}", rng.below(1_000_000_000))),
+            ];
+            if rng.chance(0.5) {
+                fields.push(("cid", rng.range(100..=350).to_string()));
+            }
+            match kind {
+                0 => {
+                    fields.remove(rng.below(7));
+                }
+                1 => {
+                    let field = rng.below(7);
+                    fields[field].1 = match KEYS[field] {
+                        "byr" => rng.range(1900..=1919).to_string(),
+                        "iyr" => rng.range(2021..=2030).to_string(),
+                        "eyr" => rng.range(2010..=2019).to_string(),
+                        "hgt" => rng
+                            .choose(&["194cm", "149cm", "77in", "58in", "170"])
+                            .to_string(),
+                        "hcl" => rng.choose(&["#12345g", "123abc", "#1234567"]).to_string(),
+                        "ecl" => rng.choose(&["xry", "blue", "#ffffff"]).to_string(),
+                        _ => rng
+                            .choose(&["12345678", "0123456789", "12345678a"])
+                            .to_string(),
+                    };
+                    complete += 1;
+                }
+                _ => {
+                    complete += 1;
+                    valid += 1;
+                }
+            }
+            rng.shuffle(&mut fields);
+
+            if n > 0 {
+                lines.push(String::new());
+            }
+            let per_line = 1 + rng.below(fields.len());
+            for chunk in fields.chunks(per_line) {
+                let pairs: Vec<String> =
+                    chunk.iter().map(|(k, v)| format!("{}:{}", k, v)).collect();
+                lines.push(pairs.join(" "));
+            }
+        }
+        Generated::from_lines(lines).with_answers(complete, valid)
     }
 }
 
@@ -171,25 +275,28 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day4 {}.solve_part1(
-                &vec!(
-                    "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd",
-                    "byr:1937 iyr:2017 cid:147 hgt:183cm",
-                    "",
-                    "iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884",
-                    "hcl:#cfa07d byr:1929",
-                    "",
-                    "hcl:#ae17e1 iyr:2013",
-                    "eyr:2024",
-                    "ecl:brn pid:760753108 byr:1931",
-                    "hgt:179cm",
-                    "",
-                    "hcl:#cfa07d eyr:2025 pid:166559648",
-                    "iyr:2011 ecl:brn hgt:59in",
+            Day4::default().solve_part1(
+                &parse_input(
+                    &vec!(
+                        "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd",
+                        "byr:1937 iyr:2017 cid:147 hgt:183cm",
+                        "",
+                        "iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884",
+                        "hcl:#cfa07d byr:1929",
+                        "",
+                        "hcl:#ae17e1 iyr:2013",
+                        "eyr:2024",
+                        "ecl:brn pid:760753108 byr:1931",
+                        "hgt:179cm",
+                        "",
+                        "hcl:#cfa07d eyr:2025 pid:166559648",
+                        "iyr:2011 ecl:brn hgt:59in",
+                    )
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
                 )
-                .iter()
-                .map(|x| x.to_string())
-                .collect()
+                .unwrap()
             ),
             2
         );
@@ -226,25 +333,28 @@ mod tests {
     #[test]
     fn part2_invalid_examples() {
         assert_eq!(
-            Day4 {}.solve_part2(
-                &vec!(
-                    "eyr:1972 cid:100",
-                    "hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926",
-                    "",
-                    "iyr:2019",
-                    "hcl:#602927 eyr:1967 hgt:170cm",
-                    "ecl:grn pid:012533040 byr:1946",
-                    "",
-                    "hcl:dab227 iyr:2012",
-                    "ecl:brn hgt:182cm pid:021572410 eyr:2020 byr:1992 cid:277",
-                    "",
-                    "hgt:59cm ecl:zzz",
-                    "eyr:2038 hcl:74454a iyr:2023",
-                    "pid:3556412378 byr:2007"
+            Day4::default().solve_part2(
+                &parse_input(
+                    &vec!(
+                        "eyr:1972 cid:100",
+                        "hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926",
+                        "",
+                        "iyr:2019",
+                        "hcl:#602927 eyr:1967 hgt:170cm",
+                        "ecl:grn pid:012533040 byr:1946",
+                        "",
+                        "hcl:dab227 iyr:2012",
+                        "ecl:brn hgt:182cm pid:021572410 eyr:2020 byr:1992 cid:277",
+                        "",
+                        "hgt:59cm ecl:zzz",
+                        "eyr:2038 hcl:74454a iyr:2023",
+                        "pid:3556412378 byr:2007"
+                    )
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
                 )
-                .iter()
-                .map(|x| x.to_string())
-                .collect()
+                .unwrap()
             ),
             0
         );
@@ -253,24 +363,27 @@ mod tests {
     #[test]
     fn part2_valid_examples() {
         assert_eq!(
-            Day4 {}.solve_part2(
-                &vec!(
-                    "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980",
-                    "hcl:#623a2f",
-                    "",
-                    "eyr:2029 ecl:blu cid:129 byr:1989",
-                    "iyr:2014 pid:896056539 hcl:#a97842 hgt:165cm",
-                    "",
-                    "hcl:#888785",
-                    "hgt:164cm byr:2001 iyr:2015 cid:88",
-                    "pid:545766238 ecl:hzl",
-                    "eyr:2022",
-                    "",
-                    "iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719"
+            Day4::default().solve_part2(
+                &parse_input(
+                    &vec!(
+                        "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980",
+                        "hcl:#623a2f",
+                        "",
+                        "eyr:2029 ecl:blu cid:129 byr:1989",
+                        "iyr:2014 pid:896056539 hcl:#a97842 hgt:165cm",
+                        "",
+                        "hcl:#888785",
+                        "hgt:164cm byr:2001 iyr:2015 cid:88",
+                        "pid:545766238 ecl:hzl",
+                        "eyr:2022",
+                        "",
+                        "iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719"
+                    )
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
                 )
-                .iter()
-                .map(|x| x.to_string())
-                .collect()
+                .unwrap()
             ),
             4
         );
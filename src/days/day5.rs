@@ -1,7 +1,55 @@
-use crate::puzzle::{io, File, Puzzle};
-pub struct Day5;
+//! Day 5: Binary Boarding. Boarding passes are validated by [`parse_input`] and decoded into
+//! seat ids by [`get_seats`].
 
-fn get_seats(input: &Vec<String>) -> Vec<u16> {
+use crate::generate::{Generated, Generator, Rng};
+use crate::puzzle::{read_lines, BufRead, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day5 {
+    passes: Vec<String>,
+}
+
+register_puzzle! {
+    puzzle: Day5,
+    year: 2020,
+    day: 5,
+    title: "Binary Boarding",
+    input: Lines,
+    reads_input: true,
+}
+
+/// Check that every boarding pass is 7 'F'/'B' followed by 3 'L'/'R' characters.
+pub fn parse_input(input: Vec<String>) -> Result<Vec<String>, PuzzleError> {
+    for (i, line) in input.iter().enumerate() {
+        if line.len() != 10 {
+            return Err(PuzzleError::malformed(
+                i,
+                line,
+                line,
+                "expected 10 characters",
+            ));
+        }
+        for (pos, c) in line.char_indices() {
+            let valid = if pos < 7 {
+                c == 'F' || c == 'B'
+            } else {
+                c == 'L' || c == 'R'
+            };
+            if !valid {
+                return Err(PuzzleError::malformed(
+                    i,
+                    line,
+                    &line[pos..],
+                    "unexpected character",
+                ));
+            }
+        }
+    }
+    Ok(input)
+}
+
+/// Decode the boarding passes into their seat ids.
+pub fn get_seats(input: &[String]) -> Vec<u16> {
     let mut seats: Vec<u16> = vec![];
     for line in input {
         let mut id: u16 = 0;
@@ -25,11 +73,15 @@ fn get_seats(input: &Vec<String>) -> Vec<u16> {
 }
 
 impl Day5 {
-    fn solve_part1(&self, input: &Vec<String>) -> u16 {
-        *get_seats(input).iter().max().unwrap()
+    pub fn solve_part1(&self, input: &[String]) -> Result<u16, PuzzleError> {
+        get_seats(input)
+            .iter()
+            .max()
+            .copied()
+            .ok_or_else(|| PuzzleError::no_solution("no boarding passes"))
     }
 
-    fn solve_part2(&self, input: &Vec<String>) -> u16 {
+    pub fn solve_part2(&self, input: &[String]) -> u16 {
         let seats = get_seats(input);
         let mut prev: u16 = 0;
         for (i, s) in seats.iter().enumerate() {
@@ -45,12 +97,45 @@ impl Day5 {
 }
 
 impl Puzzle for Day5 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let numbers: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(&numbers).to_string(),
-            self.solve_part2(&numbers).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.passes = parse_input(read_lines(input)?)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&self.passes)?.to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(&self.passes).to_string())
+    }
+}
+
+// A full row of 'size' consecutive seats, at most 1000, with one seat in the middle missing.
+impl Generator for Day5 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let count = size.clamp(3, 1000) as i64;
+        let first = rng.range(1..=1023 - count);
+        let last = first + count;
+        let missing = rng.range(first + 1..=last - 1);
+        let mut passes: Vec<String> = (first..=last)
+            .filter(|&id| id != missing)
+            .map(|id| {
+                (0..10)
+                    .map(|bit| {
+                        let set = id >> (9 - bit) & 1 == 1;
+                        match (bit < 7, set) {
+                            (true, false) => 'F',
+                            (true, true) => 'B',
+                            (false, false) => 'L',
+                            (false, true) => 'R',
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        rng.shuffle(&mut passes);
+        Generated::from_lines(passes).with_answers(last, missing)
     }
 }
 
@@ -61,21 +146,26 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day5 {}.solve_part1(&vec!("FBFBBFFRLR").iter().map(|x| x.to_string()).collect()),
-            357
+            Day5::default().solve_part1(
+                &vec!("FBFBBFFRLR")
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
+            ),
+            Ok(357)
         );
     }
 
     #[test]
     fn part1_example2() {
         assert_eq!(
-            Day5 {}.solve_part1(
+            Day5::default().solve_part1(
                 &vec!("BFFFBBFRRR", "FFFBBBFRRR", "BBFFBBFRLL")
                     .iter()
                     .map(|x| x.to_string())
-                    .collect()
+                    .collect::<Vec<String>>()
             ),
-            820
+            Ok(820)
         );
     }
 }
@@ -1,33 +1,60 @@
+//! Day 6: Custom Customs. [`get_group_answers`] counts how many people in each group answered
+//! yes to each question.
+
 use std::vec;
 
-use crate::puzzle::{io, File, Puzzle};
-pub struct Day6;
+use crate::generate::{Generated, Generator, Rng};
+use crate::parse;
+use crate::puzzle::{read_lines, BufRead, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day6 {
+    answers: Vec<String>,
+}
+
+register_puzzle! {
+    puzzle: Day6,
+    year: 2020,
+    day: 6,
+    title: "Custom Customs",
+    input: Sections,
+    reads_input: true,
+}
 
-fn get_group_answers(input: &Vec<String>) -> Vec<(usize, [usize; 26])> {
+/// Check that all answers are lowercase letters.
+pub fn parse_input(input: Vec<String>) -> Result<Vec<String>, PuzzleError> {
+    for (i, line) in input.iter().enumerate() {
+        if let Some(pos) = line.find(|c: char| !c.is_ascii_lowercase()) {
+            return Err(PuzzleError::malformed(
+                i,
+                line,
+                &line[pos..],
+                "expected a-z",
+            ));
+        }
+    }
+    Ok(input)
+}
+
+/// Get the group size and the number of yes answers per question ('a' to 'z') for each
+/// blank line separated group.
+pub fn get_group_answers(input: &[String]) -> Vec<(usize, [usize; 26])> {
     let mut groups = vec![];
-    let mut answers: [usize; 26] = [0; 26];
-    let mut group_size = 0;
-    for line in input {
-        if line.is_empty() {
-            groups.push((group_size, answers));
-            group_size = 0;
-            answers = [0; 26];
-        } else {
-            group_size += 1;
+    for group in parse::groups(input) {
+        let mut answers: [usize; 26] = [0; 26];
+        for line in group.lines {
             for c in line.as_bytes() {
                 let pos: usize = (c - ('a' as u8)) as usize;
                 answers[pos] += 1;
             }
         }
-    }
-    if group_size > 0 {
-        groups.push((group_size, answers));
+        groups.push((group.lines.len(), answers));
     }
     groups
 }
 
 impl Day6 {
-    fn solve_part1(&self, input: &Vec<String>) -> usize {
+    pub fn solve_part1(&self, input: &[String]) -> usize {
         get_group_answers(input)[..]
             .iter()
             .fold(0, |sum, (_, answers)| {
@@ -37,7 +64,7 @@ impl Day6 {
             })
     }
 
-    fn solve_part2(&self, input: &Vec<String>) -> usize {
+    pub fn solve_part2(&self, input: &[String]) -> usize {
         get_group_answers(input)[..]
             .iter()
             .fold(0, |sum, (size, answers)| {
@@ -49,12 +76,54 @@ impl Day6 {
 }
 
 impl Puzzle for Day6 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let numbers: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(&numbers).to_string(),
-            self.solve_part2(&numbers).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.answers = parse_input(read_lines(input)?)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&self.answers).to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(&self.answers).to_string())
+    }
+}
+
+// 'size' groups of one to five people. Everybody in a group shares a few answers on top of their
+// own random ones.
+impl Generator for Day6 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let (mut anyone, mut everyone) = (0, 0);
+        let mut lines = vec![];
+        for n in 0..size {
+            let shared: Vec<bool> = (0..26).map(|_| rng.chance(0.15)).collect();
+            let people: Vec<Vec<bool>> = (0..rng.range(1..=5))
+                .map(|_| {
+                    let mut person: Vec<bool> =
+                        shared.iter().map(|&yes| yes || rng.chance(0.2)).collect();
+                    // An empty line would end the group.
+                    if !person.contains(&true) {
+                        person[rng.below(26)] = true;
+                    }
+                    person
+                })
+                .collect();
+            anyone += (0..26).filter(|&q| people.iter().any(|p| p[q])).count();
+            everyone += (0..26).filter(|&q| people.iter().all(|p| p[q])).count();
+            if n > 0 {
+                lines.push(String::new());
+            }
+            for person in &people {
+                let mut answers: Vec<char> = (0..26)
+                    .filter(|&q| person[q])
+                    .map(|q| (b'a' + q as u8) as char)
+                    .collect();
+                rng.shuffle(&mut answers);
+                lines.push(answers.into_iter().collect());
+            }
+        }
+        Generated::from_lines(lines).with_answers(anyone, everyone)
     }
 }
 
@@ -65,11 +134,11 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day6 {}.solve_part1(
+            Day6::default().solve_part1(
                 &vec!("abc", "", "a", "b", "c", "", "ab", "ac", "", "a", "a", "a", "a", "", "b")
                     .iter()
                     .map(|x| x.to_string())
-                    .collect()
+                    .collect::<Vec<String>>()
             ),
             11
         );
@@ -78,11 +147,11 @@ mod tests {
     #[test]
     fn part2_example1() {
         assert_eq!(
-            Day6 {}.solve_part2(
+            Day6::default().solve_part2(
                 &vec!("abc", "", "a", "b", "c", "", "ab", "ac", "", "a", "a", "a", "a", "", "b")
                     .iter()
                     .map(|x| x.to_string())
-                    .collect()
+                    .collect::<Vec<String>>()
             ),
             6
         );
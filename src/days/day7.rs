@@ -1,13 +1,37 @@
+//! Day 7: Handy Haversacks. The rules are parsed into a map of [`Bag`]s by color with
+//! [`parse_rules`], and [`bag_graph`] turns them into a graph of which bags contain which.
+
 use std::collections::HashMap;
 use std::vec;
 
-use crate::puzzle::{io, File, Puzzle};
-pub struct Day7;
+use crate::generate::{Generated, Generator, Rng};
+use crate::graph::Digraph;
+use crate::params::Param;
+use crate::puzzle::{next_token, parse_token, read_lines, BufRead, Context, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day7 {
+    bags: Digraph<String, usize>,
+    context: Context,
+}
+
+const BAG: Param = Param::text("bag", "shiny gold", "The color of our bag");
 
+register_puzzle! {
+    puzzle: Day7,
+    year: 2020,
+    day: 7,
+    title: "Handy Haversacks",
+    input: Lines,
+    reads_input: true,
+    params: &[BAG],
+}
+
+/// A bag color and the number of bags of each color it must contain.
 #[derive(Debug)]
-struct Bag {
-    color: String,
-    contents: Vec<(usize, String)>,
+pub struct Bag {
+    pub color: String,
+    pub contents: Vec<(usize, String)>,
 }
 
 impl Bag {
@@ -23,85 +47,223 @@ impl Bag {
     }
 }
 
-fn contains(bag: &Bag, rules: &HashMap<&str, Bag>, find_color: &str) -> bool {
-    if bag.color == find_color {
-        return true;
-    }
-    for (_, color) in &bag.contents {
-        if let Some(small_bag) = rules.get(&color[..]) {
-            if contains(small_bag, rules, find_color) {
-                return true;
-            }
-        }
-    }
-    false
-}
-
-fn count_bags(bag: &Bag, rules: &HashMap<&str, Bag>) -> usize {
-    if bag.contents.is_empty() {
-        return 1;
-    }
-    let mut sum: usize = 1;
-    for (num, color) in &bag.contents {
-        let small_bag = rules.get(&color[..]).unwrap();
-        sum += num * count_bags(small_bag, rules);
-    }
-    sum
-}
-
-fn parse_rules(input: &Vec<String>) -> HashMap<&str, Bag> {
+/// Parse rules such as "light red bags contain 1 bright white bag, 2 muted yellow bags."
+/// into bags by color.
+pub fn parse_rules(input: &[String]) -> Result<HashMap<String, Bag>, PuzzleError> {
     let mut rules = HashMap::new();
-    for line in input {
+    for (i, line) in input.iter().enumerate() {
         let mut split1 = line[..].split(" bags contain ");
-        let color = split1.next().unwrap().trim_end();
-        let mut bag = Bag::new(&color);
-        let rest = split1.next().unwrap();
+        let color = next_token(&mut split1, i, line)?.trim_end();
+        let mut bag = Bag::new(color);
+        let rest = next_token(&mut split1, i, line)?;
         if rest != "no other bags." {
             let contents = rest.split(", ");
             for part in contents {
-                let part_color = part
+                let mut words = part.splitn(2, ' ');
+                let num: usize = parse_token(i, line, next_token(&mut words, i, line)?)?;
+                let part_color = next_token(&mut words, i, line)?
                     .split(" bag")
                     .next()
-                    .unwrap()
-                    .split(char::is_numeric)
-                    .next_back()
-                    .unwrap()
+                    .unwrap_or_default()
                     .trim();
-                let num: usize = part.split(' ').next().unwrap().parse::<usize>().unwrap();
                 bag.add_bag(num, part_color);
             }
         }
-        rules.insert(color, bag);
+        rules.insert(color.to_string(), bag);
     }
-    rules
+    Ok(rules)
 }
 
-impl Day7 {
-    fn solve_part1(&self, input: &Vec<String>, find_color: &str) -> usize {
-        let rules = parse_rules(input);
-        let mut count: usize = 0;
-        for (_, bag) in &rules {
-            if bag.color != find_color && contains(bag, &rules, find_color) {
-                count += 1;
+/// Build the graph of the rules, with an edge from every bag to each bag it must contain,
+/// weighted by their number.
+pub fn bag_graph(rules: &HashMap<String, Bag>) -> Result<Digraph<String, usize>, PuzzleError> {
+    let mut graph = Digraph::new();
+    // Sorted so that the node indices, and any cycle found, do not depend on the hash order.
+    let mut bags: Vec<&Bag> = rules.values().collect();
+    bags.sort_by(|a, b| a.color.cmp(&b.color));
+    for bag in bags {
+        graph.add_node(bag.color.clone());
+        for (num, color) in &bag.contents {
+            if !rules.contains_key(color) {
+                return Err(PuzzleError::NoSolution(format!(
+                    "no rule for {} bags",
+                    color
+                )));
             }
+            graph.add_edge(bag.color.clone(), color.clone(), *num);
+        }
+    }
+    Ok(graph)
+}
+
+fn cycle_error(graph: &Digraph<String, usize>, cycle: Vec<usize>) -> PuzzleError {
+    let colors: Vec<&str> = cycle.iter().map(|&i| &graph.node(i)[..]).collect();
+    PuzzleError::NoSolution(format!("bags contain themselves: {}", colors.join(" -> ")))
+}
+
+impl Day7 {
+    pub fn solve_part1(
+        &self,
+        bags: &Digraph<String, usize>,
+        find_color: &str,
+    ) -> Result<usize, PuzzleError> {
+        let bag = bags
+            .index(&find_color.to_string())
+            .ok_or_else(|| PuzzleError::NoSolution(format!("no rule for {} bags", find_color)))?;
+        // Only bags inside the bag of the color can make it contain itself, cycles elsewhere do not
+        // matter.
+        if let Some(cycle) = bags.find_cycle_from(bag) {
+            return Err(cycle_error(bags, cycle));
         }
-        count
+        Ok(bags.ancestors(bag).len())
     }
 
-    fn solve_part2(&self, input: &Vec<String>, find_color: &str) -> usize {
-        let rules = parse_rules(input);
-        let bag = rules.get(find_color).unwrap();
-        count_bags(bag, &rules) - 1
+    pub fn solve_part2(
+        &self,
+        bags: &Digraph<String, usize>,
+        find_color: &str,
+    ) -> Result<usize, PuzzleError> {
+        let bag = bags
+            .index(&find_color.to_string())
+            .ok_or_else(|| PuzzleError::NoSolution(format!("no rule for {} bags", find_color)))?;
+        // The number of bags inside each bag, plus the bag itself.
+        let counts = bags
+            .fold(|_, contents| {
+                1 + contents
+                    .iter()
+                    .map(|(num, count)| *num * *count)
+                    .sum::<usize>()
+            })
+            .map_err(|cycle| cycle_error(bags, cycle))?;
+        Ok(counts[bag] - 1)
     }
 }
 
 impl Puzzle for Day7 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let rules: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(&rules, "shiny gold").to_string(),
-            self.solve_part2(&rules, "shiny gold").to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.bags = bag_graph(&parse_rules(&read_lines(input)?)?)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self
+            .solve_part1(&self.bags, self.context.params.text(&BAG))?
+            .to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self
+            .solve_part2(&self.bags, self.context.params.text(&BAG))?
+            .to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+// 'size' rules, at most 540. Bags only contain bags further down a random order of the colors, so
+// the rules have no cycles, and the shiny gold bag is close to the end of that order to keep the
+// number of bags inside it small.
+impl Generator for Day7 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        const ADJECTIVES: [&str; 18] = [
+            "bright", "clear", "dark", "dim", "dotted", "drab", "dull", "faded", "light",
+            "mirrored", "muted", "pale", "plaid", "posh", "shiny", "striped", "vibrant", "wavy",
+        ];
+        const COLORS: [&str; 30] = [
+            "red",
+            "orange",
+            "white",
+            "yellow",
+            "gold",
+            "olive",
+            "plum",
+            "blue",
+            "black",
+            "green",
+            "violet",
+            "indigo",
+            "cyan",
+            "magenta",
+            "teal",
+            "tan",
+            "beige",
+            "coral",
+            "crimson",
+            "lime",
+            "maroon",
+            "salmon",
+            "silver",
+            "tomato",
+            "turquoise",
+            "aqua",
+            "brown",
+            "fuchsia",
+            "gray",
+            "lavender",
+        ];
+        let mut names: Vec<String> = ADJECTIVES
+            .iter()
+            .flat_map(|adjective| {
+                COLORS
+                    .iter()
+                    .map(move |color| format!("{} {}", adjective, color))
+            })
+            .filter(|name| name != "shiny gold")
+            .collect();
+        rng.shuffle(&mut names);
+        let count = size.clamp(2, 540);
+        names.truncate(count - 1);
+        let gold = count - 1 - (count / 2).min(12);
+        names.insert(gold, "shiny gold".to_string());
+
+        let mut contents: Vec<Vec<(usize, usize)>> = vec![vec![]; count];
+        for (bag, bag_contents) in contents.iter_mut().enumerate() {
+            if bag + 1 < count && rng.chance(0.8) {
+                let mut inner: Vec<usize> = (bag + 1..count).collect();
+                rng.shuffle(&mut inner);
+                inner.truncate(rng.range(1..=3) as usize);
+                *bag_contents = inner
+                    .into_iter()
+                    .map(|inner| (rng.range(1..=3) as usize, inner))
+                    .collect();
+            }
+        }
+
+        // Both answers follow the order of the bags backwards.
+        let mut contains_gold = vec![false; count];
+        let mut inside = vec![0; count];
+        for bag in (0..count).rev() {
+            contains_gold[bag] = contents[bag]
+                .iter()
+                .any(|&(_, inner)| inner == gold || contains_gold[inner]);
+            inside[bag] = contents[bag]
+                .iter()
+                .map(|&(num, inner)| num * (1 + inside[inner]))
+                .sum();
+        }
+
+        let mut lines: Vec<String> = (0..count)
+            .map(|bag| {
+                let rule = if contents[bag].is_empty() {
+                    "no other bags".to_string()
+                } else {
+                    let inner: Vec<String> = contents[bag]
+                        .iter()
+                        .map(|&(num, inner)| {
+                            let plural = if num == 1 { "" } else { "s" };
+                            format!("{} {} bag{}", num, names[inner], plural)
+                        })
+                        .collect();
+                    inner.join(", ")
+                };
+                format!("{} bags contain {}.", names[bag], rule)
+            })
+            .collect();
+        rng.shuffle(&mut lines);
+        let part1 = contains_gold.iter().filter(|&&contains| contains).count();
+        Generated::from_lines(lines).with_answers(part1, inside[gold])
     }
 }
 
@@ -112,47 +274,61 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day7 {}.solve_part1(
-                &vec!(
-                    "light red bags contain 1 bright white bag, 2 muted yellow bags.",
-                    "dark orange bags contain 3 bright white bags, 4 muted yellow bags.",
-                    "bright white bags contain 1 shiny gold bag.",
-                    "muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.",
-                    "shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.",
-                    "dark olive bags contain 3 faded blue bags, 4 dotted black bags.",
-                    "vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.",
-                    "faded blue bags contain no other bags.",
-                    "dotted black bags contain no other bags."
+            Day7::default().solve_part1(
+                &bag_graph(
+                    &parse_rules(
+                        &vec!(
+                            "light red bags contain 1 bright white bag, 2 muted yellow bags.",
+                            "dark orange bags contain 3 bright white bags, 4 muted yellow bags.",
+                            "bright white bags contain 1 shiny gold bag.",
+                            "muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.",
+                            "shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.",
+                            "dark olive bags contain 3 faded blue bags, 4 dotted black bags.",
+                            "vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.",
+                            "faded blue bags contain no other bags.",
+                            "dotted black bags contain no other bags."
+                        )
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<String>>()
+                    )
+                    .unwrap()
                 )
-                .iter()
-                .map(|x| x.to_string())
-                .collect(),
+                .unwrap(),
                 "shiny gold"
             ),
-            4
+            Ok(4)
         );
     }
 
     #[test]
     fn part2_example1() {
         assert_eq!(
-            Day7 {}.solve_part2(
-                &vec!(
-                    "light red bags contain 1 bright white bag, 2 muted yellow bags.",
-                    "dark orange bags contain 3 bright white bags, 4 muted yellow bags.",
-                    "bright white bags contain 1 shiny gold bag.",
-                    "muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.",
-                    "shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.",
-                    "dark olive bags contain 3 faded blue bags, 4 dotted black bags.",
-                    "vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.",
-                    "faded blue bags contain no other bags.",
-                    "dotted black bags contain no other bags."
+            Day7::default()
+                .solve_part2(
+                    &bag_graph(
+                        &parse_rules(
+                            &vec!(
+                            "light red bags contain 1 bright white bag, 2 muted yellow bags.",
+                            "dark orange bags contain 3 bright white bags, 4 muted yellow bags.",
+                            "bright white bags contain 1 shiny gold bag.",
+                            "muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.",
+                            "shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.",
+                            "dark olive bags contain 3 faded blue bags, 4 dotted black bags.",
+                            "vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.",
+                            "faded blue bags contain no other bags.",
+                            "dotted black bags contain no other bags."
+                        )
+                            .iter()
+                            .map(|x| x.to_string())
+                            .collect::<Vec<String>>()
+                        )
+                        .unwrap()
+                    )
+                    .unwrap(),
+                    "shiny gold"
                 )
-                .iter()
-                .map(|x| x.to_string())
-                .collect(),
-                "shiny gold"
-            ),
+                .unwrap(),
             32
         );
     }
@@ -160,22 +336,75 @@ mod tests {
     #[test]
     fn part2_example2() {
         assert_eq!(
-            Day7 {}.solve_part2(
-                &vec!(
-                    "shiny gold bags contain 2 dark red bags.",
-                    "dark red bags contain 2 dark orange bags.",
-                    "dark orange bags contain 2 dark yellow bags.",
-                    "dark yellow bags contain 2 dark green bags.",
-                    "dark green bags contain 2 dark blue bags.",
-                    "dark blue bags contain 2 dark violet bags.",
-                    "dark violet bags contain no other bags."
+            Day7::default()
+                .solve_part2(
+                    &bag_graph(
+                        &parse_rules(
+                            &vec!(
+                                "shiny gold bags contain 2 dark red bags.",
+                                "dark red bags contain 2 dark orange bags.",
+                                "dark orange bags contain 2 dark yellow bags.",
+                                "dark yellow bags contain 2 dark green bags.",
+                                "dark green bags contain 2 dark blue bags.",
+                                "dark blue bags contain 2 dark violet bags.",
+                                "dark violet bags contain no other bags."
+                            )
+                            .iter()
+                            .map(|x| x.to_string())
+                            .collect::<Vec<String>>()
+                        )
+                        .unwrap()
+                    )
+                    .unwrap(),
+                    "shiny gold"
                 )
-                .iter()
-                .map(|x| x.to_string())
-                .collect(),
-                "shiny gold"
-            ),
+                .unwrap(),
             126
         );
     }
+
+    #[test]
+    fn bags_containing_themselves() {
+        let rules = parse_rules(&[
+            "shiny gold bags contain 2 dark red bags.".to_string(),
+            "dark red bags contain 1 shiny gold bag.".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(
+            Day7::default().solve_part2(&bag_graph(&rules).unwrap(), "shiny gold"),
+            Err(PuzzleError::NoSolution(
+                "bags contain themselves: dark red -> shiny gold -> dark red".to_string()
+            ))
+        );
+        assert!(Day7::default()
+            .solve_part1(&bag_graph(&rules).unwrap(), "shiny gold")
+            .is_err());
+    }
+
+    #[test]
+    fn cycles_outside_the_bag() {
+        // The bags around shiny gold ones contain each other, which does not change which bags
+        // can hold a shiny gold one.
+        let rules = parse_rules(&[
+            "dark red bags contain 1 bright white bag, 1 shiny gold bag.".to_string(),
+            "bright white bags contain 1 dark red bag.".to_string(),
+            "shiny gold bags contain no other bags.".to_string(),
+        ])
+        .unwrap();
+        let bags = bag_graph(&rules).unwrap();
+        assert_eq!(Day7::default().solve_part1(&bags, "shiny gold"), Ok(2));
+    }
+
+    #[test]
+    fn unknown_bag() {
+        let rules = parse_rules(&["shiny gold bags contain no other bags.".to_string()]).unwrap();
+        let bags = bag_graph(&rules).unwrap();
+        let error = || {
+            Err(PuzzleError::NoSolution(
+                "no rule for dull blue bags".to_string(),
+            ))
+        };
+        assert_eq!(Day7::default().solve_part1(&bags, "dull blue"), error());
+        assert_eq!(Day7::default().solve_part2(&bags, "dull blue"), error());
+    }
 }
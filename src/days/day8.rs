@@ -1,21 +1,45 @@
+//! Day 8: Handheld Halting. [`get_program`] parses the boot code into instructions that a
+//! [`Computer`] runs until it terminates, loops or jumps out of bounds.
+
 use std::vec;
 
-use crate::puzzle::{io, File, Puzzle};
-pub struct Day8;
+use crate::generate::{Generated, Generator, Rng};
+use crate::puzzle::{next_token, parse_token, read_lines, BufRead, Context, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day8 {
+    program: Vec<(usize, Op)>,
+    context: Context,
+}
+
+register_puzzle! {
+    puzzle: Day8,
+    year: 2020,
+    day: 8,
+    title: "Handheld Halting",
+    input: Lines,
+    reads_input: true,
+}
 
-enum ExitStatus {
+/// How a program run ended, with the accumulator or the address of the bad jump.
+#[derive(Debug, PartialEq)]
+pub enum ExitStatus {
     Success(i64),
     Stuck(i64),
+    IllegalJump(usize),
 }
 
+/// An instruction and its argument.
 #[derive(Debug, Clone)]
-enum Op {
+pub enum Op {
     Acc(i64),
     Jmp(i64),
     Nop(i64),
 }
 
-struct Computer {
+/// Runs a program where each instruction is paired with the number of times it has been
+/// executed.
+pub struct Computer {
     mem: Vec<(usize, Op)>,
     pc: usize,
     acc: i64,
@@ -48,8 +72,8 @@ impl Computer {
             }
             Op::Jmp(offset) => {
                 let new_pc = self.pc as i64 + *offset;
-                if new_pc < 0 {
-                    panic!("Illegal jump offset");
+                if new_pc < 0 || new_pc as usize > self.mem.len() {
+                    return Some(ExitStatus::IllegalJump(self.pc));
                 }
                 self.pc = new_pc as usize;
             }
@@ -71,94 +95,193 @@ impl Computer {
     }
 }
 
-fn get_program(input: &Vec<String>) -> Vec<(usize, Op)> {
+/// Parse instructions such as "acc +1" into a program that has not been run yet.
+pub fn get_program(input: &[String]) -> Result<Vec<(usize, Op)>, PuzzleError> {
     let mut program = vec![];
-    for line in input {
-        let mut words = line.split(" ");
-        let opstring = words.next().unwrap().trim();
-        let argument = words.next().unwrap().trim();
+    for (i, line) in input.iter().enumerate() {
+        let mut words = line.split(' ');
+        let opstring = next_token(&mut words, i, line)?;
+        let argument = next_token(&mut words, i, line)?;
         let op = match opstring {
-            "acc" => Op::Acc(argument.parse().unwrap()),
-            "jmp" => Op::Jmp(argument.parse().unwrap()),
-            "nop" => Op::Nop(argument.parse().unwrap()),
-            _ => panic!("Illegal instruction: {}", opstring),
+            "acc" => Op::Acc(parse_token(i, line, argument)?),
+            "jmp" => Op::Jmp(parse_token(i, line, argument)?),
+            "nop" => Op::Nop(parse_token(i, line, argument)?),
+            _ => {
+                return Err(PuzzleError::malformed(
+                    i,
+                    line,
+                    opstring,
+                    "illegal instruction",
+                ))
+            }
         };
         program.push((0, op));
     }
-    program
+    Ok(program)
 }
 
 impl Day8 {
-    fn solve_part1(&self, input: &Vec<String>) -> i64 {
-        let mut c = Computer::new(get_program(input));
+    pub fn solve_part1(&self, program: &[(usize, Op)]) -> Result<i64, PuzzleError> {
+        let mut c = Computer::new(program.to_vec());
         match c.run() {
-            ExitStatus::Success(acc) => acc,
-            ExitStatus::Stuck(acc) => acc,
+            ExitStatus::Success(acc) => Ok(acc),
+            ExitStatus::Stuck(acc) => Ok(acc),
+            ExitStatus::IllegalJump(pc) => Err(PuzzleError::NoSolution(format!(
+                "illegal jump at instruction {}",
+                pc
+            ))),
         }
     }
 
-    fn solve_part2(&self, input: &Vec<String>) -> i64 {
-        let program = get_program(input);
+    pub fn solve_part2(&self, program: &[(usize, Op)]) -> Result<i64, PuzzleError> {
         for (i, (_, op)) in program.iter().enumerate() {
-            let mut modified_program = program.clone();
-            match op {
-                Op::Jmp(val) => modified_program[i] = (0, Op::Nop(*val)),
-                Op::Nop(val) => modified_program[i] = (0, Op::Jmp(*val)),
-                _ => (),
-            }
+            self.context.cancel.check()?;
+            let mut modified_program = program.to_vec();
+            let patch = match op {
+                Op::Jmp(val) => Op::Nop(*val),
+                Op::Nop(val) => Op::Jmp(*val),
+                Op::Acc(val) => Op::Acc(*val),
+            };
+            modified_program[i] = (0, patch.clone());
             let mut c = Computer::new(modified_program);
-            match c.run() {
-                ExitStatus::Success(acc) => return acc,
-                ExitStatus::Stuck(_) => (),
+            if let ExitStatus::Success(acc) = c.run() {
+                let trace = &self.context.trace;
+                trace.value("patched instruction", i as i64);
+                trace.value("patch", format!("{:?} to {:?}", op, patch));
+                return Ok(acc);
             }
         }
-        -1
+        Err(PuzzleError::no_solution(
+            "no single patched instruction makes the program terminate",
+        ))
     }
 }
 
 impl Puzzle for Day8 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let rules: Vec<String> = lines.expect("No input file").map(|l| l.unwrap()).collect();
-        return (
-            self.solve_part1(&rules).to_string(),
-            self.solve_part2(&rules).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.program = get_program(&read_lines(input)?)?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part1(&self.program)?.to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self.solve_part2(&self.program)?.to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+// A program of about 'size' instructions with one planted corrupted instruction. The program runs
+// straight down, with 'nop's whose argument points back and 'jmp's forward over dead code. The dead
+// code jumps back to an instruction that already ran, so patching any instruction but the planted
+// one, a 'jmp' turned into a 'nop', still ends in a loop.
+impl Generator for Day8 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        let mut program: Vec<Op> = vec![];
+        let mut executed: Vec<usize> = vec![];
+        let corrupt_at = rng.range(1..=size.max(4) as i64 * 3 / 4) as usize;
+        let mut corrupted = None;
+        let (mut acc_before, mut acc_total) = (0, 0);
+        while program.len() < size || corrupted.is_none() {
+            let pc = program.len();
+            let corrupt = corrupted.is_none() && pc >= corrupt_at && !executed.is_empty();
+            let choice = if executed.is_empty() {
+                0
+            } else {
+                rng.below(10)
+            };
+            if corrupt || choice >= 7 {
+                // A jump over 1 to 3 instructions of dead code, at least 2 for the corrupted one
+                // so that patching its first dead instruction still loops.
+                let skip = rng.range(if corrupt { 2 } else { 1 }..=3) as usize;
+                program.push(Op::Jmp(skip as i64 + 1));
+                executed.push(pc);
+                for dead in pc + 1..=pc + skip {
+                    let target = *rng.choose(&executed);
+                    program.push(Op::Jmp(target as i64 - dead as i64));
+                }
+                if corrupt {
+                    corrupted = Some(pc);
+                    acc_before = acc_total;
+                }
+            } else if choice >= 5 {
+                let target = *rng.choose(&executed);
+                program.push(Op::Nop(target as i64 - pc as i64));
+                executed.push(pc);
+            } else {
+                let val = rng.range(-50..=50);
+                program.push(Op::Acc(val));
+                executed.push(pc);
+                acc_total += val;
+            }
+        }
+
+        let corrupted = corrupted.unwrap();
+        if let Op::Jmp(offset) = program[corrupted] {
+            program[corrupted] = Op::Nop(offset);
+        }
+        let lines = program
+            .iter()
+            .map(|op| match op {
+                Op::Acc(val) => format!("acc {:+}", val),
+                Op::Jmp(offset) => format!("jmp {:+}", offset),
+                Op::Nop(offset) => format!("nop {:+}", offset),
+            })
+            .collect();
+        Generated::from_lines(lines).with_answers(acc_before, acc_total)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cancel::Cancel;
+
+    const EXAMPLE: &str =
+        "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6\n";
 
     #[test]
     fn part1_example1() {
-        assert_eq!(
-            Day8 {}.solve_part1(
-                &vec!(
-                    "nop +0", "acc +1", "jmp +4", "acc +3", "jmp -3", "acc -99", "acc +1",
-                    "jmp -4", "acc +6",
-                )
-                .iter()
-                .map(|x| x.to_string())
-                .collect(),
-            ),
-            5
-        );
+        let mut day = Day8::default();
+        day.parse_str(EXAMPLE).unwrap();
+        assert_eq!(day.part1(), Ok("5".to_string()));
     }
 
     #[test]
     fn part2_example1() {
+        let mut day = Day8::default();
+        day.parse_str(EXAMPLE).unwrap();
+        assert_eq!(day.part2(), Ok("8".to_string()));
+    }
+
+    #[test]
+    fn illegal_instruction() {
         assert_eq!(
-            Day8 {}.solve_part2(
-                &vec!(
-                    "nop +0", "acc +1", "jmp +4", "acc +3", "jmp -3", "acc -99", "acc +1",
-                    "jmp -4", "acc +6",
-                )
-                .iter()
-                .map(|x| x.to_string())
-                .collect(),
-            ),
-            8
+            Day8::default().parse_str("nop +0\nmul +2").err(),
+            Some(PuzzleError::Parse {
+                line: 2,
+                column: 1,
+                text: "mul".to_string(),
+                reason: "illegal instruction".to_string(),
+            })
         );
     }
+
+    #[test]
+    fn cancelled() {
+        let cancel = Cancel::new();
+        cancel.cancel();
+        let mut day = Day8::default();
+        day.set_context(Context {
+            cancel,
+            ..Context::default()
+        });
+        day.parse_str(EXAMPLE).unwrap();
+        assert_eq!(day.part2(), Err(PuzzleError::Timeout));
+    }
 }
@@ -1,5 +1,32 @@
-use crate::puzzle::{io, File, Puzzle};
-pub struct Day9;
+//! Day 9: Encoding Error. Finds the first number that isn't the sum of two of the preceding
+//! 'preamble' numbers, 25 by default, then the contiguous range summing to it.
+
+use crate::generate::{Generated, Generator, Rng};
+use crate::params::Param;
+use crate::puzzle::{parse_token, read_lines, BufRead, Context, Puzzle, PuzzleError};
+
+#[derive(Default)]
+pub struct Day9 {
+    numbers: Vec<usize>,
+    context: Context,
+}
+
+const PREAMBLE: Param = Param::integer(
+    "preamble",
+    25,
+    1,
+    "The number of preceding numbers a number must be the sum of two of",
+);
+
+register_puzzle! {
+    puzzle: Day9,
+    year: 2020,
+    day: 9,
+    title: "Encoding Error",
+    input: Numbers,
+    reads_input: true,
+    params: &[PREAMBLE],
+}
 
 fn valid(preamble: &[usize], num: usize) -> bool {
     for i in 0..preamble.len() {
@@ -13,18 +40,33 @@ fn valid(preamble: &[usize], num: usize) -> bool {
 }
 
 impl Day9 {
-    fn solve_part1(&self, numbers: &Vec<usize>, preamble_len: usize) -> usize {
+    fn preamble_len(&self) -> usize {
+        self.context.params.integer(&PREAMBLE) as usize
+    }
+
+    pub fn solve_part1(
+        &self,
+        numbers: &[usize],
+        preamble_len: usize,
+    ) -> Result<usize, PuzzleError> {
         let offset = preamble_len;
         for i in offset..numbers.len() {
             if !valid(&numbers[i - preamble_len..i], numbers[i]) {
-                return numbers[i];
+                let trace = &self.context.trace;
+                trace.value("first invalid number", numbers[i] as i64);
+                trace.value("position", i as i64);
+                return Ok(numbers[i]);
             }
         }
-        0
+        Err(PuzzleError::no_solution("all numbers are valid"))
     }
 
-    fn solve_part2(&self, numbers: &Vec<usize>, preamble_len: usize) -> usize {
-        let invalid_no = self.solve_part1(numbers, preamble_len);
+    pub fn solve_part2(
+        &self,
+        numbers: &[usize],
+        preamble_len: usize,
+    ) -> Result<usize, PuzzleError> {
+        let invalid_no = self.solve_part1(numbers, preamble_len)?;
         for i in 0..numbers.len() {
             let mut sum: usize = 0;
             let mut min: usize = std::usize::MAX;
@@ -38,27 +80,99 @@ impl Day9 {
                 }
                 sum += numbers[j];
                 if sum == invalid_no {
-                    return min + max;
+                    let trace = &self.context.trace;
+                    trace.value("contiguous range", format!("positions {} to {}", i, j));
+                    trace.value("smallest", min as i64);
+                    trace.value("largest", max as i64);
+                    return Ok(min + max);
                 }
                 if sum > invalid_no {
                     break;
                 }
             }
         }
-        0
+        Err(PuzzleError::NoSolution(format!(
+            "no contiguous range sums to {}",
+            invalid_no
+        )))
     }
 }
 
 impl Puzzle for Day9 {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String) {
-        let numbers: Vec<usize> = lines
-            .expect("No input file")
-            .map(|l| l.unwrap().parse::<usize>().unwrap())
-            .collect();
-        return (
-            self.solve_part1(&numbers, 25).to_string(),
-            self.solve_part2(&numbers, 25).to_string(),
-        );
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+        self.numbers = read_lines(input)?
+            .iter()
+            .enumerate()
+            .map(|(i, l)| parse_token::<usize>(i, l, l))
+            .collect::<Result<Vec<usize>, PuzzleError>>()?;
+        Ok(())
+    }
+
+    fn part1(&self) -> Result<String, PuzzleError> {
+        Ok(self
+            .solve_part1(&self.numbers, self.preamble_len())?
+            .to_string())
+    }
+
+    fn part2(&self) -> Result<String, PuzzleError> {
+        Ok(self
+            .solve_part2(&self.numbers, self.preamble_len())?
+            .to_string())
+    }
+
+    fn set_context(&mut self, context: Context) {
+        self.context = context;
+    }
+}
+
+// 'size' numbers, at least 30, with a preamble of 25. Every number after the preamble is the sum
+// of two of the 25 before it, except for a planted one that is the sum of a contiguous range of at
+// least three earlier numbers instead. The numbers grow about a bit every 11 numbers and overflow
+// past 600 or so, so the size is capped at 500.
+impl Generator for Day9 {
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated {
+        const PREAMBLE: usize = 25;
+        let count = size.clamp(30, 500);
+        loop {
+            let mut numbers: Vec<usize> = (1..=50).collect();
+            rng.shuffle(&mut numbers);
+            numbers.truncate(PREAMBLE);
+            let invalid_at = PREAMBLE + 4 + rng.below(count - PREAMBLE - 4);
+            let mut planted = None;
+            while numbers.len() < count {
+                let window = &numbers[numbers.len() - PREAMBLE..];
+                if numbers.len() == invalid_at {
+                    let len = rng.range(3..=17.min(invalid_at as i64 - 1)) as usize;
+                    let start = rng.below(invalid_at - len);
+                    let range = &numbers[start..start + len];
+                    let sum: usize = range.iter().sum();
+                    let is_pair = (0..PREAMBLE)
+                        .any(|i| (0..PREAMBLE).any(|j| i != j && window[i] + window[j] == sum));
+                    // The solver takes the first range that sums to the number.
+                    let earlier = (0..start).any(|i| {
+                        let mut total = 0;
+                        numbers[i..].iter().any(|x| {
+                            total += x;
+                            total == sum
+                        })
+                    });
+                    if is_pair || earlier {
+                        break;
+                    }
+                    let min_max = range.iter().min().unwrap() + range.iter().max().unwrap();
+                    planted = Some((sum, min_max));
+                    numbers.push(sum);
+                } else {
+                    let i = rng.below(PREAMBLE);
+                    let j = (i + 1 + rng.below(PREAMBLE - 1)) % PREAMBLE;
+                    numbers.push(window[i] + window[j]);
+                }
+            }
+            if let (Some((invalid, min_max)), true) = (planted, numbers.len() == count) {
+                let lines = numbers.iter().map(|x| x.to_string()).collect();
+                return Generated::from_lines(lines).with_answers(invalid, min_max);
+            }
+        }
     }
 }
 
@@ -69,28 +183,40 @@ mod tests {
     #[test]
     fn part1_example1() {
         assert_eq!(
-            Day9 {}.solve_part1(
+            Day9::default().solve_part1(
                 &vec!(
                     35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127, 219, 299, 277,
                     309, 576,
                 ),
                 5
             ),
-            127
+            Ok(127)
         );
     }
 
     #[test]
     fn part2_example1() {
         assert_eq!(
-            Day9 {}.solve_part2(
+            Day9::default().solve_part2(
                 &vec!(
                     35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127, 219, 299, 277,
                     309, 576,
                 ),
                 5
             ),
-            62
+            Ok(62)
         );
     }
+
+    #[test]
+    fn largest_generated_input() {
+        for seed in 0..10 {
+            let generated = Day9::default().generate(usize::MAX, &mut Rng::new(seed));
+            assert_eq!(generated.input.lines().count(), 500);
+            let mut day = Day9::default();
+            day.parse_str(&generated.input).unwrap();
+            assert_eq!(day.part1().ok(), generated.answers[0]);
+            assert_eq!(day.part2().ok(), generated.answers[1]);
+        }
+    }
 }
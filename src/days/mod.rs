@@ -1,57 +1,186 @@
+//! One module per day. Each has a `DayN` type implementing [`Puzzle`], plus the parsers and
+//! helpers its solution is built from. A day registers itself with `register_puzzle!`, the
+//! modules and [`REGISTRY`] are generated by the build script from the files in this directory.
+//! The 2020 days live directly in this directory, later years in a directory per year such as
+//! `2021/day1.rs`, which becomes the module `y2021::day1`.
+
+use std::fmt;
+
+use crate::generate::{Generated, Rng};
+use crate::params::Param;
 use crate::puzzle::Puzzle;
-mod day1;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day17;
-mod day18;
-mod day19;
-mod day2;
-mod day20;
-mod day21;
-mod day22;
-mod day23;
-mod day24;
-mod day25;
-mod day3;
-mod day4;
-mod day5;
-mod day6;
-mod day7;
-mod day8;
-mod day9;
-
-pub fn puzzle_factory(day: u8) -> Option<Box<dyn Puzzle>> {
-    match day {
-        1 => Some(Box::new(day1::Day1 {})),
-        2 => Some(Box::new(day2::Day2 {})),
-        3 => Some(Box::new(day3::Day3 {})),
-        4 => Some(Box::new(day4::Day4 {})),
-        5 => Some(Box::new(day5::Day5 {})),
-        6 => Some(Box::new(day6::Day6 {})),
-        7 => Some(Box::new(day7::Day7 {})),
-        8 => Some(Box::new(day8::Day8 {})),
-        9 => Some(Box::new(day9::Day9 {})),
-        10 => Some(Box::new(day10::Day10 {})),
-        11 => Some(Box::new(day11::Day11 {})),
-        12 => Some(Box::new(day12::Day12 {})),
-        13 => Some(Box::new(day13::Day13 {})),
-        14 => Some(Box::new(day14::Day14 {})),
-        15 => Some(Box::new(day15::Day15 {})),
-        16 => Some(Box::new(day16::Day16 {})),
-        17 => Some(Box::new(day17::Day17 {})),
-        18 => Some(Box::new(day18::Day18 {})),
-        19 => Some(Box::new(day19::Day19 {})),
-        20 => Some(Box::new(day20::Day20 {})),
-        21 => Some(Box::new(day21::Day21 {})),
-        22 => Some(Box::new(day22::Day22 {})),
-        23 => Some(Box::new(day23::Day23 {})),
-        24 => Some(Box::new(day24::Day24 {})),
-        25 => Some(Box::new(day25::Day25 {})),
-        _ => None,
+use crate::reference::Reference;
+
+/// Identifies a puzzle by its year and day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PuzzleId {
+    pub year: u16,
+    pub day: u8,
+}
+
+impl PuzzleId {
+    pub fn new(year: u16, day: u8) -> Self {
+        PuzzleId { year, day }
+    }
+}
+
+impl fmt::Display for PuzzleId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} day {}", self.year, self.day)
+    }
+}
+
+/// The shape of a puzzle input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputKind {
+    /// One number per line.
+    Numbers,
+    /// One record per line.
+    Lines,
+    /// A rectangular map of characters.
+    Grid,
+    /// Blocks of lines separated by blank lines.
+    Sections,
+    /// A single line.
+    SingleLine,
+}
+
+impl InputKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputKind::Numbers => "numbers",
+            InputKind::Lines => "lines",
+            InputKind::Grid => "grid",
+            InputKind::Sections => "sections",
+            InputKind::SingleLine => "single line",
+        }
+    }
+}
+
+/// Metadata of a registered puzzle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PuzzleInfo {
+    pub year: u16,
+    pub day: u8,
+    pub title: &'static str,
+    pub input: InputKind,
+    /// False if the puzzle doesn't read an input file and is parsed from an empty input instead.
+    pub reads_input: bool,
+}
+
+impl PuzzleInfo {
+    pub fn id(&self) -> PuzzleId {
+        PuzzleId::new(self.year, self.day)
+    }
+}
+
+/// A registered puzzle: its metadata, a function creating it, one generating inputs for it, the
+/// parameters it accepts and, if the day has one, a function creating its reference solver.
+pub struct Registration {
+    pub info: PuzzleInfo,
+    pub create: fn() -> Box<dyn Puzzle>,
+    pub generate: fn(usize, &mut Rng) -> Generated,
+    pub params: &'static [Param],
+    /// The solver's version, part of the key of its cached answers. Bump it when a change to the
+    /// solver can change its answers.
+    pub version: &'static str,
+    pub reference: Option<fn() -> Box<dyn Reference>>,
+}
+
+// Register the puzzle type of a day module, which must implement Default and Generator, and
+// Reference if 'reference: true' is given. 'params' lists the parameters the puzzle reads from its
+// context and 'version' is the solver version, "1" if not given. This defines the module's
+// REGISTRATION that the generated REGISTRY refers to.
+macro_rules! register_puzzle {
+    (@reference $puzzle:ident) => {
+        None
+    };
+    (@reference $puzzle:ident true) => {
+        Some(|| Box::new($puzzle::default()))
+    };
+    (@params) => {
+        &[]
+    };
+    (@params $params:expr) => {
+        $params
+    };
+    (@version) => {
+        "1"
+    };
+    (@version $version:expr) => {
+        $version
+    };
+    (
+        puzzle: $puzzle:ident,
+        year: $year:expr,
+        day: $day:expr,
+        title: $title:expr,
+        input: $input:ident,
+        reads_input: $reads_input:expr
+        $(, version: $version:expr)?
+        $(, params: $params:expr)?
+        $(, reference: $reference:tt)? $(,)?
+    ) => {
+        pub const REGISTRATION: $crate::days::Registration = $crate::days::Registration {
+            info: $crate::days::PuzzleInfo {
+                year: $year,
+                day: $day,
+                title: $title,
+                input: $crate::days::InputKind::$input,
+                reads_input: $reads_input,
+            },
+            create: || Box::new($puzzle::default()),
+            generate: |size, rng| {
+                $crate::generate::Generator::generate(&$puzzle::default(), size, rng)
+            },
+            params: register_puzzle!(@params $($params)?),
+            version: register_puzzle!(@version $($version)?),
+            reference: register_puzzle!(@reference $puzzle $($reference)?),
+        };
+    };
+}
+
+include!(concat!(env!("OUT_DIR"), "/days.rs"));
+
+/// Get the registration of the puzzle 'id', if there is one.
+pub fn find(id: PuzzleId) -> Option<&'static Registration> {
+    REGISTRY
+        .iter()
+        .find(|registration| registration.info.id() == id)
+}
+
+/// The years with at least one registered puzzle, in ascending order.
+pub fn years() -> Vec<u16> {
+    let mut years: Vec<u16> = REGISTRY
+        .iter()
+        .map(|registration| registration.info.year)
+        .collect();
+    years.sort_unstable();
+    years.dedup();
+    years
+}
+
+/// Create the puzzle for 'day' of 'year', or None if there is no solution for that day.
+pub fn puzzle_factory(year: u16, day: u8) -> Option<Box<dyn Puzzle>> {
+    find(PuzzleId::new(year, day)).map(|registration| (registration.create)())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry() {
+        let mut ids: Vec<PuzzleId> = REGISTRY.iter().map(|r| r.info.id()).collect();
+        assert!(ids.iter().all(|id| (1..=25).contains(&id.day)));
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), REGISTRY.len(), "a day is registered twice");
+        assert!(years().contains(&2020));
+
+        let id = PuzzleId::new(2020, 18);
+        assert_eq!(find(id).unwrap().info.title, "Operation Order");
+        assert_eq!(id.to_string(), "2020 day 18");
+        assert!(find(PuzzleId::new(2020, 26)).is_none());
     }
 }
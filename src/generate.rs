@@ -0,0 +1,166 @@
+//! Synthetic puzzle inputs for stress and performance testing. Every day implements
+//! [`Generator`], which builds a valid input of a requested size from a seeded [`Rng`] so that
+//! the same size and seed always give the same input. Where the construction fixes the answers,
+//! such as the planted pair and triple of Day 1, the generator returns them as well.
+
+use std::ops::RangeInclusive;
+
+use crate::days::{self, PuzzleId};
+
+/// A small pseudo-random number generator (SplitMix64). Not suitable for anything but tests.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number below 'n', which must not be 0.
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "empty range");
+        // Reject the top values that would make the lower results slightly more likely.
+        let n = n as u64;
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return (x % n) as usize;
+            }
+        }
+    }
+
+    /// A number in 'range', which must not be empty.
+    pub fn range(&mut self, range: RangeInclusive<i64>) -> i64 {
+        let (low, high) = range.into_inner();
+        assert!(low <= high, "empty range");
+        let span = (high as i128 - low as i128 + 1) as u128;
+        if span > u64::MAX as u128 {
+            return self.next_u64() as i64;
+        }
+        let n = span as u64;
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return (low as i128 + (x % n) as i128) as i64;
+            }
+        }
+    }
+
+    /// True with probability 'p'.
+    pub fn chance(&mut self, p: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    /// A random element of 'items', which must not be empty.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+/// A generated input and the answers it was built to have, None where they are not known
+/// without solving the puzzle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Generated {
+    pub input: String,
+    pub answers: [Option<String>; 2],
+}
+
+impl Generated {
+    /// An input built from 'lines', with unknown answers.
+    pub fn from_lines(lines: Vec<String>) -> Self {
+        let mut input = lines.join("\n");
+        input.push('\n');
+        Generated {
+            input,
+            answers: [None, None],
+        }
+    }
+
+    pub fn with_answers(mut self, part1: impl ToString, part2: impl ToString) -> Self {
+        self.answers = [Some(part1.to_string()), Some(part2.to_string())];
+        self
+    }
+}
+
+/// Builds synthetic inputs for a puzzle.
+pub trait Generator {
+    /// Generate an input with about 'size' records, such as lines, groups or tiles. What a record
+    /// is, and how far 'size' is honoured, depends on the day, see its implementation.
+    fn generate(&self, size: usize, rng: &mut Rng) -> Generated;
+}
+
+/// Generate an input for the puzzle 'id' with the given size and seed, or None if there is no such
+/// puzzle.
+pub fn generate(id: PuzzleId, size: usize, seed: u64) -> Option<Generated> {
+    days::find(id).map(|registration| (registration.generate)(size, &mut Rng::new(seed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reproducible() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let first: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+        let second: Vec<u64> = (0..10).map(|_| b.next_u64()).collect();
+        assert_eq!(first, second);
+        assert_ne!(Rng::new(43).next_u64(), first[0]);
+    }
+
+    #[test]
+    fn ranges() {
+        let mut rng = Rng::new(1);
+        for _ in 0..1000 {
+            assert!(rng.below(7) < 7);
+            assert!((-3..=3).contains(&rng.range(-3..=3)));
+        }
+        assert_eq!(rng.range(5..=5), 5);
+        let mut items: Vec<usize> = (0..20).collect();
+        rng.shuffle(&mut items);
+        items.sort_unstable();
+        assert_eq!(items, (0..20).collect::<Vec<usize>>());
+    }
+
+    // Every generated input parses, and the answers the generator knows are the ones found.
+    #[test]
+    fn generated_inputs_are_solved() {
+        for registration in days::REGISTRY {
+            let id = registration.info.id();
+            let generated = generate(id, 20, 7).unwrap();
+            let mut puzzle = (registration.create)();
+            puzzle
+                .parse_str(&generated.input)
+                .unwrap_or_else(|e| panic!("{}: {}\n{}", id, e, generated.input));
+            for (part, expected) in generated.answers.iter().enumerate() {
+                if let Some(expected) = expected {
+                    let answer = if part == 0 {
+                        puzzle.part1()
+                    } else {
+                        puzzle.part2()
+                    };
+                    assert_eq!(answer.as_ref(), Ok(expected), "{} part {}", id, part + 1);
+                }
+            }
+        }
+    }
+}
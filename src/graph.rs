@@ -0,0 +1,456 @@
+//! Graph algorithms for the puzzles about rules that refer to each other, such as the Day 7 bags
+//! that contain other bags, and about assigning each item to exactly one candidate, such as the
+//! Day 16 ticket fields and the Day 21 allergens.
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::ops::Range;
+
+/// A directed graph with nodes of type `N` and edge weights of type `W`. Nodes are numbered in the
+/// order they are added, and all algorithms work on those indices.
+#[derive(Debug, Clone)]
+pub struct Digraph<N, W> {
+    nodes: Vec<N>,
+    indices: HashMap<N, usize>,
+    edges: Vec<Vec<(usize, W)>>,
+}
+
+impl<N, W> Default for Digraph<N, W> {
+    fn default() -> Self {
+        Digraph {
+            nodes: vec![],
+            indices: HashMap::new(),
+            edges: vec![],
+        }
+    }
+}
+
+impl<N: Clone + Eq + Hash, W> Digraph<N, W> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add 'node' unless it is already in the graph, and return its index.
+    pub fn add_node(&mut self, node: N) -> usize {
+        if let Some(&i) = self.indices.get(&node) {
+            return i;
+        }
+        self.nodes.push(node.clone());
+        self.edges.push(vec![]);
+        self.indices.insert(node, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    /// Add an edge, adding its nodes if they are not in the graph yet.
+    pub fn add_edge(&mut self, from: N, to: N, weight: W) {
+        let from = self.add_node(from);
+        let to = self.add_node(to);
+        self.edges[from].push((to, weight));
+    }
+
+    pub fn index(&self, node: &N) -> Option<usize> {
+        self.indices.get(node).copied()
+    }
+}
+
+impl<N, W> Digraph<N, W> {
+    pub fn node(&self, i: usize) -> &N {
+        &self.nodes[i]
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The targets and weights of the edges leaving node 'i'.
+    pub fn edges(&self, i: usize) -> &[(usize, W)] {
+        &self.edges[i]
+    }
+
+    /// All nodes ordered so that every edge points forward, or the nodes of a cycle if there is
+    /// none. The cycle starts and ends with the same node.
+    pub fn topological_sort(&self) -> Result<Vec<usize>, Vec<usize>> {
+        let mut order = self.postorder(0..self.len())?;
+        order.reverse();
+        Ok(order)
+    }
+
+    /// The nodes of a cycle, starting and ending with the same node, or None if the graph is
+    /// acyclic.
+    pub fn find_cycle(&self) -> Option<Vec<usize>> {
+        self.postorder(0..self.len()).err()
+    }
+
+    /// The nodes of a cycle that can be reached from node 'start', as for [`Digraph::find_cycle`].
+    pub fn find_cycle_from(&self, start: usize) -> Option<Vec<usize>> {
+        self.postorder(start..start + 1).err()
+    }
+
+    /// The nodes with a path to node 'i', in no particular order. 'i' itself is only among them if
+    /// it is on a cycle.
+    pub fn ancestors(&self, i: usize) -> Vec<usize> {
+        let mut predecessors = vec![vec![]; self.len()];
+        for (from, edges) in self.edges.iter().enumerate() {
+            for (to, _) in edges {
+                predecessors[*to].push(from);
+            }
+        }
+        let mut seen = vec![false; self.len()];
+        let mut todo = vec![i];
+        let mut ancestors = vec![];
+        while let Some(node) = todo.pop() {
+            for &predecessor in &predecessors[node] {
+                if !seen[predecessor] {
+                    seen[predecessor] = true;
+                    ancestors.push(predecessor);
+                    todo.push(predecessor);
+                }
+            }
+        }
+        ancestors
+    }
+
+    /// Compute a value for every node from the values of the nodes its edges point to, each node
+    /// being visited once. 'f' gets the node and the weight and value of each of its edges. Returns
+    /// the values by node index, or the nodes of a cycle as for [`Digraph::topological_sort`].
+    pub fn fold<T, F>(&self, mut f: F) -> Result<Vec<T>, Vec<usize>>
+    where
+        F: FnMut(usize, &[(&W, &T)]) -> T,
+    {
+        let mut values: Vec<Option<T>> = (0..self.len()).map(|_| None).collect();
+        for i in self.postorder(0..self.len())? {
+            let value = {
+                let successors: Vec<(&W, &T)> = self.edges[i]
+                    .iter()
+                    .map(|(j, weight)| (weight, values[*j].as_ref().unwrap()))
+                    .collect();
+                f(i, &successors)
+            };
+            values[i] = Some(value);
+        }
+        Ok(values.into_iter().map(Option::unwrap).collect())
+    }
+
+    // Depth first search from each of the 'roots' in turn, listing each node after all nodes
+    // reachable from it. Iterative so that long chains cannot overflow the stack.
+    fn postorder(&self, roots: Range<usize>) -> Result<Vec<usize>, Vec<usize>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            New,
+            OnStack,
+            Done,
+        }
+        let mut state = vec![State::New; self.len()];
+        let mut order = Vec::with_capacity(self.len());
+        for root in roots {
+            if state[root] != State::New {
+                continue;
+            }
+            // Nodes on the current path and the index of the next edge to follow from each.
+            let mut stack = vec![(root, 0)];
+            state[root] = State::OnStack;
+            while let Some((node, edge)) = stack.last_mut() {
+                let node = *node;
+                match self.edges[node].get(*edge) {
+                    Some(&(next, _)) => {
+                        *edge += 1;
+                        match state[next] {
+                            State::New => {
+                                state[next] = State::OnStack;
+                                stack.push((next, 0));
+                            }
+                            State::OnStack => {
+                                let start = stack.iter().position(|(n, _)| *n == next).unwrap();
+                                let mut cycle: Vec<usize> =
+                                    stack[start..].iter().map(|(n, _)| *n).collect();
+                                cycle.push(next);
+                                return Err(cycle);
+                            }
+                            State::Done => (),
+                        }
+                    }
+                    None => {
+                        state[node] = State::Done;
+                        order.push(node);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        Ok(order)
+    }
+}
+
+/// Why [`Bipartite::unique_matching`] could not assign every left node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatchingError {
+    /// No matching assigns this left node, and the others, a right node.
+    Unmatched(usize),
+    /// These left nodes can be assigned different right nodes in different matchings.
+    Ambiguous(Vec<usize>),
+}
+
+/// A bipartite graph with 'left' and 'right' nodes numbered from 0, where each left node is to
+/// be assigned one of the right nodes it has an edge to.
+#[derive(Debug, Clone)]
+pub struct Bipartite {
+    right: usize,
+    edges: Vec<Vec<usize>>,
+}
+
+impl Bipartite {
+    pub fn new(left: usize, right: usize) -> Self {
+        Bipartite {
+            right,
+            edges: vec![vec![]; left],
+        }
+    }
+
+    pub fn add_edge(&mut self, left: usize, right: usize) {
+        assert!(right < self.right, "right node {} out of range", right);
+        if !self.edges[left].contains(&right) {
+            self.edges[left].push(right);
+        }
+    }
+
+    /// A maximum matching found with the Hopcroft-Karp algorithm, as the right node matched to
+    /// each left node.
+    pub fn maximum_matching(&self) -> Vec<Option<usize>> {
+        let mut left_match: Vec<Option<usize>> = vec![None; self.edges.len()];
+        let mut right_match: Vec<Option<usize>> = vec![None; self.right];
+        loop {
+            // Breadth first search from the free left nodes, layering the left nodes by the length
+            // of the shortest alternating path to them.
+            let mut layer = vec![usize::MAX; self.edges.len()];
+            let mut queue = VecDeque::new();
+            for (l, m) in left_match.iter().enumerate() {
+                if m.is_none() {
+                    layer[l] = 0;
+                    queue.push_back(l);
+                }
+            }
+            let mut found = false;
+            while let Some(l) = queue.pop_front() {
+                for &r in &self.edges[l] {
+                    match right_match[r] {
+                        None => found = true,
+                        Some(next) if layer[next] == usize::MAX => {
+                            layer[next] = layer[l] + 1;
+                            queue.push_back(next);
+                        }
+                        Some(_) => (),
+                    }
+                }
+            }
+            if !found {
+                return left_match;
+            }
+
+            // Augment along vertex disjoint shortest paths.
+            for l in 0..self.edges.len() {
+                if left_match[l].is_none() {
+                    self.augment(l, &mut layer, &mut left_match, &mut right_match);
+                }
+            }
+        }
+    }
+
+    fn augment(
+        &self,
+        l: usize,
+        layer: &mut [usize],
+        left_match: &mut [Option<usize>],
+        right_match: &mut [Option<usize>],
+    ) -> bool {
+        for &r in &self.edges[l] {
+            let next_ok = match right_match[r] {
+                None => true,
+                Some(next) => {
+                    layer[next] == layer[l] + 1
+                        && self.augment(next, layer, left_match, right_match)
+                }
+            };
+            if next_ok {
+                left_match[l] = Some(r);
+                right_match[r] = Some(l);
+                return true;
+            }
+        }
+        // Dead end, no need to try this node again in this phase.
+        layer[l] = usize::MAX;
+        false
+    }
+
+    /// The right node assigned to each left node if every left node can be assigned one and there
+    /// is only one way to do so.
+    pub fn unique_matching(&self) -> Result<Vec<usize>, MatchingError> {
+        let matching = self.maximum_matching();
+        let mut left_match = vec![];
+        for (l, m) in matching.iter().enumerate() {
+            left_match.push(m.ok_or(MatchingError::Unmatched(l))?);
+        }
+        let mut right_match = vec![None; self.right];
+        for (l, &r) in left_match.iter().enumerate() {
+            right_match[r] = Some(l);
+        }
+
+        // Left node l can give up its right node for another one, r, if r is free or if the left
+        // node matched to r can do the same in turn, ending either at a free right node or back at
+        // l. So l is ambiguous if it can reach itself or a node next to a free right node in the
+        // graph with an edge from l to every other left node whose right node l could take.
+        let (matched, right_match) = (&left_match, &right_match);
+        let successors = |l: usize| {
+            self.edges[l]
+                .iter()
+                .filter(move |&&r| r != matched[l])
+                .map(move |&r| right_match[r])
+        };
+        let mut ambiguous = vec![];
+        for l in 0..left_match.len() {
+            let mut seen = vec![false; left_match.len()];
+            let mut queue = VecDeque::from(vec![l]);
+            'search: while let Some(node) = queue.pop_front() {
+                for next in successors(node) {
+                    match next {
+                        None => {
+                            ambiguous.push(l);
+                            break 'search;
+                        }
+                        Some(next) if next == l => {
+                            ambiguous.push(l);
+                            break 'search;
+                        }
+                        Some(next) if !seen[next] => {
+                            seen[next] = true;
+                            queue.push_back(next);
+                        }
+                        Some(_) => (),
+                    }
+                }
+            }
+        }
+        if ambiguous.is_empty() {
+            Ok(left_match)
+        } else {
+            Err(MatchingError::Ambiguous(ambiguous))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digraph(edges: &[(&'static str, &'static str)]) -> Digraph<&'static str, ()> {
+        let mut graph = Digraph::new();
+        for &(from, to) in edges {
+            graph.add_edge(from, to, ());
+        }
+        graph
+    }
+
+    #[test]
+    fn topological_order() {
+        let graph = digraph(&[("a", "b"), ("c", "a"), ("b", "d"), ("c", "d")]);
+        let order = graph.topological_sort().unwrap();
+        let position = |node| order.iter().position(|&i| i == graph.index(&node).unwrap());
+        assert!(position("c") < position("a"));
+        assert!(position("a") < position("b"));
+        assert!(position("b") < position("d"));
+        assert_eq!(graph.find_cycle(), None);
+    }
+
+    #[test]
+    fn cycles() {
+        let graph = digraph(&[("a", "b"), ("b", "c"), ("c", "b"), ("c", "d")]);
+        let cycle: Vec<&str> = graph
+            .find_cycle()
+            .unwrap()
+            .iter()
+            .map(|&i| *graph.node(i))
+            .collect();
+        assert_eq!(cycle, ["b", "c", "b"]);
+        assert!(graph.topological_sort().is_err());
+        assert!(digraph(&[("a", "a")]).fold(|_, _| 0).is_err());
+        // Only the cycles below the start count.
+        assert!(graph.find_cycle_from(graph.index(&"a").unwrap()).is_some());
+        assert_eq!(graph.find_cycle_from(graph.index(&"d").unwrap()), None);
+    }
+
+    #[test]
+    fn ancestors() {
+        let graph = digraph(&[("a", "b"), ("b", "c"), ("c", "b"), ("c", "d"), ("e", "a")]);
+        let ancestors = |node| {
+            let mut nodes: Vec<&str> = graph
+                .ancestors(graph.index(&node).unwrap())
+                .iter()
+                .map(|&i| *graph.node(i))
+                .collect();
+            nodes.sort_unstable();
+            nodes
+        };
+        assert_eq!(ancestors("d"), ["a", "b", "c", "e"]);
+        assert_eq!(ancestors("a"), ["e"]);
+        assert_eq!(ancestors("b"), ["a", "b", "c", "e"]);
+    }
+
+    #[test]
+    fn fold_paths() {
+        // Count the paths from every node to "d", each node being evaluated once.
+        let graph = digraph(&[("a", "b"), ("a", "c"), ("b", "d"), ("c", "d"), ("a", "d")]);
+        let paths = graph
+            .fold(|node, successors| {
+                if *graph.node(node) == "d" {
+                    1
+                } else {
+                    successors.iter().map(|(_, paths)| **paths).sum()
+                }
+            })
+            .unwrap();
+        assert_eq!(paths[graph.index(&"a").unwrap()], 3);
+    }
+
+    #[test]
+    fn matchings() {
+        // 0 can only be 1, which leaves 0 for 1 and 2 for 2.
+        let mut graph = Bipartite::new(3, 3);
+        for &(l, r) in &[(0, 1), (1, 0), (1, 1), (2, 0), (2, 1), (2, 2)] {
+            graph.add_edge(l, r);
+        }
+        assert_eq!(graph.unique_matching(), Ok(vec![1, 0, 2]));
+
+        // 0 and 1 can swap, 2 is fixed.
+        let mut graph = Bipartite::new(3, 3);
+        for &(l, r) in &[(0, 0), (0, 1), (1, 0), (1, 1), (2, 2)] {
+            graph.add_edge(l, r);
+        }
+        assert_eq!(
+            graph.unique_matching(),
+            Err(MatchingError::Ambiguous(vec![0, 1]))
+        );
+
+        // 1 could also take the unused right node 2.
+        let mut graph = Bipartite::new(2, 3);
+        for &(l, r) in &[(0, 0), (1, 0), (1, 1), (1, 2)] {
+            graph.add_edge(l, r);
+        }
+        assert_eq!(
+            graph.unique_matching(),
+            Err(MatchingError::Ambiguous(vec![1]))
+        );
+
+        // 0 and 1 both need right node 0.
+        let mut graph = Bipartite::new(2, 2);
+        graph.add_edge(0, 0);
+        graph.add_edge(1, 0);
+        assert_eq!(graph.maximum_matching().iter().flatten().count(), 1);
+        assert!(matches!(
+            graph.unique_matching(),
+            Err(MatchingError::Unmatched(_))
+        ));
+    }
+}
@@ -0,0 +1,312 @@
+//! A rectangular grid of cells for the puzzles whose input is a map, such as the Day 3 slope, the
+//! Day 11 seat layout or the Day 20 image tiles.
+
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+use crate::puzzle::PuzzleError;
+
+/// Offsets of the 4 orthogonal neighbours, clockwise from the top. y grows downwards.
+pub const DIRECTIONS_4: [(i64, i64); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+/// Offsets of all 8 neighbours, clockwise from the top.
+pub const DIRECTIONS_8: [(i64, i64); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+/// A rectangular grid stored row by row. Cells are addressed as (x, y), with (0, 0) the top left
+/// corner. Indexing with `grid[(x, y)]` panics outside the grid, `get` returns None instead and
+/// `get_wrapping` treats the grid as repeating in all directions.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// Create a grid from its rows, or None if the rows are not all the same length.
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Option<Grid<T>> {
+        let height = rows.len();
+        let width = rows.first().map_or(0, |row| row.len());
+        if rows.iter().any(|row| row.len() != width) {
+            return None;
+        }
+        Some(Grid {
+            width,
+            height,
+            cells: rows.into_iter().flatten().collect(),
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn offset(&self, x: i64, y: i64) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            None
+        } else {
+            Some(y as usize * self.width + x as usize)
+        }
+    }
+
+    /// The cell at (x, y), or None if that is outside the grid.
+    pub fn get(&self, x: i64, y: i64) -> Option<&T> {
+        self.offset(x, y).map(|i| &self.cells[i])
+    }
+
+    pub fn get_mut(&mut self, x: i64, y: i64) -> Option<&mut T> {
+        self.offset(x, y).map(move |i| &mut self.cells[i])
+    }
+
+    /// The cell at (x, y) of the grid repeated in all directions. Panics if the grid is empty.
+    pub fn get_wrapping(&self, x: i64, y: i64) -> &T {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        &self[(x, y)]
+    }
+
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+
+    /// All cells with their position, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), &T)> + '_ {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(i, cell)| ((i % width, i / width), cell))
+    }
+
+    /// The neighbours of (x, y) at the given 'offsets' that are inside the grid, with their
+    /// position.
+    pub fn neighbours<'a>(
+        &'a self,
+        x: usize,
+        y: usize,
+        offsets: &'a [(i64, i64)],
+    ) -> impl Iterator<Item = ((usize, usize), &'a T)> + 'a {
+        offsets.iter().filter_map(move |(dx, dy)| {
+            let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+            self.get(nx, ny)
+                .map(|cell| ((nx as usize, ny as usize), cell))
+        })
+    }
+
+    pub fn neighbours4(&self, x: usize, y: usize) -> impl Iterator<Item = ((usize, usize), &T)> {
+        self.neighbours(x, y, &DIRECTIONS_4)
+    }
+
+    pub fn neighbours8(&self, x: usize, y: usize) -> impl Iterator<Item = ((usize, usize), &T)> {
+        self.neighbours(x, y, &DIRECTIONS_8)
+    }
+
+    /// The cells seen from (x, y) looking in 'direction' with their position, nearest first, up
+    /// to the edge of the grid. (x, y) itself is not included and 'direction' must not be (0, 0).
+    pub fn ray(
+        &self,
+        x: usize,
+        y: usize,
+        direction: (i64, i64),
+    ) -> impl Iterator<Item = ((usize, usize), &T)> {
+        let (dx, dy) = direction;
+        (1..).map_while(move |i| {
+            let (nx, ny) = (x as i64 + i * dx, y as i64 + i * dy);
+            self.get(nx, ny)
+                .map(|cell| ((nx as usize, ny as usize), cell))
+        })
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(width: usize, height: usize, value: T) -> Grid<T> {
+        Grid {
+            width,
+            height,
+            cells: vec![value; width * height],
+        }
+    }
+
+    /// The grid turned a quarter turn clockwise.
+    pub fn rotate_clockwise(&self) -> Grid<T> {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in 0..self.width {
+            for x in 0..self.height {
+                cells.push(self[(y, self.height - 1 - x)].clone());
+            }
+        }
+        Grid {
+            width: self.height,
+            height: self.width,
+            cells,
+        }
+    }
+
+    /// The grid mirrored left to right.
+    pub fn flip_horizontal(&self) -> Grid<T> {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in 0..self.height {
+            cells.extend(self.row(y).iter().rev().cloned());
+        }
+        Grid { cells, ..*self }
+    }
+
+    /// The grid mirrored top to bottom.
+    pub fn flip_vertical(&self) -> Grid<T> {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in (0..self.height).rev() {
+            cells.extend_from_slice(self.row(y));
+        }
+        Grid { cells, ..*self }
+    }
+}
+
+impl<T: PartialEq> Grid<T> {
+    /// Count the cells equal to 'value'.
+    pub fn count(&self, value: &T) -> usize {
+        self.cells.iter().filter(|cell| *cell == value).count()
+    }
+}
+
+impl Grid<char> {
+    /// Parse a map with one row per line. Every row must have the same length and only contain
+    /// characters from 'valid'.
+    pub fn from_lines(lines: &[String], valid: &str) -> Result<Grid<char>, PuzzleError> {
+        let mut rows: Vec<Vec<char>> = vec![];
+        for (i, line) in lines.iter().enumerate() {
+            if line.is_empty() {
+                return Err(PuzzleError::malformed(i, line, line, "empty map row"));
+            }
+            if let Some((pos, _)) = line.char_indices().find(|(_, c)| !valid.contains(*c)) {
+                return Err(PuzzleError::malformed(
+                    i,
+                    line,
+                    &line[pos..],
+                    "unexpected character",
+                ));
+            }
+            let row: Vec<char> = line.chars().collect();
+            if !rows.is_empty() && row.len() != rows[0].len() {
+                return Err(PuzzleError::malformed(i, line, line, "row length differs"));
+            }
+            rows.push(row);
+        }
+        Ok(Grid::from_rows(rows).unwrap())
+    }
+}
+
+// An empty grid, written out since deriving it would require T: Default.
+impl<T> Default for Grid<T> {
+    fn default() -> Self {
+        Grid {
+            width: 0,
+            height: 0,
+            cells: vec![],
+        }
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        assert!(x < self.width, "x {} is outside the grid", x);
+        &self.cells[y * self.width + x]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+        assert!(x < self.width, "x {} is outside the grid", x);
+        &mut self.cells[y * self.width + x]
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.height {
+            for cell in self.row(y) {
+                write!(f, "{}", cell)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(lines: &[&str]) -> Grid<char> {
+        let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        Grid::from_lines(&lines, ".#L").unwrap()
+    }
+
+    #[test]
+    fn parse_and_index() {
+        let g = grid(&["#..", ".L."]);
+        assert_eq!((g.width(), g.height()), (3, 2));
+        assert_eq!(g[(1, 1)], 'L');
+        assert_eq!(g.get(3, 0), None);
+        assert_eq!(g.get(-1, 0), None);
+        assert_eq!(g.get_wrapping(3, -2), &'#');
+        assert_eq!(g.count(&'.'), 4);
+        assert_eq!(g.to_string(), "#..\n.L.\n");
+
+        let lines = vec!["#..".to_string(), "#x.".to_string(), "#".to_string()];
+        match Grid::from_lines(&lines, ".#") {
+            Err(PuzzleError::Parse { line, column, .. }) => assert_eq!((line, column), (2, 2)),
+            _ => panic!("Expected a parse error"),
+        }
+        assert!(Grid::from_lines(&lines[2..], ".#").is_ok());
+        assert!(Grid::from_lines(&[lines[0].clone(), lines[2].clone()], ".#").is_err());
+    }
+
+    #[test]
+    fn neighbours_and_rays() {
+        let g = grid(&["#.#", "...", "L.#"]);
+        assert_eq!(g.neighbours8(0, 0).count(), 3);
+        assert_eq!(g.neighbours4(1, 1).filter(|(_, c)| **c == '.').count(), 4);
+        assert_eq!(g.neighbours8(1, 1).filter(|(_, c)| **c != '.').count(), 4);
+        assert_eq!(
+            g.neighbours4(2, 2).map(|(pos, _)| pos).collect::<Vec<_>>(),
+            vec![(2, 1), (1, 2)]
+        );
+        assert_eq!(
+            g.ray(0, 0, (1, 0)).map(|(_, c)| c).collect::<String>(),
+            ".#"
+        );
+        assert_eq!(
+            g.ray(0, 0, (1, 1)).collect::<Vec<_>>(),
+            vec![((1, 1), &'.'), ((2, 2), &'#')]
+        );
+        assert_eq!(g.ray(0, 0, (-1, 0)).count(), 0);
+    }
+
+    #[test]
+    fn rotate_and_flip() {
+        let g = grid(&["#..", "L.."]);
+        assert_eq!(g.rotate_clockwise().to_string(), "L#\n..\n..\n");
+        assert_eq!(g.flip_horizontal().to_string(), "..#\n..L\n");
+        assert_eq!(g.flip_vertical().to_string(), "L..\n#..\n");
+        let mut turned = g.clone();
+        for _ in 0..4 {
+            turned = turned.rotate_clockwise();
+        }
+        assert_eq!(turned, g);
+    }
+}
@@ -0,0 +1,44 @@
+//! Solutions for Advent of Code 2020.
+//!
+//! Every day implements the [`Puzzle`] trait and can be created by number with
+//! [`puzzle_factory`], [`days::REGISTRY`] lists all puzzles with their metadata. The modules under
+//! [`days`] also expose the types and helper functions each solution is built from, such as the
+//! Day 8 handheld [`days::day8::Computer`] or the Day 18 expression evaluators.
+//!
+//! ```
+//! use advent_of_code_2020::{puzzle_factory, Puzzle};
+//!
+//! let mut day = puzzle_factory(2020, 1).unwrap();
+//! day.parse_str("1721\n979\n366\n299\n675\n1456\n").unwrap();
+//! assert_eq!(day.part1().unwrap(), "514579");
+//! ```
+//!
+//! Building blocks shared by several days, such as the 2-D [`grid::Grid`], have their own modules.
+//! The remaining modules hold what the command line tool is made of: running and timing days,
+//! benchmarking, verifying answers and writing reports.
+
+pub mod automaton;
+pub mod bench;
+pub mod bigint;
+pub mod cache;
+pub mod cancel;
+pub mod cli;
+pub mod days;
+pub mod generate;
+pub mod graph;
+pub mod grid;
+pub mod json;
+pub mod math;
+pub mod params;
+pub mod parse;
+pub mod pool;
+pub mod progress;
+pub mod puzzle;
+pub mod reference;
+pub mod report;
+pub mod runner;
+pub mod trace;
+pub mod verify;
+
+pub use days::puzzle_factory;
+pub use puzzle::{Puzzle, PuzzleError};
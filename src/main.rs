@@ -1,12 +1,16 @@
 use std::env;
-use std::fs::File;
-use std::io::{self, BufRead};
-use std::path::Path;
+use std::io::{self, IsTerminal};
 use std::process;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-mod days;
-mod puzzle;
+use advent_of_code_2020::cache::{self, Cache};
+use advent_of_code_2020::days::{self, PuzzleId};
+use advent_of_code_2020::params::Params;
+use advent_of_code_2020::pool::Pool;
+use advent_of_code_2020::progress::Progress;
+use advent_of_code_2020::puzzle::Context;
+use advent_of_code_2020::trace::Trace;
+use advent_of_code_2020::{bench, cli, generate, report, runner, verify};
 
 #[macro_export]
 macro_rules! err_exit {
@@ -20,35 +24,241 @@ macro_rules! err_exit {
 macro_rules! usage_exit {
     ($($arg:tt)*) => ({
         std::eprintln!($($arg)*);
-        std::eprintln!("usage: {} day", env::current_exe().unwrap().file_name().unwrap().to_str().unwrap());
+        std::eprintln!("usage: {} [bench [--warmup N] [--runs N|--time SECONDS] [--save FILE] [--compare FILE]|verify [--record]|list|generate [--size N] [--seed N]|cache clear|--help] [--part 1|2] [--format text|json|csv] [--input PATH|-] [--jobs N] [--param NAME=VALUE] [--explain] [--no-cache] [--timeout DURATION] [--year YEAR|all|first-last] day|all|first-last|day,day,...", env::current_exe().unwrap().file_name().unwrap().to_str().unwrap());
         process::exit(1);
     })
 }
 
-fn get_input<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
-where
-    P: AsRef<Path>,
-{
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+fn solve_one(
+    id: PuzzleId,
+    parts: &[u8],
+    input: &runner::Input,
+    context: &Context,
+    cache: Option<&Cache>,
+    timeout: Option<Duration>,
+) {
+    let result = runner::run_day(id, parts, input, context, cache, timeout)
+        .unwrap_or_else(|| err_exit!("No solution found for {}", id));
+    if let Some(e) = result.error() {
+        eprintln!("Day {} of {}: {}", id.day, id.year, e);
+        process::exit(e.exit_code());
+    }
+    for part in &result.parts {
+        let ordinal = if part.part == 1 { "First" } else { "Second" };
+        let mark = if part.cached { " (cached)" } else { "" };
+        println!(
+            "{} answer found: {}{}",
+            ordinal,
+            part.answer.as_ref().unwrap(),
+            mark
+        );
+    }
+    println!("Parse time: {}", runner::format_duration(result.parse_time));
+    for part in &result.parts {
+        let time = if part.cached {
+            "cached".to_string()
+        } else {
+            runner::format_duration(part.elapsed)
+        };
+        println!("Part {} time: {}", part.part, time);
+    }
+    println!("Execution time: {} ms", result.elapsed().as_millis());
+    report::print_traces(&[result]);
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        usage_exit!("Missing mandatory argument 'day'");
-    }
-    let day = args[1]
-        .parse::<u8>()
-        .unwrap_or_else(|e| usage_exit!("Incorrect 'day' argument. {}", e));
-    let input_filename = format!("input/day{}.txt", day);
-    let puzzle =
-        days::puzzle_factory(day).unwrap_or_else(|| err_exit!("No solution found for day {}", day));
-    let lines = get_input(&input_filename);
+// Solve several days and print a table, or the records for all days in a machine-readable format.
+// A missing input file only counts as a failure if a single day was asked for.
+fn solve_many(
+    puzzles: &[PuzzleId],
+    input: &runner::Input,
+    context: &Context,
+    cache: Option<&Cache>,
+    pool: &Pool,
+    options: &cli::Options,
+) {
+    let (parts, format, explain) = (&options.parts, options.format, options.explain);
     let now = Instant::now();
-    let answer = puzzle.solve(lines);
-    let elapsed = now.elapsed().as_millis();
-    println!("First answer found: {}", answer.0);
-    println!("Second answer found: {}", answer.1);
-    println!("Execution time: {} ms", elapsed);
+    let results = runner::run_days(puzzles, parts, input, context, cache, options.timeout, pool);
+    if format == cli::Format::Text {
+        runner::print_table(&results, now.elapsed());
+        report::print_traces(&results);
+    } else {
+        let records = report::solve_records(&results, parts, explain);
+        let columns: &[&str] = if explain {
+            &report::EXPLAIN_COLUMNS
+        } else {
+            &report::SOLVE_COLUMNS
+        };
+        report::print_records(columns, &records, format);
+    }
+    let failure = results
+        .iter()
+        .filter(|result| puzzles.len() == 1 || !result.is_skipped())
+        .find_map(|result| result.error());
+    if let Some(e) = failure {
+        process::exit(e.exit_code());
+    }
+}
+
+// Parse the year and day selections into the registered puzzles. Each explicitly selected day must
+// have a solution in one of the selected years. An explicit input file only makes sense for a
+// single puzzle.
+fn select_puzzles(options: &cli::Options) -> Vec<PuzzleId> {
+    let years = runner::parse_years(&options.years).unwrap_or_else(|e| usage_exit!("{}", e));
+    let days = runner::parse_days(&options.days).unwrap_or_else(|e| usage_exit!("{}", e));
+    let puzzles = runner::select_puzzles(&years, &days);
+    if options.input.is_some() && puzzles.len() > 1 {
+        usage_exit!("Option '--input' needs a single day of a single year");
+    }
+    if options.days != "all" {
+        if let Some(day) = days
+            .iter()
+            .find(|day| !puzzles.iter().any(|id| id.day == **day))
+        {
+            err_exit!("No solution found for day {}", day);
+        }
+    }
+    if puzzles.is_empty() {
+        err_exit!("No solutions found for year {}", options.years);
+    }
+    puzzles
+}
+
+// Check the '--param' overrides against the parameters of the single selected puzzle, and record
+// traces with '--explain'.
+fn context(puzzles: &[PuzzleId], options: &cli::Options) -> Context {
+    let trace = if options.explain {
+        Trace::recording()
+    } else {
+        Trace::default()
+    };
+    if options.params.is_empty() {
+        return Context {
+            trace,
+            ..Context::default()
+        };
+    }
+    if puzzles.len() != 1 {
+        usage_exit!("Option '--param' needs a single day of a single year");
+    }
+    let registration = days::find(puzzles[0]).unwrap();
+    let params = Params::new(registration.params, &options.params)
+        .unwrap_or_else(|e| usage_exit!("{} for {}", e, puzzles[0]));
+    Context {
+        params,
+        trace,
+        ..Context::default()
+    }
+}
+
+// Print the parameters each puzzle accepts, with their defaults.
+fn print_help(puzzles: &[PuzzleId]) {
+    for id in puzzles {
+        let registration = days::find(*id).unwrap();
+        println!("{}: {}", id, registration.info.title);
+        if registration.params.is_empty() {
+            println!("    no parameters");
+        }
+        for param in registration.params {
+            println!("    --param {}", param);
+        }
+    }
+}
+
+// Print a generated input for a single puzzle, and the answers it is known to have to stderr so
+// that the input can be piped into another run.
+fn generate_one(puzzles: &[PuzzleId], parts: &[u8], options: &cli::GenerateOptions) {
+    if puzzles.len() != 1 {
+        usage_exit!("Command 'generate' needs a single day of a single year");
+    }
+    let generated = generate::generate(puzzles[0], options.size, options.seed).unwrap();
+    print!("{}", generated.input);
+    for part in parts {
+        if let Some(answer) = &generated.answers[*part as usize - 1] {
+            eprintln!("Part {} answer: {}", part, answer);
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = cli::parse_args(&args).unwrap_or_else(|e| usage_exit!("{}", e));
+    if options.command == cli::Command::ClearCache {
+        match Cache::new(cache::DIR).clear() {
+            Ok(removed) => println!("Removed {} cached answers", removed),
+            Err(e) => err_exit!("Failed to clear '{}': {}", cache::DIR, e),
+        }
+        return;
+    }
+    let puzzles = select_puzzles(&options);
+    let input = runner::Input::from_arg(options.input.as_ref())
+        .unwrap_or_else(|e| err_exit!("Failed to read stdin: {}", e));
+    let pool = options.jobs.map_or_else(Pool::per_cpu, Pool::new);
+    let context = context(&puzzles, &options);
+    let exit_code = match options.command {
+        cli::Command::Solve => {
+            // Progress is only drawn for a person watching, and never while benchmarking.
+            let progress = if io::stderr().is_terminal() {
+                Progress::enabled()
+            } else {
+                Progress::default()
+            };
+            let context = Context {
+                progress,
+                ..context
+            };
+            // Cached answers come without an explanation.
+            let cache = Cache::new(cache::DIR);
+            let cache = Some(&cache).filter(|_| !options.no_cache && !options.explain);
+            let single = options.days.parse::<u8>().is_ok() && puzzles.len() == 1;
+            if single && options.format == cli::Format::Text {
+                solve_one(
+                    puzzles[0],
+                    &options.parts,
+                    &input,
+                    &context,
+                    cache,
+                    options.timeout,
+                )
+            } else {
+                solve_many(&puzzles, &input, &context, cache, &pool, &options)
+            }
+            return;
+        }
+        cli::Command::Bench => bench::run(
+            &puzzles,
+            &options.parts,
+            &input,
+            &context,
+            options.timeout,
+            &options.bench,
+            options.format,
+        ),
+        cli::Command::Verify => verify::run(
+            &puzzles,
+            &options.parts,
+            &input,
+            options.timeout,
+            &pool,
+            options.record,
+        ),
+        cli::Command::Generate => {
+            generate_one(&puzzles, &options.parts, &options.generate);
+            return;
+        }
+        cli::Command::Help => {
+            print_help(&puzzles);
+            return;
+        }
+        cli::Command::ClearCache => unreachable!(),
+        cli::Command::List => {
+            let records = report::list_records(&puzzles);
+            report::print_records(&report::LIST_COLUMNS, &records, options.format);
+            return;
+        }
+    };
+    match exit_code {
+        Ok(exit_code) => process::exit(exit_code),
+        Err(e) => err_exit!("{}", e),
+    }
 }
@@ -0,0 +1,226 @@
+//! Number theory for the puzzles that are really about modular arithmetic, such as lining up the
+//! Day 13 buses and breaking the Day 25 handshake.
+
+use std::collections::HashMap;
+
+use crate::bigint::BigUint;
+
+/// The greatest common divisor g of 'a' and 'b', with x and y such that a * x + b * y = g.
+pub fn egcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1, 0);
+    let (mut old_y, mut y) = (0, 1);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+    if old_r < 0 {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+pub fn gcd(a: i128, b: i128) -> i128 {
+    egcd(a, b).0
+}
+
+/// The inverse of 'a' modulo 'm', or None if they are not coprime.
+pub fn mod_inverse(a: i128, m: i128) -> Option<i128> {
+    let (g, x, _) = egcd(a.rem_euclid(m), m);
+    if g == 1 {
+        Some(x.rem_euclid(m))
+    } else {
+        None
+    }
+}
+
+// (a * b) mod m without overflowing, for a positive 'm'.
+fn mul_mod(a: i128, b: i128, m: i128) -> i128 {
+    let (mut a, mut b) = (a.rem_euclid(m), b.rem_euclid(m));
+    if let Some(product) = a.checked_mul(b) {
+        return product % m;
+    }
+    // Double and add, keeping every intermediate value below m.
+    let mut result = 0;
+    while b > 0 {
+        if b & 1 == 1 {
+            result = if result >= m - a {
+                result - (m - a)
+            } else {
+                result + a
+            };
+        }
+        a = if a >= m - a { a - (m - a) } else { a + a };
+        b >>= 1;
+    }
+    result
+}
+
+/// Solve the system of congruences x = r (mod m) given as (r, m) pairs, where the moduli must be
+/// positive but need not be coprime. Returns the smallest non-negative solution and the least
+/// common multiple of the moduli, all solutions being x plus a multiple of it. None if there is
+/// no solution or if the least common multiple or any step towards it does not fit in an i128,
+/// see [`crt_big`] for those.
+pub fn crt(congruences: &[(i128, i128)]) -> Option<(i128, i128)> {
+    let (mut x, mut m) = (0, 1);
+    for (r, n) in congruences {
+        let r = r.rem_euclid(*n);
+        let (g, p, _) = egcd(m, *n);
+        if (r - x) % g != 0 {
+            return None;
+        }
+        // x + m * k = r (mod n) gives k = (r - x) / g * p (mod n / g).
+        let lcm = (m / g).checked_mul(*n)?;
+        let k = mul_mod((r - x) / g, p, n / g);
+        x = m.checked_mul(k)?.checked_add(x)?.rem_euclid(lcm);
+        m = lcm;
+    }
+    Some((x, m))
+}
+
+/// [`crt`] for systems whose solution does not fit in an i128. The moduli themselves must still
+/// fit, only the running solution and least common multiple are arbitrary-precision. None if
+/// there is no solution.
+pub fn crt_big(congruences: &[(i128, i128)]) -> Option<(BigUint, BigUint)> {
+    let (mut x, mut m) = (BigUint::zero(), BigUint::from(1));
+    for (r, n) in congruences {
+        let r = r.rem_euclid(*n);
+        let big_n = BigUint::from_u128(*n as u128);
+        // Only m and x modulo n matter for the step, and those fit in an i128 again.
+        let mm = (&m % &big_n).to_u128()? as i128;
+        let xm = (&x % &big_n).to_u128()? as i128;
+        let (g, p, _) = egcd(mm, *n);
+        if (r - xm) % g != 0 {
+            return None;
+        }
+        let k = mul_mod((r - xm) / g, p, n / g);
+        x = &x + &(&m * &BigUint::from_u128(k as u128));
+        m = &m * &BigUint::from_u128((n / g) as u128);
+    }
+    Some((x, m))
+}
+
+/// 'base' to the power 'exp' modulo 'modulus', by repeated squaring.
+pub fn mod_pow(base: u64, mut exp: u64, modulus: u64) -> u64 {
+    let modulus = u128::from(modulus);
+    let mut base = u128::from(base) % modulus;
+    let mut result = 1 % modulus;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * base % modulus;
+        }
+        base = base * base % modulus;
+        exp >>= 1;
+    }
+    result as u64
+}
+
+/// The smallest x with 'base' to the power x equal to 'target' modulo 'modulus', found with the
+/// baby-step giant-step algorithm in about sqrt(modulus) steps. 'base' must be coprime to
+/// 'modulus'. None if there is no such x.
+pub fn discrete_log(base: u64, target: u64, modulus: u64) -> Option<u64> {
+    let target = target % modulus;
+    let steps = (modulus as f64).sqrt().ceil() as u64 + 1;
+
+    // Baby steps: base^j for j < steps, keeping the smallest j for each value.
+    let mut baby_steps = HashMap::new();
+    let mut value = 1 % modulus;
+    for j in 0..steps {
+        baby_steps.entry(value).or_insert(j);
+        value = (u128::from(value) * u128::from(base) % u128::from(modulus)) as u64;
+    }
+
+    // Giant steps: target * base^(-steps * i) for increasing i.
+    let giant = mod_inverse(
+        i128::from(mod_pow(base, steps, modulus)),
+        i128::from(modulus),
+    )? as u64;
+    let mut value = target;
+    for i in 0..steps {
+        if let Some(j) = baby_steps.get(&value) {
+            return Some(i * steps + j);
+        }
+        value = (u128::from(value) * u128::from(giant) % u128::from(modulus)) as u64;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcd_and_inverse() {
+        assert_eq!(egcd(240, 46), (2, -9, 47));
+        assert_eq!(gcd(-12, 18), 6);
+        assert_eq!(mod_inverse(3, 11), Some(4));
+        assert_eq!(mod_inverse(-3, 11), Some(7));
+        assert_eq!(mod_inverse(6, 9), None);
+    }
+
+    #[test]
+    fn chinese_remainder() {
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        // Non-coprime moduli, consistent and inconsistent.
+        assert_eq!(crt(&[(5, 6), (1, 4)]), Some((5, 12)));
+        assert_eq!(crt(&[(0, 4), (1, 6)]), None);
+        assert_eq!(crt(&[]), Some((0, 1)));
+
+        let big = (1i128 << 61) - 1;
+        let (x, m) = crt(&[(-1, big), (3, 1_000_000_007), (-5, 998_244_353)]).unwrap();
+        assert_eq!(m, big * 1_000_000_007 * 998_244_353);
+        assert_eq!(
+            ((x + 1) % big, x % 1_000_000_007, (x + 5) % 998_244_353),
+            (0, 3, 0)
+        );
+        assert_eq!(crt(&[(0, big), (0, big - 2), (0, big - 4)]), None);
+
+        // A least common multiple above 2^126, close to the limit of an i128.
+        let (first, second) = ((1i128 << 64) - 59, (3i128 << 61) + 1);
+        let congruences = [(first - 1, first), (second - 1, second)];
+        let (x, m) = crt(&congruences).unwrap();
+        assert!(m > 1i128 << 126);
+        assert_eq!((x % first, x % second), (first - 1, second - 1));
+        assert_eq!(
+            crt_big(&congruences).map(|(x, m)| (x.to_u128(), m.to_u128())),
+            Some((Some(x as u128), Some(m as u128)))
+        );
+        assert_eq!(crt(&[(1, first), (1, second), (1, 5)]), None);
+
+        assert_eq!(
+            crt_big(&[(2, 3), (3, 5), (2, 7)]),
+            Some((BigUint::from(23), BigUint::from(105)))
+        );
+        assert_eq!(
+            crt_big(&[(5, 6), (1, 4)]),
+            Some((BigUint::from(5), BigUint::from(12)))
+        );
+        assert_eq!(crt_big(&[(0, 4), (1, 6)]), None);
+        let (x, m) = crt_big(&[(1, big), (2, big - 2), (3, big - 4)]).unwrap();
+        assert_eq!(
+            m.to_string(),
+            "12259964326927110819014568368945502097447248019291373553"
+        );
+        for (r, n) in &[(1, big), (2, big - 2), (3, big - 4)] {
+            assert_eq!(
+                (&x % &BigUint::from_u128(*n as u128)).to_u128(),
+                Some(*r as u128)
+            );
+        }
+    }
+
+    #[test]
+    fn power_and_log() {
+        assert_eq!(mod_pow(7, 8, 20201227), 5764801);
+        assert_eq!(mod_pow(2, 0, 1), 0);
+        assert_eq!(mod_pow(u64::MAX, u64::MAX, (1 << 61) - 1), 4747561509943);
+        assert_eq!(discrete_log(7, 5764801, 20201227), Some(8));
+        assert_eq!(discrete_log(7, 17807724, 20201227), Some(11));
+        assert_eq!(discrete_log(7, 1, 20201227), Some(0));
+        // 2 only generates the powers of 2 modulo 7.
+        assert_eq!(discrete_log(2, 3, 7), None);
+    }
+}
@@ -0,0 +1,240 @@
+//! Named puzzle parameters, such as the number of turns of Day 15. A day declares its parameters
+//! as [`Param`] constants and lists them in its registration, the command line can override them
+//! with `--param name=value`, and the solver reads them from the [`Params`] it is given.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The type of a parameter, with its default value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamType {
+    /// A whole number from 'min' to 'max'.
+    Integer {
+        default: i64,
+        min: i64,
+        max: i64,
+    },
+    Text {
+        default: &'static str,
+    },
+}
+
+/// A parameter a puzzle accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Param {
+    pub name: &'static str,
+    pub kind: ParamType,
+    pub help: &'static str,
+}
+
+impl Param {
+    pub const fn integer(name: &'static str, default: i64, min: i64, help: &'static str) -> Self {
+        Param::integer_range(name, default, min, i64::MAX, help)
+    }
+
+    /// An integer parameter that is also bounded from above, such as a size to allocate.
+    pub const fn integer_range(
+        name: &'static str,
+        default: i64,
+        min: i64,
+        max: i64,
+        help: &'static str,
+    ) -> Self {
+        Param {
+            name,
+            kind: ParamType::Integer { default, min, max },
+            help,
+        }
+    }
+
+    pub const fn text(name: &'static str, default: &'static str, help: &'static str) -> Self {
+        Param {
+            name,
+            kind: ParamType::Text { default },
+            help,
+        }
+    }
+
+    // Parse an overriding value for this parameter.
+    fn parse(&self, value: &str) -> Result<Value, String> {
+        match self.kind {
+            ParamType::Integer { min, max, .. } => match value.parse::<i64>() {
+                Ok(n) if n >= min && n <= max => Ok(Value::Integer(n)),
+                Ok(n) if n < min => Err(format!(
+                    "Parameter '{}' must be at least {}",
+                    self.name, min
+                )),
+                Ok(_) => Err(format!("Parameter '{}' must be at most {}", self.name, max)),
+                Err(_) => Err(format!(
+                    "Incorrect value '{}' for parameter '{}', expected a whole number",
+                    value, self.name
+                )),
+            },
+            ParamType::Text { .. } if value.is_empty() => {
+                Err(format!("Parameter '{}' must not be empty", self.name))
+            }
+            ParamType::Text { .. } => Ok(Value::Text(value.to_string())),
+        }
+    }
+}
+
+/// Describes the parameter for '--help', e.g. "preamble=25 (integer, at least 1): ...".
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ParamType::Integer { default, min, max } if max == i64::MAX => write!(
+                f,
+                "{}={} (integer, at least {}): {}",
+                self.name, default, min, self.help
+            ),
+            ParamType::Integer { default, min, max } => write!(
+                f,
+                "{}={} (integer, {} to {}): {}",
+                self.name, default, min, max, self.help
+            ),
+            ParamType::Text { default } => {
+                write!(f, "{}={} (text): {}", self.name, default, self.help)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Text(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Integer(n) => write!(f, "{}", n),
+            Value::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// The overridden parameters of a puzzle, all others have their default value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Params {
+    values: BTreeMap<String, Value>,
+}
+
+impl Params {
+    /// Check the 'overrides' of name and value against the parameters 'params' a puzzle accepts.
+    pub fn new(params: &[Param], overrides: &[(String, String)]) -> Result<Self, String> {
+        let mut values = BTreeMap::new();
+        for (name, value) in overrides {
+            let param = params
+                .iter()
+                .find(|param| param.name == name)
+                .ok_or_else(|| format!("Unknown parameter '{}'", name))?;
+            values.insert(name.clone(), param.parse(value)?);
+        }
+        Ok(Params { values })
+    }
+
+    /// The value of the integer parameter 'param'.
+    pub fn integer(&self, param: &Param) -> i64 {
+        match (self.values.get(param.name), param.kind) {
+            (Some(Value::Integer(n)), _) => *n,
+            (None, ParamType::Integer { default, .. }) => default,
+            _ => panic!("parameter '{}' is not an integer", param.name),
+        }
+    }
+
+    /// The value of the text parameter 'param'.
+    pub fn text<'a>(&'a self, param: &'a Param) -> &'a str {
+        match (self.values.get(param.name), &param.kind) {
+            (Some(Value::Text(text)), _) => text,
+            (None, ParamType::Text { default }) => default,
+            _ => panic!("parameter '{}' is not text", param.name),
+        }
+    }
+
+    /// The overridden parameters in name order.
+    pub fn overrides(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.values.iter().map(|(name, value)| (&name[..], value))
+    }
+}
+
+/// Split a '--param' argument such as "preamble=5" into name and value.
+pub fn parse_override(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!(
+            "Incorrect parameter '{}', expected name=value",
+            arg
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TURNS: Param = Param::integer("turns", 2020, 1, "The number of turns");
+    const BAG: Param = Param::text("bag", "shiny gold", "The color of our bag");
+    const CUPS: Param = Param::integer_range("cups", 9, 5, 100, "The number of cups");
+
+    fn overrides(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn defaults_and_overrides() {
+        let params = Params::default();
+        assert_eq!(params.integer(&TURNS), 2020);
+        assert_eq!(params.text(&BAG), "shiny gold");
+
+        let params = Params::new(&[TURNS, BAG], &overrides(&[("turns", "10")])).unwrap();
+        assert_eq!(params.integer(&TURNS), 10);
+        assert_eq!(params.text(&BAG), "shiny gold");
+        let params = Params::new(&[TURNS, BAG], &overrides(&[("bag", "dark red")])).unwrap();
+        assert_eq!(params.text(&BAG), "dark red");
+        assert_eq!(
+            params.overrides().collect::<Vec<_>>(),
+            vec![("bag", &Value::Text("dark red".to_string()))]
+        );
+    }
+
+    #[test]
+    fn incorrect_overrides() {
+        for pairs in &[
+            [("turns", "0")],
+            [("turns", "many")],
+            [("bag", "")],
+            [("cycles", "6")],
+            [("cups", "4")],
+            [("cups", "101")],
+        ] {
+            assert!(Params::new(&[TURNS, BAG, CUPS], &overrides(pairs)).is_err());
+        }
+        assert!(Params::new(&[CUPS], &overrides(&[("cups", "100")])).is_ok());
+        assert_eq!(
+            parse_override("turns=5"),
+            Ok(("turns".to_string(), "5".to_string()))
+        );
+        assert!(parse_override("turns").is_err());
+        assert!(parse_override("=5").is_err());
+    }
+
+    #[test]
+    fn description() {
+        assert_eq!(
+            TURNS.to_string(),
+            "turns=2020 (integer, at least 1): The number of turns"
+        );
+        assert_eq!(
+            BAG.to_string(),
+            "bag=shiny gold (text): The color of our bag"
+        );
+        assert_eq!(
+            CUPS.to_string(),
+            "cups=9 (integer, 5 to 100): The number of cups"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Helpers for the input formats shared by several days: blank line separated groups, sections
+//! with a header line such as "your ticket:", number lists and `key:value` pairs. All errors
+//! point out the line and column of the offending text, see [`PuzzleError::malformed`].
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::puzzle::{parse_token, PuzzleError};
+
+/// A run of consecutive non-empty lines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Group<'a> {
+    /// 0-based index of the first line of the group in the input.
+    pub start: usize,
+    pub lines: &'a [String],
+}
+
+impl<'a> Group<'a> {
+    /// The lines of the group with their 0-based index in the input.
+    pub fn lines(&self) -> impl Iterator<Item = (usize, &'a str)> + 'a {
+        let start = self.start;
+        self.lines
+            .iter()
+            .enumerate()
+            .map(move |(i, line)| (start + i, &line[..]))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+/// Split 'lines' on blank lines. Several blank lines in a row count as one separator, and leading
+/// or trailing blank lines are ignored.
+pub fn groups(lines: &[String]) -> Vec<Group<'_>> {
+    let mut groups = vec![];
+    let mut start = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if line.is_empty() {
+            if i > start {
+                groups.push(Group {
+                    start,
+                    lines: &lines[start..i],
+                });
+            }
+            start = i + 1;
+        }
+    }
+    if lines.len() > start {
+        groups.push(Group {
+            start,
+            lines: &lines[start..],
+        });
+    }
+    groups
+}
+
+/// A group whose first line may be a header, a line ending with ':'.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Section<'a> {
+    /// The 0-based line index and the text of the header without the ':'.
+    pub header: Option<(usize, &'a str)>,
+    /// The lines after the header.
+    pub body: Group<'a>,
+}
+
+/// Split 'lines' into blank line separated sections, such as the "Player 1:" and "Player 2:"
+/// decks of Day 22.
+pub fn sections(lines: &[String]) -> Vec<Section<'_>> {
+    groups(lines)
+        .into_iter()
+        .map(|group| match group.lines[0].strip_suffix(':') {
+            Some(header) => Section {
+                header: Some((group.start, header)),
+                body: Group {
+                    start: group.start + 1,
+                    lines: &group.lines[1..],
+                },
+            },
+            None => Section {
+                header: None,
+                body: group,
+            },
+        })
+        .collect()
+}
+
+/// Split 'lines' into exactly the sections named by 'headers', in that order, and return their
+/// bodies. An empty header stands for a section without a header line.
+pub fn named_sections<'a>(
+    lines: &'a [String],
+    headers: &[&str],
+) -> Result<Vec<Group<'a>>, PuzzleError> {
+    let sections = sections(lines);
+    let mut bodies = vec![];
+    for (n, expected) in headers.iter().enumerate() {
+        let section = match sections.get(n) {
+            Some(section) => section,
+            None => {
+                let reason = if expected.is_empty() {
+                    "missing section".to_string()
+                } else {
+                    format!("missing section '{}:'", expected)
+                };
+                return Err(PuzzleError::malformed(lines.len(), "", "", &reason));
+            }
+        };
+        match section.header {
+            Some((_, header)) if header == *expected => {}
+            Some((i, header)) => {
+                let reason = if expected.is_empty() {
+                    "unexpected header".to_string()
+                } else {
+                    format!("expected '{}:'", expected)
+                };
+                return Err(PuzzleError::malformed(i, &lines[i], header, &reason));
+            }
+            None if expected.is_empty() => {}
+            None => {
+                let (i, line) = section.body.lines().next().unwrap();
+                return Err(PuzzleError::malformed(
+                    i,
+                    line,
+                    line,
+                    &format!("expected '{}:'", expected),
+                ));
+            }
+        }
+        bodies.push(section.body);
+    }
+    if let Some(section) = sections.get(headers.len()) {
+        let i = section.header.map_or(section.body.start, |(i, _)| i);
+        return Err(PuzzleError::malformed(
+            i,
+            &lines[i],
+            &lines[i],
+            "unexpected section",
+        ));
+    }
+    Ok(bodies)
+}
+
+/// Parse the numbers in 'token', a slice of the input line 'text' at the 0-based line index
+/// 'line', separated by any of the characters in 'separators'. Runs of separators count as one, so
+/// "1, 2" can be parsed with the separators ", ".
+pub fn numbers<T>(
+    line: usize,
+    text: &str,
+    token: &str,
+    separators: &str,
+) -> Result<Vec<T>, PuzzleError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    token
+        .split(|c| separators.contains(c))
+        .filter(|number| !number.is_empty())
+        .map(|number| parse_token(line, text, number))
+        .collect()
+}
+
+/// Split 'token', a slice of the input line 'text' at the 0-based line index 'line', into a key
+/// and a value at the first 'separator'. Both are trimmed.
+pub fn key_value<'a>(
+    line: usize,
+    text: &'a str,
+    token: &'a str,
+    separator: char,
+) -> Result<(&'a str, &'a str), PuzzleError> {
+    let mut parts = token.splitn(2, separator);
+    let key = parts.next().unwrap_or_default().trim();
+    match parts.next() {
+        Some(value) if !key.is_empty() => Ok((key, value.trim())),
+        _ => Err(PuzzleError::malformed(
+            line,
+            text,
+            token,
+            &format!("expected key{}value", separator),
+        )),
+    }
+}
+
+/// Get all key/value pairs of a record spread over the lines of 'group', such as a Day 4
+/// passport. Pairs are separated by whitespace, keys and values by 'separator'.
+pub fn records<'a>(
+    group: &Group<'a>,
+    separator: char,
+) -> Result<Vec<(&'a str, &'a str)>, PuzzleError> {
+    let mut pairs = vec![];
+    for (i, line) in group.lines() {
+        for token in line.split_whitespace() {
+            pairs.push(key_value(i, line, token, separator)?);
+        }
+    }
+    Ok(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    fn position(result: Result<impl fmt::Debug, PuzzleError>) -> (usize, usize) {
+        match result {
+            Err(PuzzleError::Parse { line, column, .. }) => (line, column),
+            other => panic!("Expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn blank_line_groups() {
+        let input = lines(&["", "a", "b", "", "", "c", ""]);
+        let groups = groups(&input);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            groups[0].lines().collect::<Vec<_>>(),
+            vec![(1, "a"), (2, "b")]
+        );
+        assert_eq!(groups[1].start, 5);
+        assert!(super::groups(&[]).is_empty());
+    }
+
+    #[test]
+    fn sections_with_headers() {
+        let input = lines(&["a: 1-3", "", "your ticket:", "7,1", "", "nearby tickets:"]);
+        let sections = sections(&input);
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections[0].header, None);
+        assert_eq!(sections[1].header, Some((2, "your ticket")));
+        assert_eq!(sections[1].body.lines().next(), Some((3, "7,1")));
+        assert!(sections[2].body.is_empty());
+
+        let bodies = named_sections(&input, &["", "your ticket", "nearby tickets"]).unwrap();
+        assert_eq!(bodies[1].start, 3);
+        assert_eq!(position(named_sections(&input, &["", "my ticket"])), (3, 1));
+        assert_eq!(
+            position(named_sections(&input, &["", "your ticket"])),
+            (6, 1)
+        );
+        assert_eq!(position(named_sections(&input, &["x"])), (1, 1));
+        assert_eq!(
+            position(named_sections(&input[..4], &["", "your ticket", "nearby"])),
+            (5, 1)
+        );
+    }
+
+    #[test]
+    fn number_lists() {
+        assert_eq!(
+            numbers::<u32>(0, "1, 2,3", "1, 2,3", ", "),
+            Ok(vec![1, 2, 3])
+        );
+        assert_eq!(numbers::<i32>(0, "-1 2", "-1 2", " "), Ok(vec![-1, 2]));
+        let text = "x: 1-3 or 5-y";
+        assert_eq!(
+            position(numbers::<u32>(3, text, &text[3..], "-or ")),
+            (4, 13)
+        );
+    }
+
+    #[test]
+    fn key_value_pairs() {
+        let input = lines(&["ecl:gry pid:1", "hgt:60in", "", "byr"]);
+        let groups = groups(&input);
+        assert_eq!(
+            records(&groups[0], ':'),
+            Ok(vec![("ecl", "gry"), ("pid", "1"), ("hgt", "60in")])
+        );
+        assert_eq!(position(records(&groups[1], ':')), (4, 1));
+        assert_eq!(key_value(0, "0: 4 1", "0: 4 1", ':'), Ok(("0", "4 1")));
+        assert_eq!(position(key_value(0, " :c", " :c", ':')), (1, 1));
+    }
+}
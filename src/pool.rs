@@ -0,0 +1,102 @@
+//! A bounded pool of worker threads for running independent tasks, such as whole days,
+//! concurrently while keeping their results in order.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pool {
+    jobs: usize,
+}
+
+impl Pool {
+    /// A pool running at most 'jobs' tasks at a time, which must be at least 1.
+    pub fn new(jobs: usize) -> Self {
+        assert!(jobs > 0, "a pool needs at least one job");
+        Pool { jobs }
+    }
+
+    /// A pool running one task per available CPU.
+    pub fn per_cpu() -> Self {
+        Pool::new(thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+
+    pub fn jobs(&self) -> usize {
+        self.jobs
+    }
+
+    /// Run 'task' on each of 'items' and return the results in the order of the items. With a
+    /// single job, or a single item, the tasks run on the calling thread.
+    pub fn map<T, R, F>(&self, items: &[T], task: F) -> Vec<R>
+    where
+        T: Sync,
+        R: Send,
+        F: Fn(&T) -> R + Sync,
+    {
+        let workers = self.jobs.min(items.len());
+        if workers <= 1 {
+            return items.iter().map(task).collect();
+        }
+        // Each worker takes the next item that nobody has taken yet, so a slow task only holds up
+        // its own worker.
+        let next = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                let (next, task, sender) = (&next, &task, sender.clone());
+                scope.spawn(move || loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= items.len() {
+                        break;
+                    }
+                    sender.send((i, task(&items[i]))).unwrap();
+                });
+            }
+        });
+        drop(sender);
+        let mut results: Vec<Option<R>> = items.iter().map(|_| None).collect();
+        for (i, result) in receiver {
+            results[i] = Some(result);
+        }
+        results.into_iter().map(Option::unwrap).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn results_in_order() {
+        let items: Vec<u64> = (0..50).collect();
+        let results = Pool::new(4).map(&items, |n| {
+            // Let later items finish first.
+            thread::sleep(Duration::from_micros(50 - n));
+            n * n
+        });
+        assert_eq!(results, items.iter().map(|n| n * n).collect::<Vec<u64>>());
+        assert!(Pool::new(3).map(&[] as &[u64], |n| *n).is_empty());
+    }
+
+    #[test]
+    fn bounded() {
+        let (running, most) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        Pool::new(3).map(&[(); 20], |_| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            most.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(2));
+            running.fetch_sub(1, Ordering::SeqCst);
+        });
+        let most = most.load(Ordering::SeqCst);
+        assert!(most > 1 && most <= 3, "{} tasks at a time", most);
+    }
+
+    #[test]
+    fn single_job_runs_on_the_caller() {
+        let caller = thread::current().id();
+        let threads = Pool::new(1).map(&[1, 2, 3], |_| thread::current().id());
+        assert!(threads.iter().all(|id| *id == caller));
+    }
+}
@@ -0,0 +1,282 @@
+//! Progress of long-running solvers. A solver reports the work it has done, and the total work if
+//! it is known, to the [`Progress`] in its context. While a part runs, the runner registers a
+//! [`Line`] for it, and a single drawer thread shows all registered lines together on one status
+//! line on stderr, a few times a second, so that days solved side by side do not overwrite each
+//! other.
+//!
+//! The default progress is disabled and ignores every report, so solvers can report from their
+//! inner loops without slowing down benchmarks.
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How often the status line is drawn. Parts finishing sooner never show up on it.
+const INTERVAL: Duration = Duration::from_millis(200);
+
+/// The width the status line is cut to, unless the COLUMNS environment variable says otherwise.
+const WIDTH: usize = 80;
+
+// A total of 0 means that the total is not known.
+#[derive(Debug, Default)]
+struct Counts {
+    done: AtomicUsize,
+    total: AtomicUsize,
+}
+
+// The lines being shown, and the thread drawing them while there are any.
+#[derive(Debug, Default)]
+struct Board {
+    state: Mutex<BoardState>,
+}
+
+#[derive(Debug, Default)]
+struct BoardState {
+    next_id: usize,
+    lines: Vec<(usize, String, Instant, Progress)>,
+    drawer: Option<(Sender<()>, JoinHandle<()>)>,
+}
+
+impl Board {
+    fn add(board: &Arc<Board>, label: String, progress: Progress) -> usize {
+        let mut state = board.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.lines.push((id, label, Instant::now(), progress));
+        if state.drawer.is_none() {
+            let (done, finished) = mpsc::channel::<()>();
+            let board = Arc::clone(board);
+            // Dropping the sender stops the drawing.
+            let drawer = thread::spawn(move || {
+                let mut drawn = false;
+                while finished.recv_timeout(INTERVAL) == Err(RecvTimeoutError::Timeout) {
+                    let status = board.status(width());
+                    if !status.is_empty() || drawn {
+                        eprint!("\r\x1b[K{}", status);
+                        let _ = io::stderr().flush();
+                        drawn = true;
+                    }
+                }
+                if drawn {
+                    eprint!("\r\x1b[K");
+                }
+            });
+            state.drawer = Some((done, drawer));
+        }
+        id
+    }
+
+    fn remove(&self, id: usize) {
+        let drawer = {
+            let mut state = self.state.lock().unwrap();
+            state.lines.retain(|line| line.0 != id);
+            if state.lines.is_empty() {
+                state.drawer.take()
+            } else {
+                None
+            }
+        };
+        // The drawer locks the state too, so it is stopped after the lock is released.
+        if let Some((done, drawer)) = drawer {
+            drop(done);
+            let _ = drawer.join();
+        }
+    }
+
+    // The lines that have been running for at least an interval, cut to 'width' characters.
+    fn status(&self, width: usize) -> String {
+        let state = self.state.lock().unwrap();
+        let lines: Vec<String> = state
+            .lines
+            .iter()
+            .filter(|(_, _, start, _)| start.elapsed() >= INTERVAL)
+            .filter_map(|(_, label, _, progress)| {
+                let (done, total) = progress.get()?;
+                Some(format_line(label, done, total))
+            })
+            .collect();
+        lines
+            .join(" | ")
+            .chars()
+            .take(width.saturating_sub(1))
+            .collect()
+    }
+}
+
+fn width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .unwrap_or(WIDTH)
+}
+
+#[derive(Debug, Clone)]
+struct Shared {
+    counts: Arc<Counts>,
+    board: Arc<Board>,
+}
+
+/// Where a solver reports its progress. Clones report to the same place.
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    shared: Option<Shared>,
+}
+
+impl Progress {
+    /// A progress that keeps the reports, the default one does not.
+    pub fn enabled() -> Self {
+        Progress {
+            shared: Some(Shared {
+                counts: Arc::default(),
+                board: Arc::default(),
+            }),
+        }
+    }
+
+    /// A progress with reports of its own, shown on the same status line as this one. Disabled if
+    /// this one is.
+    pub fn child(&self) -> Self {
+        Progress {
+            shared: self.shared.as_ref().map(|shared| Shared {
+                counts: Arc::default(),
+                board: Arc::clone(&shared.board),
+            }),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.shared.is_some()
+    }
+
+    /// 'done' units of work out of 'total', if known.
+    pub fn update(&self, done: usize, total: Option<usize>) {
+        if let Some(shared) = &self.shared {
+            shared.counts.done.store(done, Ordering::Relaxed);
+            shared
+                .counts
+                .total
+                .store(total.unwrap_or(0), Ordering::Relaxed);
+        }
+    }
+
+    /// Add 'work' units to the work done, for solvers that do not count it themselves.
+    pub fn advance(&self, work: usize) {
+        if let Some(shared) = &self.shared {
+            shared.counts.done.fetch_add(work, Ordering::Relaxed);
+        }
+    }
+
+    /// The work done and the total work, None if the progress is disabled.
+    pub fn get(&self) -> Option<(usize, Option<usize>)> {
+        let counts = &self.shared.as_ref()?.counts;
+        let total = counts.total.load(Ordering::Relaxed);
+        Some((
+            counts.done.load(Ordering::Relaxed),
+            Some(total).filter(|total| *total > 0),
+        ))
+    }
+
+    /// Start over, and show the progress after 'label' on the status line until the returned line
+    /// is dropped. Nothing is shown if the progress is disabled.
+    pub fn line(&self, label: String) -> Line {
+        self.update(0, None);
+        let id = self
+            .shared
+            .as_ref()
+            .map(|shared| Board::add(&shared.board, label, self.clone()));
+        Line {
+            board: self.shared.as_ref().map(|shared| Arc::clone(&shared.board)),
+            id: id.unwrap_or(0),
+        }
+    }
+}
+
+/// A line on the status line, see [`Progress::line`]. Dropping it removes it, and the status line
+/// is erased once no lines are left.
+pub struct Line {
+    board: Option<Arc<Board>>,
+    id: usize,
+}
+
+impl Drop for Line {
+    fn drop(&mut self) {
+        if let Some(board) = &self.board {
+            board.remove(self.id);
+        }
+    }
+}
+
+/// The text of a progress line, such as "2020 day 15 part 2: 45% (13500000/30000000)".
+pub fn format_line(label: &str, done: usize, total: Option<usize>) -> String {
+    match total {
+        Some(total) => format!(
+            "{}: {}% ({}/{})",
+            label,
+            (done as f64 / total as f64 * 100.0).min(100.0) as u32,
+            done,
+            total
+        ),
+        None => format!("{}: {}", label, done),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports() {
+        let progress = Progress::enabled();
+        progress.clone().update(10, Some(40));
+        assert_eq!(progress.get(), Some((10, Some(40))));
+        progress.advance(5);
+        assert_eq!(progress.get(), Some((15, Some(40))));
+        progress.update(3, None);
+        assert_eq!(progress.get(), Some((3, None)));
+
+        let disabled = Progress::default();
+        disabled.update(10, Some(40));
+        disabled.advance(1);
+        assert_eq!(disabled.get(), None);
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(
+            format_line("2020 day 15 part 2", 13500000, Some(30000000)),
+            "2020 day 15 part 2: 45% (13500000/30000000)"
+        );
+        assert_eq!(
+            format_line("2020 day 20 part 1", 812, None),
+            "2020 day 20 part 1: 812"
+        );
+    }
+
+    #[test]
+    fn concurrent_lines() {
+        let progress = Progress::enabled();
+        let (first, second) = (progress.child(), progress.child());
+        let board = Arc::clone(&progress.shared.as_ref().unwrap().board);
+        let first_line = first.line("day 15".to_string());
+        let second_line = second.line("day 20".to_string());
+        first.update(1, Some(4));
+        second.advance(7);
+        // New lines wait an interval before they are shown.
+        assert_eq!(board.status(80), "");
+        thread::sleep(INTERVAL);
+        // Both lines share the one status line, which is cut to the width.
+        assert_eq!(board.status(80), "day 15: 25% (1/4) | day 20: 7");
+        assert_eq!(board.status(10), "day 15: 2");
+        drop(first_line);
+        assert_eq!(board.status(80), "day 20: 7");
+        assert!(board.state.lock().unwrap().drawer.is_some());
+        drop(second_line);
+        assert!(board.state.lock().unwrap().drawer.is_none());
+
+        let disabled = Progress::default();
+        drop(disabled.child().line("day 1".to_string()));
+    }
+}
@@ -1,6 +1,228 @@
-pub use std::fs::File;
+use std::error::Error;
+use std::fmt;
 pub use std::io::{self, BufRead};
+use std::str::FromStr;
 
+use crate::cancel::Cancel;
+use crate::params::Params;
+use crate::progress::Progress;
+use crate::trace::Trace;
+
+#[derive(Debug)]
+pub enum PuzzleError {
+    /// The input could not be opened or read.
+    Io(io::Error),
+    /// A line of the input could not be parsed. Line and column are 1-based.
+    Parse {
+        line: usize,
+        column: usize,
+        text: String,
+        reason: String,
+    },
+    /// The input was well-formed but the puzzle has no answer for it.
+    NoSolution(String),
+    /// The solver was cancelled because it ran out of time.
+    Timeout,
+}
+
+impl PuzzleError {
+    /// Create a parse error for 'token', which should be a slice of the input line 'text' found at
+    /// the 0-based line index 'line'. The column is derived from the position of 'token' in 'text'.
+    pub fn malformed(line: usize, text: &str, token: &str, reason: &str) -> Self {
+        let start = text.as_ptr() as usize;
+        let pos = token.as_ptr() as usize;
+        let column = if pos >= start && pos <= start + text.len() {
+            pos - start + 1
+        } else {
+            1
+        };
+        PuzzleError::Parse {
+            line: line + 1,
+            column,
+            text: token.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn no_solution(reason: &str) -> Self {
+        PuzzleError::NoSolution(reason.to_string())
+    }
+
+    /// Process exit code used by the runner, one per error kind.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            PuzzleError::Io(_) => 2,
+            PuzzleError::Parse { .. } => 3,
+            PuzzleError::NoSolution(_) => 4,
+            PuzzleError::Timeout => 7,
+        }
+    }
+}
+
+impl fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PuzzleError::Io(e) => write!(f, "Failed to read input: {}", e),
+            PuzzleError::Parse {
+                line,
+                column,
+                text,
+                reason,
+            } => write!(
+                f,
+                "Malformed input at line {}, column {}: {} ('{}')",
+                line, column, reason, text
+            ),
+            PuzzleError::NoSolution(reason) => write!(f, "No solution found: {}", reason),
+            PuzzleError::Timeout => write!(f, "Timed out"),
+        }
+    }
+}
+
+impl Error for PuzzleError {}
+
+// I/O errors are compared by kind only, which is enough for the unit tests.
+impl PartialEq for PuzzleError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (PuzzleError::Io(a), PuzzleError::Io(b)) => a.kind() == b.kind(),
+            (
+                PuzzleError::Parse {
+                    line,
+                    column,
+                    text,
+                    reason,
+                },
+                PuzzleError::Parse {
+                    line: line2,
+                    column: column2,
+                    text: text2,
+                    reason: reason2,
+                },
+            ) => line == line2 && column == column2 && text == text2 && reason == reason2,
+            (PuzzleError::NoSolution(a), PuzzleError::NoSolution(b)) => a == b,
+            (PuzzleError::Timeout, PuzzleError::Timeout) => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<io::Error> for PuzzleError {
+    fn from(e: io::Error) -> Self {
+        PuzzleError::Io(e)
+    }
+}
+
+/// Read all input lines, turning a read failure into an error.
+pub fn read_lines(input: &mut dyn BufRead) -> Result<Vec<String>, PuzzleError> {
+    let mut lines = vec![];
+    for line in input.lines() {
+        lines.push(line?);
+    }
+    Ok(lines)
+}
+
+/// Get the line of an input that should be a single line, a trailing empty line is allowed.
+pub fn single_line(lines: &[String]) -> Result<&str, PuzzleError> {
+    let mut non_empty = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty());
+    match (lines.first(), non_empty.nth(1)) {
+        (None, _) => Err(PuzzleError::Parse {
+            line: 1,
+            column: 1,
+            text: String::new(),
+            reason: "missing line".to_string(),
+        }),
+        (Some(_), Some((i, line))) => Err(PuzzleError::malformed(
+            i,
+            line,
+            line,
+            "expected a single line",
+        )),
+        (Some(line), None) => Ok(line),
+    }
+}
+
+/// Parse 'token', a slice of the input line 'text' at the 0-based line index 'line'.
+pub fn parse_token<T>(line: usize, text: &str, token: &str) -> Result<T, PuzzleError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    token
+        .parse::<T>()
+        .map_err(|e| PuzzleError::malformed(line, text, token, &e.to_string()))
+}
+
+/// Get the next token from 'tokens', reporting the end of the line 'text' if there are none left.
+pub fn next_token<'a, I>(tokens: &mut I, line: usize, text: &'a str) -> Result<&'a str, PuzzleError>
+where
+    I: Iterator<Item = &'a str>,
+{
+    tokens
+        .next()
+        .ok_or_else(|| PuzzleError::malformed(line, text, &text[text.len()..], "missing token"))
+}
+
+/// What a puzzle is solved with besides its input.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    /// Overrides of the parameters the puzzle declares.
+    pub params: Params,
+    /// Where to record the steps to the answers, for '--explain'.
+    pub trace: Trace,
+    /// Checked by long-running solvers, which stop with PuzzleError::Timeout once it is cancelled.
+    pub cancel: Cancel,
+    /// Where long-running solvers report how far they got, drawn on stderr by the runner.
+    pub progress: Progress,
+}
+
+/// A puzzle is solved in three steps: the input is parsed once into the puzzle's own state, after
+/// which each part can be solved (and timed) on its own.
 pub trait Puzzle {
-    fn solve(&self, lines: io::Result<io::Lines<io::BufReader<File>>>) -> (String, String);
+    fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError>;
+    fn part1(&self) -> Result<String, PuzzleError>;
+    fn part2(&self) -> Result<String, PuzzleError>;
+
+    // Parse an input held in memory, such as a puzzle example.
+    fn parse_str(&mut self, input: &str) -> Result<(), PuzzleError> {
+        self.parse(&mut input.as_bytes())
+    }
+
+    /// Use 'context' instead of the default one, before parsing. Puzzles without parameters or
+    /// explanations can ignore it.
+    fn set_context(&mut self, _context: Context) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_column() {
+        let text = "acc +1x";
+        match parse_token::<i64>(4, text, &text[4..]) {
+            Err(PuzzleError::Parse {
+                line, column, text, ..
+            }) => {
+                assert_eq!(line, 5);
+                assert_eq!(column, 5);
+                assert_eq!(text, "+1x");
+            }
+            _ => panic!("Expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn missing_token() {
+        let text = "nop";
+        let mut tokens = text.split(' ');
+        assert_eq!(next_token(&mut tokens, 0, text).unwrap(), "nop");
+        match next_token(&mut tokens, 0, text) {
+            Err(PuzzleError::Parse { column, .. }) => assert_eq!(column, 4),
+            _ => panic!("Expected a parse error"),
+        }
+    }
 }
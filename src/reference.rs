@@ -0,0 +1,241 @@
+//! Differential testing of the solvers. A day can implement [`Reference`], a slow but obviously
+//! correct solver, and register it with `reference: true`. [`check`] then runs both solvers on
+//! small generated inputs and shrinks any input they disagree on to a minimal one.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::days::{PuzzleId, Registration};
+use crate::generate::{Generated, Rng};
+use crate::puzzle::{Puzzle, PuzzleError};
+
+/// An answer, or the error found instead.
+pub type Answer = Result<String, PuzzleError>;
+
+/// A reference solver, working on the input parsed by the day's [`Puzzle`] implementation.
+pub trait Reference: Puzzle {
+    /// The answer to 'part' found the simple way, or None if the part is out of reach of a simple
+    /// solver and is not checked.
+    fn reference(&self, part: u8) -> Option<Answer>;
+}
+
+/// An input on which a solver and its reference solver disagree.
+#[derive(Debug, PartialEq)]
+pub struct Mismatch {
+    pub id: PuzzleId,
+    /// The seed of the generated input the mismatch was found with.
+    pub seed: u64,
+    pub part: u8,
+    /// The generated input after shrinking.
+    pub input: String,
+    pub expected: Answer,
+    pub found: Answer,
+}
+
+fn describe(answer: &Answer) -> String {
+    match answer {
+        Ok(answer) => answer.clone(),
+        Err(e) => format!("error '{}'", e),
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} part {} with seed {}: the reference solver gives {}, the solver gives {}",
+            self.id,
+            self.part,
+            self.seed,
+            describe(&self.expected),
+            describe(&self.found)
+        )?;
+        write!(f, "minimized input:\n{}", self.input)
+    }
+}
+
+// The first part the solvers disagree on for 'input', with the expected and the found answer.
+// Inputs that do not parse are not compared.
+fn compare(create: fn() -> Box<dyn Reference>, input: &str) -> Option<(u8, Answer, Answer)> {
+    let mut puzzle = create();
+    puzzle.parse_str(input).ok()?;
+    for part in 1..=2 {
+        if let Some(expected) = puzzle.reference(part) {
+            let found = if part == 1 {
+                puzzle.part1()
+            } else {
+                puzzle.part2()
+            };
+            if found != expected {
+                return Some((part, expected, found));
+            }
+        }
+    }
+    None
+}
+
+// Remove as many items as possible while 'fails' still holds, first in large chunks and then in
+// smaller ones down to single items, until no single item can be removed.
+fn shrink_items<T: Clone>(mut items: Vec<T>, fails: &dyn Fn(&[T]) -> bool) -> Vec<T> {
+    let mut chunk = items.len() / 2;
+    while chunk > 0 {
+        let mut removed = false;
+        let mut start = 0;
+        while start < items.len() {
+            let end = (start + chunk).min(items.len());
+            let candidate: Vec<T> = [&items[..start], &items[end..]].concat();
+            if fails(&candidate) {
+                items = candidate;
+                removed = true;
+            } else {
+                start = end;
+            }
+        }
+        // Removing an item can make another one removable again.
+        if chunk > 1 || !removed {
+            chunk /= 2;
+        }
+    }
+    items
+}
+
+fn join(lines: &[String]) -> String {
+    let mut input = lines.join("\n");
+    input.push('\n');
+    input
+}
+
+/// Shrink 'input' for which 'fails' holds to a smaller input for which it still holds, by
+/// removing lines and then characters from the remaining lines.
+pub fn shrink(input: &str, fails: &dyn Fn(&str) -> bool) -> String {
+    let lines: Vec<String> = input.lines().map(|line| line.to_string()).collect();
+    let mut lines = shrink_items(lines, &|lines| fails(&join(lines)));
+    for i in 0..lines.len() {
+        let chars: Vec<char> = lines[i].chars().collect();
+        let chars = shrink_items(chars, &|chars| {
+            let mut candidate = lines.clone();
+            candidate[i] = chars.iter().collect();
+            fails(&join(&candidate))
+        });
+        lines[i] = chars.into_iter().collect();
+    }
+    join(&lines)
+}
+
+/// Compare the solver made by 'create' with its reference solver on the inputs 'generate' makes
+/// with 'size' and each of 'seeds'. The first mismatch is shrunk and returned, None if they always
+/// agree.
+pub fn check_with(
+    id: PuzzleId,
+    create: fn() -> Box<dyn Reference>,
+    generate: fn(usize, &mut Rng) -> Generated,
+    size: usize,
+    seeds: Range<u64>,
+) -> Option<Mismatch> {
+    for seed in seeds {
+        let input = generate(size, &mut Rng::new(seed)).input;
+        if let Some((part, _, _)) = compare(create, &input) {
+            let input = shrink(
+                &input,
+                &|input| matches!(compare(create, input), Some((found, _, _)) if found == part),
+            );
+            let (part, expected, found) = compare(create, &input).unwrap();
+            return Some(Mismatch {
+                id,
+                seed,
+                part,
+                input,
+                expected,
+                found,
+            });
+        }
+    }
+    None
+}
+
+/// Compare the solver of 'registration' with its reference solver, see [`check_with`]. Days
+/// without a reference solver always pass.
+pub fn check(registration: &Registration, size: usize, seeds: Range<u64>) -> Option<Mismatch> {
+    let create = registration.reference?;
+    check_with(
+        registration.info.id(),
+        create,
+        registration.generate,
+        size,
+        seeds,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::days;
+    use crate::puzzle::{parse_token, read_lines, BufRead};
+
+    // A sum of numbers with a solver that forgets the numbers above 90.
+    #[derive(Default)]
+    struct Sum {
+        numbers: Vec<u64>,
+    }
+
+    impl Puzzle for Sum {
+        fn parse(&mut self, input: &mut dyn BufRead) -> Result<(), PuzzleError> {
+            for (i, line) in read_lines(input)?.iter().enumerate() {
+                self.numbers.push(parse_token(i, line, line)?);
+            }
+            Ok(())
+        }
+
+        fn part1(&self) -> Result<String, PuzzleError> {
+            let sum: u64 = self.numbers.iter().filter(|n| **n <= 90).sum();
+            Ok(sum.to_string())
+        }
+
+        fn part2(&self) -> Result<String, PuzzleError> {
+            Ok(self.numbers.len().to_string())
+        }
+    }
+
+    impl Reference for Sum {
+        fn reference(&self, part: u8) -> Option<Answer> {
+            match part {
+                1 => Some(Ok(self.numbers.iter().sum::<u64>().to_string())),
+                _ => Some(Ok(self.numbers.len().to_string())),
+            }
+        }
+    }
+
+    fn numbers(size: usize, rng: &mut Rng) -> Generated {
+        Generated::from_lines((0..size).map(|_| rng.below(100).to_string()).collect())
+    }
+
+    #[test]
+    fn mismatches_are_shrunk() {
+        let id = PuzzleId::new(2020, 0);
+        let mismatch = check_with(id, || Box::new(Sum::default()), numbers, 20, 0..10).unwrap();
+        assert_eq!(mismatch.part, 1);
+        assert_eq!(mismatch.input.lines().count(), 1);
+        let number: u64 = mismatch.input.trim().parse().unwrap();
+        assert!(number > 90);
+        assert_eq!(mismatch.expected, Ok(number.to_string()));
+        assert_eq!(mismatch.found, Ok("0".to_string()));
+        // The failing seed gives the same input again.
+        let input = numbers(20, &mut Rng::new(mismatch.seed)).input;
+        assert!(input.lines().any(|line| line == mismatch.input.trim()));
+    }
+
+    #[test]
+    fn shrinking_characters() {
+        let shrunk = shrink("abc\nxyz\nbxb\n", &|input| input.contains('b'));
+        assert_eq!(shrunk, "b\n");
+    }
+
+    #[test]
+    fn solvers_match_references() {
+        for registration in days::REGISTRY {
+            if let Some(mismatch) = check(registration, 10, 0..30) {
+                panic!("{}", mismatch);
+            }
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use crate::cli::Format;
+use crate::days::{self, PuzzleId};
+use crate::json::Value;
+use crate::puzzle::PuzzleError;
+use crate::runner::{self, DayResult};
+use crate::trace::{Detail, Step};
+
+/// Columns of the machine-readable output for solved days, one record per day and part. Times are
+/// in nanoseconds, 'status' is "ok", "error", "timeout" or "skipped" and 'cached' tells whether the
+/// answer came from the cache.
+pub const SOLVE_COLUMNS: [&str; 10] = [
+    "year", "day", "part", "status", "answer", "error", "parse_ns", "time_ns", "input", "cached",
+];
+
+/// The columns of the machine-readable output with '--explain', SOLVE_COLUMNS followed by the
+/// steps recorded for the part, see trace_value().
+pub const EXPLAIN_COLUMNS: [&str; 11] = [
+    "year", "day", "part", "status", "answer", "error", "parse_ns", "time_ns", "input", "cached",
+    "trace",
+];
+
+/// Columns of the puzzle list, one record per registered puzzle.
+pub const LIST_COLUMNS: [&str; 5] = ["year", "day", "title", "input", "reads_input"];
+
+/// Print 'records', each holding one value per column, as a JSON array of objects, as CSV with a
+/// header line or as a plain table.
+pub fn print_records(columns: &[&str], records: &[Vec<Value>], format: Format) {
+    match format {
+        Format::Json => {
+            let objects = records
+                .iter()
+                .map(|record| {
+                    let members = columns.iter().map(|column| column.to_string());
+                    Value::Object(members.zip(record.iter().cloned()).collect())
+                })
+                .collect();
+            println!("{}", Value::Array(objects));
+        }
+        Format::Csv => {
+            println!("{}", columns.join(","));
+            for record in records {
+                let fields: Vec<String> = record.iter().map(csv_field).collect();
+                println!("{}", fields.join(","));
+            }
+        }
+        Format::Text => {
+            let mut rows = vec![columns.iter().map(|column| column.to_string()).collect()];
+            for record in records {
+                rows.push(record.iter().map(text_field).collect());
+            }
+            // Text columns read better left aligned, numbers right aligned.
+            let left_aligned: Vec<usize> = (0..columns.len())
+                .filter(|i| {
+                    records
+                        .iter()
+                        .any(|record| matches!(record[*i], Value::Str(_)))
+                })
+                .collect();
+            runner::print_rows(&rows, &left_aligned);
+        }
+    }
+}
+
+fn text_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Str(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+// Fields with a comma, quote or line break are quoted, with any quotes doubled.
+fn csv_field(value: &Value) -> String {
+    let field = text_field(value);
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+/// One record per selected puzzle, see LIST_COLUMNS.
+pub fn list_records(puzzles: &[PuzzleId]) -> Vec<Vec<Value>> {
+    puzzles
+        .iter()
+        .filter_map(|id| days::find(*id))
+        .map(|registration| {
+            let info = &registration.info;
+            vec![
+                Value::Int(info.year as i64),
+                Value::Int(info.day as i64),
+                Value::from(info.title),
+                Value::from(info.input.name()),
+                Value::from(info.reads_input),
+            ]
+        })
+        .collect()
+}
+
+pub fn nanos(duration: std::time::Duration) -> Value {
+    Value::Int(duration.as_nanos() as i64)
+}
+
+/// One record per selected part of each day, see SOLVE_COLUMNS, or EXPLAIN_COLUMNS if 'explain'
+/// is set.
+pub fn solve_records(results: &[DayResult], parts: &[u8], explain: bool) -> Vec<Vec<Value>> {
+    let mut records = vec![];
+    for result in results {
+        for part in parts {
+            let (status, answer, error, time) = match (&result.parsed, result.part(*part)) {
+                _ if result.is_skipped() => ("skipped", None, None, Value::Null),
+                (Err(e), _) => ("error", None, Some(e.to_string()), Value::Null),
+                (Ok(()), Some(part)) => match &part.answer {
+                    Ok(answer) => ("ok", Some(answer.clone()), None, nanos(part.elapsed)),
+                    Err(e @ PuzzleError::Timeout) => {
+                        ("timeout", None, Some(e.to_string()), nanos(part.elapsed))
+                    }
+                    Err(e) => ("error", None, Some(e.to_string()), nanos(part.elapsed)),
+                },
+                (Ok(()), None) => continue,
+            };
+            let parse_time = if result.is_skipped() {
+                Value::Null
+            } else {
+                nanos(result.parse_time)
+            };
+            records.push(vec![
+                Value::Int(result.id.year as i64),
+                Value::Int(result.id.day as i64),
+                Value::Int(*part as i64),
+                Value::from(status),
+                Value::from(answer),
+                Value::from(error),
+                parse_time,
+                time,
+                Value::from(result.input.clone()),
+                Value::from(result.part(*part).is_some_and(|part| part.cached)),
+            ]);
+            if explain {
+                let steps = result.part(*part).map_or(&[][..], |part| &part.trace[..]);
+                records.last_mut().unwrap().push(trace_value(steps));
+            }
+        }
+    }
+    records
+}
+
+/// The steps of a trace as a JSON array of objects, each with a 'label' and either a 'value', the
+/// 'columns' and 'rows' of a table or the 'rows' of a grid.
+pub fn trace_value(steps: &[Step]) -> Value {
+    let rows = |rows: &[Vec<Value>]| {
+        Value::Array(rows.iter().map(|row| Value::Array(row.clone())).collect())
+    };
+    let objects = steps
+        .iter()
+        .map(|step| {
+            let mut members = vec![("label".to_string(), Value::from(&step.label[..]))];
+            match &step.detail {
+                Detail::Value(value) => members.push(("value".to_string(), value.clone())),
+                Detail::Table {
+                    columns,
+                    rows: values,
+                } => {
+                    let columns = columns.iter().map(|c| Value::from(&c[..])).collect();
+                    members.push(("columns".to_string(), Value::Array(columns)));
+                    members.push(("rows".to_string(), rows(values)));
+                }
+                Detail::Grid(values) => members.push(("rows".to_string(), rows(values))),
+            }
+            Value::Object(members)
+        })
+        .collect();
+    Value::Array(objects)
+}
+
+/// The steps of a trace as indented text: a value follows its label, tables and grids go below it.
+pub fn trace_lines(steps: &[Step]) -> Vec<String> {
+    let mut lines = vec![];
+    for step in steps {
+        match &step.detail {
+            Detail::Value(value) => lines.push(format!("  {}: {}", step.label, text_field(value))),
+            Detail::Table { columns, rows } => {
+                lines.push(format!("  {}:", step.label));
+                let mut table = vec![columns.clone()];
+                table.extend(rows.iter().map(|row| row.iter().map(text_field).collect()));
+                let left_aligned: Vec<usize> = (0..columns.len())
+                    .filter(|i| rows.iter().any(|row| matches!(row[*i], Value::Str(_))))
+                    .collect();
+                for line in runner::format_rows(&table, &left_aligned) {
+                    lines.push(format!("    {}", line));
+                }
+            }
+            Detail::Grid(rows) => {
+                lines.push(format!("  {}:", step.label));
+                let cells: Vec<Vec<String>> = rows
+                    .iter()
+                    .map(|row| row.iter().map(text_field).collect())
+                    .collect();
+                let width = cells.iter().flatten().map(|c| c.chars().count()).max();
+                for row in &cells {
+                    let row: Vec<String> = row
+                        .iter()
+                        .map(|cell| format!("{:>w$}", cell, w = width.unwrap_or(0)))
+                        .collect();
+                    lines.push(format!("    {}", row.join(" ")));
+                }
+            }
+        }
+    }
+    lines
+}
+
+/// Print the steps recorded for each solved part of 'results' under a heading naming the day and
+/// part. Parts without steps are left out.
+pub fn print_traces(results: &[DayResult]) {
+    for result in results {
+        for part in result.parts.iter().filter(|part| !part.trace.is_empty()) {
+            println!("{} part {}:", result.id, part.part);
+            for line in trace_lines(&part.trace) {
+                println!("{}", line);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_quoting() {
+        assert_eq!(
+            csv_field(&Value::from("hkflr,ctmcqjf")),
+            "\"hkflr,ctmcqjf\""
+        );
+        assert_eq!(csv_field(&Value::from("say \"hi\"")), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field(&Value::from("1007104")), "1007104");
+        assert_eq!(csv_field(&Value::Int(42)), "42");
+        assert_eq!(csv_field(&Value::Null), "");
+    }
+
+    #[test]
+    fn traces() {
+        let steps = vec![
+            Step {
+                label: "invalid number".to_string(),
+                detail: Detail::Value(Value::Int(127)),
+            },
+            Step {
+                label: "fields".to_string(),
+                detail: Detail::Table {
+                    columns: vec!["position".to_string(), "field".to_string()],
+                    rows: vec![
+                        vec![Value::Int(0), Value::from("row")],
+                        vec![Value::Int(10), Value::from("class")],
+                    ],
+                },
+            },
+            Step {
+                label: "tiles".to_string(),
+                detail: Detail::Grid(vec![
+                    vec![Value::Int(1951), Value::Int(311)],
+                    vec![Value::Int(2729), Value::Int(1427)],
+                ]),
+            },
+        ];
+        assert_eq!(
+            trace_lines(&steps),
+            vec![
+                "  invalid number: 127",
+                "  fields:",
+                "    position | field",
+                "    ---------+------",
+                "           0 | row",
+                "          10 | class",
+                "  tiles:",
+                "    1951  311",
+                "    2729 1427",
+            ]
+        );
+        assert_eq!(
+            trace_value(&steps[..2]).to_string(),
+            "[{\"label\":\"invalid number\",\"value\":127},\
+             {\"label\":\"fields\",\"columns\":[\"position\",\"field\"],\
+             \"rows\":[[0,\"row\"],[10,\"class\"]]}]"
+        );
+    }
+}
@@ -0,0 +1,543 @@
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::cache::{Cache, Key};
+use crate::cancel::Cancel;
+use crate::days::{self, PuzzleId, Registration};
+use crate::pool::Pool;
+use crate::puzzle::{Context, PuzzleError};
+use crate::trace::{Step, Trace};
+
+pub struct PartResult {
+    pub part: u8,
+    pub answer: Result<String, PuzzleError>,
+    pub elapsed: Duration,
+    /// The steps recorded while solving the part, and while parsing for the first part solved.
+    /// Only recorded when explaining.
+    pub trace: Vec<Step>,
+    /// Whether the answer was found in the cache rather than solved.
+    pub cached: bool,
+}
+
+pub struct DayResult {
+    pub id: PuzzleId,
+    /// Path of the input, "-" for stdin.
+    pub input: String,
+    pub parsed: Result<(), PuzzleError>,
+    pub parse_time: Duration,
+    /// Empty if the input could not be parsed.
+    pub parts: Vec<PartResult>,
+}
+
+impl DayResult {
+    /// A day without an input file is reported as skipped rather than failed.
+    pub fn is_skipped(&self) -> bool {
+        match &self.parsed {
+            Err(PuzzleError::Io(e)) => e.kind() == io::ErrorKind::NotFound,
+            _ => false,
+        }
+    }
+
+    /// The first error of the day, from parsing or from one of the parts.
+    pub fn error(&self) -> Option<&PuzzleError> {
+        match &self.parsed {
+            Err(e) => Some(e),
+            Ok(()) => self
+                .parts
+                .iter()
+                .find_map(|part| part.answer.as_ref().err()),
+        }
+    }
+
+    pub fn part(&self, part: u8) -> Option<&PartResult> {
+        self.parts.iter().find(|result| result.part == part)
+    }
+
+    /// Time spent parsing and solving the parts that were run.
+    pub fn elapsed(&self) -> Duration {
+        self.parts
+            .iter()
+            .fold(self.parse_time, |total, part| total + part.elapsed)
+    }
+}
+
+/// The year whose inputs may also be found directly in the input directory, the layout used before
+/// there were several years.
+pub const FLAT_LAYOUT_YEAR: u16 = 2020;
+
+/// The input file of a puzzle, input/<year>/dayN.txt. For FLAT_LAYOUT_YEAR this falls back to
+/// input/dayN.txt if there is no input in the year directory.
+pub fn input_filename(id: PuzzleId) -> String {
+    let path = format!("input/{}/day{}.txt", id.year, id.day);
+    if id.year == FLAT_LAYOUT_YEAR && !Path::new(&path).exists() {
+        return format!("input/day{}.txt", id.day);
+    }
+    path
+}
+
+/// Where to read the input of a day from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    /// The day's own input file, see input_filename().
+    Default,
+    Path(String),
+    /// The contents of stdin, read once up front so a day can be run several times.
+    Stdin(String),
+}
+
+impl Input {
+    /// Create an input from a '--input' argument, where "-" means stdin.
+    pub fn from_arg(arg: Option<&String>) -> io::Result<Input> {
+        match arg.map(|arg| &arg[..]) {
+            None => Ok(Input::Default),
+            Some("-") => {
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text)?;
+                Ok(Input::Stdin(text))
+            }
+            Some(path) => Ok(Input::Path(path.to_string())),
+        }
+    }
+
+    pub fn path(&self, id: PuzzleId) -> String {
+        match self {
+            Input::Default => input_filename(id),
+            Input::Path(path) => path.clone(),
+            Input::Stdin(_) => "-".to_string(),
+        }
+    }
+}
+
+/// Parse a day selection such as "all", "7", "1-10", "3,7,20" or a mix like "1-5,9".
+pub fn parse_days(arg: &str) -> Result<Vec<u8>, String> {
+    if arg == "all" {
+        return Ok((1..=25).collect());
+    }
+    parse_ranges(arg, "day")?
+        .into_iter()
+        .map(|day| u8::try_from(day).map_err(|_| format!("Incorrect day '{}'", day)))
+        .collect()
+}
+
+/// Parse a year selection such as "all", "2020" or "2020-2022". "all" selects the years with at
+/// least one registered puzzle.
+pub fn parse_years(arg: &str) -> Result<Vec<u16>, String> {
+    if arg == "all" {
+        return Ok(days::years());
+    }
+    parse_ranges(arg, "year")
+}
+
+// Parse a comma separated list of numbers and ranges of numbers, dropping duplicates. 'what' names
+// the kind of number for error messages.
+fn parse_ranges(arg: &str, what: &str) -> Result<Vec<u16>, String> {
+    let parse = |number: &str| {
+        number
+            .trim()
+            .parse::<u16>()
+            .map_err(|e| format!("Incorrect {} '{}'. {}", what, number, e))
+    };
+    let mut selected = vec![];
+    for part in arg.split(',') {
+        let mut range = part.splitn(2, '-');
+        let first = parse(range.next().unwrap_or_default())?;
+        let last = match range.next() {
+            Some(last) => parse(last)?,
+            None => first,
+        };
+        if first > last {
+            return Err(format!("Empty {} range '{}'", what, part));
+        }
+        for number in first..=last {
+            if !selected.contains(&number) {
+                selected.push(number);
+            }
+        }
+    }
+    Ok(selected)
+}
+
+/// The registered puzzles for the selected 'years' and 'days', ordered by year and then in the
+/// order of 'days'.
+pub fn select_puzzles(years: &[u16], days: &[u8]) -> Vec<PuzzleId> {
+    let mut years = years.to_vec();
+    years.sort_unstable();
+    let mut selected = vec![];
+    for year in years {
+        for day in days {
+            let id = PuzzleId::new(year, *day);
+            if days::find(id).is_some() {
+                selected.push(id);
+            }
+        }
+    }
+    selected
+}
+
+// Read the input of the puzzle 'id'. Days that do not read their input get an empty one.
+fn read_input<'a>(
+    id: PuzzleId,
+    registration: &Registration,
+    input: &'a Input,
+) -> Result<Cow<'a, str>, PuzzleError> {
+    match input {
+        Input::Stdin(text) => Ok(Cow::Borrowed(text)),
+        Input::Default if !registration.info.reads_input => Ok(Cow::Borrowed("")),
+        _ => match fs::read_to_string(input.path(id)) {
+            Ok(text) => Ok(Cow::Owned(text)),
+            // Name the missing or unreadable file in the error.
+            Err(e) => Err(PuzzleError::Io(io::Error::new(
+                e.kind(),
+                format!("{}: {}", input.path(id), e),
+            ))),
+        },
+    }
+}
+
+/// Parse the input of the puzzle 'id' and solve the selected parts with 'context', timing each
+/// step separately. Answers found in 'cache' are not solved again, and the input is not even
+/// parsed if all of them are found. New answers are added to the cache. A part still running after
+/// 'timeout' is cancelled and fails with PuzzleError::Timeout. If the progress of 'context' is
+/// enabled, the progress of each part is drawn on stderr while it runs.
+pub fn run_day(
+    id: PuzzleId,
+    parts: &[u8],
+    input: &Input,
+    context: &Context,
+    cache: Option<&Cache>,
+    timeout: Option<Duration>,
+) -> Option<DayResult> {
+    let registration = days::find(id)?;
+    let mut puzzle = (registration.create)();
+    // Every day records into its own trace.
+    let trace = if context.trace.is_recording() {
+        Trace::recording()
+    } else {
+        Trace::default()
+    };
+    let cancel = if timeout.is_some() {
+        Cancel::new()
+    } else {
+        Cancel::default()
+    };
+    // Every day reports its own progress, all shown on one status line.
+    let progress = context.progress.child();
+    puzzle.set_context(Context {
+        trace: trace.clone(),
+        cancel: cancel.clone(),
+        progress: progress.clone(),
+        ..context.clone()
+    });
+    let text = read_input(id, registration, input);
+    let mut keys = vec![];
+    let mut cached = vec![None; parts.len()];
+    if let (Some(cache), Ok(text)) = (cache, &text) {
+        for part in parts {
+            keys.push(Key::new(
+                id,
+                *part,
+                registration.version,
+                text,
+                &context.params,
+            ));
+        }
+        cached = keys.iter().map(|key| cache.get(key)).collect();
+    }
+    // Reading the input and looking up the cache do not count as parsing.
+    let now = Instant::now();
+    let parsed = match text {
+        Ok(_) if cached.iter().all(Option::is_some) => Ok(()),
+        Ok(text) => puzzle.parse_str(&text),
+        Err(e) => Err(e),
+    };
+    let parse_time = now.elapsed();
+    let mut results = vec![];
+    if parsed.is_ok() {
+        for (i, part) in parts.iter().enumerate() {
+            if let Some(answer) = cached[i].take() {
+                results.push(PartResult {
+                    part: *part,
+                    answer: Ok(answer),
+                    elapsed: Duration::ZERO,
+                    trace: vec![],
+                    cached: true,
+                });
+                continue;
+            }
+            let deadline = timeout.map(|timeout| cancel.after(timeout));
+            let line = progress.line(format!("{} part {}", id, part));
+            let now = Instant::now();
+            let answer = if *part == 1 {
+                puzzle.part1()
+            } else {
+                puzzle.part2()
+            };
+            let elapsed = now.elapsed();
+            drop(deadline);
+            drop(line);
+            // The cache only saves time, failing to write to it is not an error.
+            if let (Some(cache), Ok(answer)) = (cache, &answer) {
+                let _ = cache.put(&keys[i], answer);
+            }
+            results.push(PartResult {
+                part: *part,
+                answer,
+                elapsed,
+                trace: trace.take(),
+                cached: false,
+            });
+        }
+    }
+    Some(DayResult {
+        id,
+        input: input.path(id),
+        parsed,
+        parse_time,
+        parts: results,
+    })
+}
+
+/// Run several days on the worker 'pool', see run_day(). The results are in the order of
+/// 'puzzles', and every day is timed on its own, not counting the time it waited for a worker.
+pub fn run_days(
+    puzzles: &[PuzzleId],
+    parts: &[u8],
+    input: &Input,
+    context: &Context,
+    cache: Option<&Cache>,
+    timeout: Option<Duration>,
+    pool: &Pool,
+) -> Vec<DayResult> {
+    pool.map(puzzles, |id| {
+        run_day(*id, parts, input, context, cache, timeout).unwrap()
+    })
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let micros = duration.as_micros();
+    if micros < 1000 {
+        format!("{} us", micros)
+    } else if micros < 1_000_000 {
+        format!("{:.1} ms", micros as f64 / 1000.0)
+    } else {
+        format!("{:.2} s", micros as f64 / 1_000_000.0)
+    }
+}
+
+/// Label of a puzzle in the day column of a table. The year is only included if the table has
+/// puzzles of several years, e.g. "2021/7".
+pub fn day_label(id: PuzzleId, several_years: bool) -> String {
+    if several_years {
+        format!("{}/{}", id.year, id.day)
+    } else {
+        id.day.to_string()
+    }
+}
+
+/// True if 'ids' are not all from the same year.
+pub fn several_years(ids: &[PuzzleId]) -> bool {
+    ids.iter().any(|id| id.year != ids[0].year)
+}
+
+/// Print 'rows' as aligned columns, with a separator line below the first (header) row. Columns
+/// listed in 'left_aligned' are left aligned, all others right aligned.
+pub fn print_rows(rows: &[Vec<String>], left_aligned: &[usize]) {
+    for line in format_rows(rows, left_aligned) {
+        println!("{}", line);
+    }
+}
+
+/// The lines print_rows() prints.
+pub fn format_rows(rows: &[Vec<String>], left_aligned: &[usize]) -> Vec<String> {
+    let mut lines = vec![];
+    let mut widths = vec![0; rows[0].len()];
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = std::cmp::max(widths[i], cell.chars().count());
+        }
+    }
+    for (i, row) in rows.iter().enumerate() {
+        let cells: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(column, cell)| {
+                if left_aligned.contains(&column) {
+                    format!("{:<w$}", cell, w = widths[column])
+                } else {
+                    format!("{:>w$}", cell, w = widths[column])
+                }
+            })
+            .collect();
+        lines.push(cells.join(" | ").trim_end().to_string());
+        if i == 0 {
+            let separator: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+            lines.push(separator.join("-+-"));
+        }
+    }
+    lines
+}
+
+/// The rows of the summary table: a header, then one row per day with both answers, the parse
+/// time, the time of each part and the day's total.
+pub fn table_rows(results: &[DayResult]) -> Vec<Vec<String>> {
+    let mut rows = vec![vec![
+        "Day".to_string(),
+        "Part 1".to_string(),
+        "Part 2".to_string(),
+        "Parse".to_string(),
+        "Time 1".to_string(),
+        "Time 2".to_string(),
+        "Total".to_string(),
+    ]];
+    let ids: Vec<PuzzleId> = results.iter().map(|result| result.id).collect();
+    for result in results {
+        let mut row = vec![day_label(result.id, several_years(&ids))];
+        match &result.parsed {
+            Ok(()) => {
+                let mut times = vec![format_duration(result.parse_time)];
+                for part in 1..=2 {
+                    let (answer, time) = match result.part(part) {
+                        Some(PartResult {
+                            answer: Ok(answer),
+                            cached: true,
+                            ..
+                        }) => (answer.clone(), "cached".to_string()),
+                        Some(PartResult {
+                            answer: Ok(answer),
+                            elapsed,
+                            ..
+                        }) => (answer.clone(), format_duration(*elapsed)),
+                        Some(PartResult {
+                            answer: Err(PuzzleError::Timeout),
+                            elapsed,
+                            ..
+                        }) => ("TIMEOUT".to_string(), format_duration(*elapsed)),
+                        Some(PartResult { answer: Err(e), .. }) => {
+                            (format!("error: {}", e), String::new())
+                        }
+                        None => (String::new(), String::new()),
+                    };
+                    row.push(answer);
+                    times.push(time);
+                }
+                row.append(&mut times);
+                row.push(format_duration(result.elapsed()));
+            }
+            Err(_) if result.is_skipped() => row.push("skipped".to_string()),
+            Err(e) => row.push(format!("error: {}", e)),
+        }
+        row.resize(rows[0].len(), String::new());
+        rows.push(row);
+    }
+    rows
+}
+
+/// Print one aligned row per day with the answers and the time spent on each step, followed by the
+/// total runtime.
+pub fn print_table(results: &[DayResult], total: Duration) {
+    // Answers are left aligned, the day and the times right aligned.
+    print_rows(&table_rows(results), &[1, 2]);
+    println!("Total execution time: {}", format_duration(total));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_days_selection() {
+        assert_eq!(parse_days("7"), Ok(vec![7]));
+        assert_eq!(parse_days("1-3"), Ok(vec![1, 2, 3]));
+        assert_eq!(parse_days("3,7,20"), Ok(vec![3, 7, 20]));
+        assert_eq!(parse_days("1-2,2,5"), Ok(vec![1, 2, 5]));
+        assert_eq!(parse_days("all").unwrap().len(), 25);
+        assert!(parse_days("3-1").is_err());
+        assert!(parse_days("x").is_err());
+        assert!(parse_days("300").is_err());
+    }
+
+    #[test]
+    fn year_selection() {
+        assert_eq!(parse_years("2020-2022,2020"), Ok(vec![2020, 2021, 2022]));
+        assert!(parse_years("all").unwrap().contains(&2020));
+        assert!(parse_years("2022-2020").is_err());
+
+        let puzzles = select_puzzles(&[2019, 2020], &[25, 3, 26]);
+        assert_eq!(
+            puzzles,
+            vec![PuzzleId::new(2020, 25), PuzzleId::new(2020, 3)]
+        );
+        let ids = [PuzzleId::new(2020, 1), PuzzleId::new(2021, 7)];
+        assert!(several_years(&ids));
+        assert_eq!(day_label(ids[1], true), "2021/7");
+        assert_eq!(day_label(ids[1], false), "7");
+    }
+
+    #[test]
+    fn summary_rows() {
+        let part = |part, answer: &str, millis| PartResult {
+            part,
+            answer: Ok(answer.to_string()),
+            elapsed: Duration::from_millis(millis),
+            trace: vec![],
+            cached: false,
+        };
+        let solved = DayResult {
+            id: PuzzleId::new(2020, 15),
+            input: input_filename(PuzzleId::new(2020, 15)),
+            parsed: Ok(()),
+            parse_time: Duration::from_micros(20),
+            parts: vec![part(1, "436", 2), part(2, "175594", 3000)],
+        };
+        let skipped = DayResult {
+            id: PuzzleId::new(2020, 16),
+            input: input_filename(PuzzleId::new(2020, 16)),
+            parsed: Err(PuzzleError::Io(io::ErrorKind::NotFound.into())),
+            parse_time: Duration::ZERO,
+            parts: vec![],
+        };
+        let rows = table_rows(&[solved, skipped]);
+        assert_eq!(
+            rows[0],
+            ["Day", "Part 1", "Part 2", "Parse", "Time 1", "Time 2", "Total"]
+        );
+        // Each part is timed on its own.
+        assert_eq!(
+            rows[1],
+            ["15", "436", "175594", "20 us", "2.0 ms", "3.00 s", "3.00 s"]
+        );
+        assert_eq!(rows[2], ["16", "skipped", "", "", "", "", ""]);
+    }
+
+    #[test]
+    fn timed_out_part() {
+        // Part 2 plays 30 million turns, far longer than the timeout.
+        let input = Input::Stdin("0,3,6\n".to_string());
+        let timeout = Some(Duration::from_millis(10));
+        let context = Context::default();
+        let result = run_day(
+            PuzzleId::new(2020, 15),
+            &[1, 2],
+            &input,
+            &context,
+            None,
+            timeout,
+        );
+        let parts = result.unwrap().parts;
+        assert_eq!(parts[0].answer.as_ref().ok(), Some(&"436".to_string()));
+        assert_eq!(parts[1].answer.as_ref().err(), Some(&PuzzleError::Timeout));
+    }
+
+    #[test]
+    fn input_layout() {
+        assert_eq!(input_filename(PuzzleId::new(2020, 1)), "input/day1.txt");
+        assert_eq!(
+            input_filename(PuzzleId::new(2021, 1)),
+            "input/2021/day1.txt"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+//! Explanations of how a puzzle got to its answers, for `--explain`. A solver records the
+//! intermediate results worth checking, such as a resolved mapping or the index of a patched
+//! instruction, as [`Step`]s in the [`Trace`] of its context. The runner collects them per part and
+//! the report module renders them as text or JSON.
+//!
+//! A trace that is not recording ignores everything, so solvers can record unconditionally and
+//! only need [`Trace::is_recording`] to skip building large tables.
+
+use std::sync::{Arc, Mutex};
+
+use crate::json::Value;
+
+/// What a step shows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Detail {
+    Value(Value),
+    /// Rows of values under a header.
+    Table {
+        columns: Vec<String>,
+        rows: Vec<Vec<Value>>,
+    },
+    /// Rows of values without a header, laid out as they are, such as tiles in an image.
+    Grid(Vec<Vec<Value>>),
+}
+
+/// An intermediate result, with a label saying what it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub label: String,
+    pub detail: Detail,
+}
+
+/// Where a solver records its steps. Clones record into the same list.
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    steps: Option<Arc<Mutex<Vec<Step>>>>,
+}
+
+impl Trace {
+    /// A trace that records steps, the default one does not.
+    pub fn recording() -> Self {
+        Trace {
+            steps: Some(Arc::new(Mutex::new(vec![]))),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.steps.is_some()
+    }
+
+    fn record(&self, label: &str, detail: Detail) {
+        if let Some(steps) = &self.steps {
+            steps.lock().unwrap().push(Step {
+                label: label.to_string(),
+                detail,
+            });
+        }
+    }
+
+    pub fn value<T: Into<Value>>(&self, label: &str, value: T) {
+        if self.is_recording() {
+            self.record(label, Detail::Value(value.into()));
+        }
+    }
+
+    pub fn table(&self, label: &str, columns: &[&str], rows: Vec<Vec<Value>>) {
+        let columns = columns.iter().map(|column| column.to_string()).collect();
+        self.record(label, Detail::Table { columns, rows });
+    }
+
+    pub fn grid(&self, label: &str, rows: Vec<Vec<Value>>) {
+        self.record(label, Detail::Grid(rows));
+    }
+
+    /// Remove and return the steps recorded so far.
+    pub fn take(&self) -> Vec<Step> {
+        match &self.steps {
+            Some(steps) => std::mem::take(&mut *steps.lock().unwrap()),
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recording() {
+        let trace = Trace::recording();
+        trace.clone().value("invalid number", 127i64);
+        trace.table(
+            "fields",
+            &["position", "field"],
+            vec![vec![Value::Int(0), Value::from("row")]],
+        );
+        let steps = trace.take();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].label, "invalid number");
+        assert_eq!(steps[0].detail, Detail::Value(Value::Int(127)));
+        assert!(trace.take().is_empty());
+    }
+
+    #[test]
+    fn not_recording() {
+        let trace = Trace::default();
+        assert!(!trace.is_recording());
+        trace.value("invalid number", 127i64);
+        trace.grid("tiles", vec![vec![Value::Int(1)]]);
+        assert!(trace.take().is_empty());
+    }
+}
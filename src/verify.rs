@@ -0,0 +1,182 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::time::Duration;
+
+use crate::days::PuzzleId;
+use crate::pool::Pool;
+use crate::puzzle::{Context, PuzzleError};
+use crate::runner::{self, Input};
+
+/// The answers we got the stars for, one "year day part answer" line per part.
+pub const MANIFEST: &str = "input/answers.txt";
+
+/// Exit code used when an answer does not match the manifest.
+pub const FAIL_EXIT_CODE: i32 = 6;
+
+pub type Answers = BTreeMap<(PuzzleId, u8), String>;
+
+#[derive(Debug, PartialEq)]
+pub enum Status {
+    Pass,
+    Fail,
+    Missing,
+}
+
+pub fn parse_answers(text: &str) -> Result<Answers, String> {
+    let mut answers = Answers::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = || format!("Malformed answers at line {}: '{}'", i + 1, line);
+        let mut fields = line.splitn(4, ' ');
+        let year = fields.next().and_then(|f| f.parse::<u16>().ok());
+        let day = fields.next().and_then(|f| f.parse::<u8>().ok());
+        let part = fields.next().and_then(|f| f.parse::<u8>().ok());
+        match (year, day, part, fields.next()) {
+            (Some(year), Some(day), Some(part @ 1..=2), Some(answer)) => {
+                answers.insert((PuzzleId::new(year, day), part), answer.to_string());
+            }
+            _ => return Err(error()),
+        }
+    }
+    Ok(answers)
+}
+
+pub fn format_answers(answers: &Answers) -> String {
+    let mut text = "# year day part answer\n".to_string();
+    for ((id, part), answer) in answers {
+        text += &format!("{} {} {} {}\n", id.year, id.day, part, answer);
+    }
+    text
+}
+
+// A manifest that does not exist yet has no answers.
+fn load_answers(path: &str) -> Result<Answers, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse_answers(&text),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Answers::new()),
+        Err(e) => Err(format!("Failed to read '{}': {}", path, e)),
+    }
+}
+
+/// 'answer' is None if solving the part failed.
+pub fn check(expected: Option<&String>, answer: Option<&String>) -> Status {
+    match (expected, answer) {
+        (None, _) => Status::Missing,
+        (Some(expected), Some(answer)) if expected == answer => Status::Pass,
+        _ => Status::Fail,
+    }
+}
+
+/// Solve the selected days and compare the answers with the manifest, or write them to the
+/// manifest if 'record' is set. Returns the exit code.
+pub fn run(
+    puzzles: &[PuzzleId],
+    parts: &[u8],
+    input: &Input,
+    timeout: Option<Duration>,
+    pool: &Pool,
+    record: bool,
+) -> Result<i32, String> {
+    let mut answers = load_answers(MANIFEST)?;
+    let mut rows = vec![vec![
+        "Day".to_string(),
+        "Part".to_string(),
+        "Status".to_string(),
+        "Answer".to_string(),
+        "Expected".to_string(),
+    ]];
+    let (mut nof_passed, mut nof_failed, mut nof_missing) = (0, 0, 0);
+    // Cached answers would hide a solver that broke without a new version, so all days are solved.
+    for result in runner::run_days(
+        puzzles,
+        parts,
+        input,
+        &Context::default(),
+        None,
+        timeout,
+        pool,
+    ) {
+        let id = &result.id;
+        for part in parts {
+            let expected = answers.get(&(*id, *part)).cloned();
+            let answer: Result<String, String> = match (&result.parsed, result.part(*part)) {
+                _ if result.is_skipped() => Err("no input file".to_string()),
+                (Err(e), _) => Err(format!("error: {}", e)),
+                (Ok(()), Some(part)) => part.answer.as_ref().cloned().map_err(|e| match e {
+                    PuzzleError::Timeout => "TIMEOUT".to_string(),
+                    e => format!("error: {}", e),
+                }),
+                (Ok(()), None) => continue,
+            };
+            let status = if result.is_skipped() {
+                Status::Missing
+            } else {
+                check(expected.as_ref(), answer.as_ref().ok())
+            };
+            let status = match status {
+                Status::Pass => {
+                    nof_passed += 1;
+                    "PASS"
+                }
+                Status::Fail => {
+                    nof_failed += 1;
+                    "FAIL"
+                }
+                Status::Missing => {
+                    nof_missing += 1;
+                    "MISSING"
+                }
+            };
+            if let (true, Ok(answer)) = (record, &answer) {
+                answers.insert((*id, *part), answer.clone());
+            }
+            rows.push(vec![
+                runner::day_label(*id, runner::several_years(puzzles)),
+                part.to_string(),
+                status.to_string(),
+                answer.unwrap_or_else(|e| e),
+                expected.unwrap_or_default(),
+            ]);
+        }
+    }
+    runner::print_rows(&rows, &[2, 3, 4]);
+    println!(
+        "{} passed, {} failed, {} missing",
+        nof_passed, nof_failed, nof_missing
+    );
+
+    if record {
+        fs::write(MANIFEST, format_answers(&answers))
+            .map_err(|e| format!("Failed to write '{}': {}", MANIFEST, e))?;
+        println!("Answers recorded in {}", MANIFEST);
+        return Ok(0);
+    }
+    Ok(if nof_failed > 0 { FAIL_EXIT_CODE } else { 0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answers_manifest() {
+        let text = "# year day part answer\n2020 1 1 1007104\n2020 21 2 hkflr,ctmcqjf\n";
+        let answers = parse_answers(text).unwrap();
+        assert_eq!(
+            answers.get(&(PuzzleId::new(2020, 21), 2)),
+            Some(&"hkflr,ctmcqjf".to_string())
+        );
+        assert_eq!(format_answers(&answers), text);
+        assert!(parse_answers("2020 1 3 42").is_err());
+        assert!(parse_answers("2020 1 1").is_err());
+
+        let expected = answers.get(&(PuzzleId::new(2020, 1), 1));
+        assert_eq!(check(expected, Some(&"1007104".to_string())), Status::Pass);
+        assert_eq!(check(expected, Some(&"1007105".to_string())), Status::Fail);
+        assert_eq!(check(expected, None), Status::Fail);
+        assert_eq!(check(None, Some(&"1".to_string())), Status::Missing);
+    }
+}
@@ -0,0 +1,56 @@
+use advent_of_code_2020::bigint::BigUint;
+use advent_of_code_2020::days::{day18, day8};
+use advent_of_code_2020::json::Value;
+use advent_of_code_2020::{puzzle_factory, runner, PuzzleError};
+
+#[test]
+fn solve_from_str() {
+    let mut day = puzzle_factory(2020, 1).unwrap();
+    day.parse_str("1721\n979\n366\n299\n675\n1456\n").unwrap();
+    assert_eq!(day.part1(), Ok("514579".to_string()));
+    assert_eq!(day.part2(), Ok("241861950".to_string()));
+    assert!(puzzle_factory(2020, 26).is_none());
+}
+
+#[test]
+fn parse_error() {
+    let mut day = puzzle_factory(2020, 8).unwrap();
+    match day.parse_str("nop +0\nmul +2\n") {
+        Err(e @ PuzzleError::Parse { .. }) => assert_eq!(e.exit_code(), 3),
+        _ => panic!("Expected a parse error"),
+    }
+}
+
+#[test]
+fn handheld_computer() {
+    let input: Vec<String> =
+        "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6"
+            .lines()
+            .map(|line| line.to_string())
+            .collect();
+    let program = day8::get_program(&input).unwrap();
+    assert_eq!(
+        day8::Computer::new(program).run(),
+        day8::ExitStatus::Stuck(5)
+    );
+}
+
+#[test]
+fn expression_evaluators() {
+    let expr = "2 * 3 + (4 * 5)";
+    assert_eq!(
+        day18::calc_left_to_right(0, expr, expr),
+        Ok(BigUint::from(26))
+    );
+    assert_eq!(
+        day18::calc_add_before_mul(0, expr, expr),
+        Ok(BigUint::from(46))
+    );
+}
+
+#[test]
+fn day_selection_and_json() {
+    assert_eq!(runner::parse_days("1-3,7"), Ok(vec![1, 2, 3, 7]));
+    let value = Value::Object(vec![("day".to_string(), Value::from(7i64))]);
+    assert_eq!(value.to_string(), "{\"day\":7}");
+}